@@ -0,0 +1,48 @@
+// Benchmarks each agent's choose_action over positions from random games.
+use acquire::agent::create_seeded_agent;
+use acquire::game::{default_chain_names, GameState};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+// Difficulty levels understood by create_seeded_agent.
+const DIFFICULTIES: [usize; 2] = [0, 1];
+
+fn sample_positions(num_games: u64) -> Vec<GameState> {
+    let mut positions = Vec::new();
+    for seed in 0..num_games {
+        let ai = create_seeded_agent(0, seed);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut game = GameState::new(4, &mut rng, default_chain_names()).unwrap();
+        loop {
+            positions.push(game.clone());
+            if game.take_turn(ai.choose_action(&game)).unwrap() {
+                break;
+            }
+        }
+    }
+    positions
+}
+
+fn bench_choose_action(c: &mut Criterion) {
+    let positions = sample_positions(10);
+    let mut group = c.benchmark_group("choose_action");
+    for difficulty in DIFFICULTIES {
+        let agent = create_seeded_agent(difficulty, 0);
+        let mut idx = 0;
+        group.bench_with_input(
+            BenchmarkId::from_parameter(difficulty),
+            &positions,
+            |b, positions| {
+                b.iter(|| {
+                    idx = (idx + 1) % positions.len();
+                    agent.choose_action(black_box(&positions[idx]))
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_choose_action);
+criterion_main!(benches);
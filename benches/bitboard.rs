@@ -0,0 +1,19 @@
+// Neighbor lookups on the occupancy bitboard.
+use acquire::game::{default_chain_names, GameState, Tile, NUM_TILES};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+fn bench_neighbors(c: &mut Criterion) {
+    let game = GameState::from_seed(4, 3, default_chain_names()).unwrap();
+    let board = &game.board;
+    c.bench_function("num_neighbors/all_tiles", |b| {
+        b.iter(|| {
+            (0..NUM_TILES)
+                .filter_map(Tile::from_index)
+                .map(|t| board.num_neighbors(black_box(t)))
+                .sum::<usize>()
+        })
+    });
+}
+
+criterion_group!(benches, bench_neighbors);
+criterion_main!(benches);
@@ -0,0 +1,52 @@
+// Compares listing each chain's tracked cells against scanning the whole grid
+// for them. See benches/merge.rs for the merger relabeling comparison.
+use acquire::agent::create_seeded_agent;
+use acquire::game::{default_chain_names, GameState, Tile, MAX_NUM_CHAINS, NUM_TILES};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+// Plays seeded random games until one's board has a few sizable chains.
+fn mid_game() -> GameState {
+    for seed in 0.. {
+        let ai = create_seeded_agent(1, seed);
+        let mut game = GameState::from_seed(4, seed, default_chain_names()).unwrap();
+        while !game.take_turn(ai.choose_action(&game)).unwrap() {
+            if (0..MAX_NUM_CHAINS)
+                .filter(|&i| game.board.chain_size(i) >= 4)
+                .count()
+                >= 3
+            {
+                return game;
+            }
+        }
+    }
+    unreachable!()
+}
+
+fn bench_chain_cells(c: &mut Criterion) {
+    let game = mid_game();
+    let board = &game.board;
+    let mut group = c.benchmark_group("chain_cells");
+    group.bench_function("scan", |b| {
+        b.iter(|| {
+            (0..MAX_NUM_CHAINS)
+                .map(|i| {
+                    (0..NUM_TILES)
+                        .filter_map(Tile::from_index)
+                        .filter(|&t| black_box(board).chain_at(t) == Some(i))
+                        .count()
+                })
+                .sum::<usize>()
+        })
+    });
+    group.bench_function("list", |b| {
+        b.iter(|| {
+            (0..MAX_NUM_CHAINS)
+                .map(|i| black_box(board).chain_cells(i).count())
+                .sum::<usize>()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_chain_cells);
+criterion_main!(benches);
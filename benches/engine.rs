@@ -0,0 +1,135 @@
+// Benchmarks for the rules engine: setup, cloning, each phase of take_turn,
+// bonus calculation, and whole random playouts at each player count.
+//
+// To compare against another revision, run
+//   cargo bench --bench engine -- --save-baseline before
+// there first, and then this benchmark with `-- --baseline before`.
+use acquire::agent::create_seeded_agent;
+use acquire::game::{
+    calculate_bonuses, default_chain_names, GameState, TurnAction, TurnPhase, MAX_NUM_CHAINS,
+};
+use criterion::{
+    black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput,
+};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+const PHASE_NAMES: [&str; 6] = [
+    "place_tile",
+    "create_chain",
+    "pick_winning_chain",
+    "distribute_bonuses",
+    "resolve_merger",
+    "buy_stock",
+];
+
+fn new_game(num_players: usize, seed: u64) -> GameState {
+    GameState::from_seed(num_players, seed, default_chain_names()).unwrap()
+}
+
+fn phase_index(phase: &TurnPhase) -> Option<usize> {
+    match phase {
+        TurnPhase::PlaceTile(_) => Some(0),
+        TurnPhase::CreateChain(_, _) => Some(1),
+        TurnPhase::PickWinningChain(_, _) => Some(2),
+        TurnPhase::DistributeBonuses(_, _, _) => Some(3),
+        TurnPhase::ResolveMerger(_, _, _) => Some(4),
+        TurnPhase::BuyStock(_) => Some(5),
+        TurnPhase::GameOver(_) => None,
+    }
+}
+
+// Plays random games, keeping the states seen in each phase along with the
+// action that was taken from them.
+fn sample_turns(num_games: u64) -> Vec<Vec<(GameState, TurnAction)>> {
+    let mut samples = vec![Vec::new(); PHASE_NAMES.len()];
+    for seed in 0..num_games {
+        let ai = create_seeded_agent(0, seed);
+        let mut game = new_game(4, seed);
+        loop {
+            let action = ai.choose_action(&game);
+            if let Some(i) = phase_index(&game.turn_state.phase) {
+                samples[i].push((game.clone(), action.clone()));
+            }
+            if game.take_turn(action).unwrap() {
+                break;
+            }
+        }
+    }
+    samples
+}
+
+fn bench_setup(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(0);
+    c.bench_function("new_game", |b| {
+        b.iter(|| GameState::new(black_box(4), &mut rng, default_chain_names()).unwrap())
+    });
+    let game = new_game(4, 0);
+    c.bench_function("clone", |b| b.iter(|| black_box(&game).clone()));
+}
+
+fn bench_take_turn(c: &mut Criterion) {
+    let samples = sample_turns(20);
+    let mut group = c.benchmark_group("take_turn");
+    for (name, turns) in PHASE_NAMES.iter().zip(&samples) {
+        if turns.is_empty() {
+            continue;
+        }
+        let mut idx = 0;
+        group.bench_function(*name, |b| {
+            b.iter_batched(
+                || {
+                    idx = (idx + 1) % turns.len();
+                    turns[idx].clone()
+                },
+                |(mut game, action)| game.take_turn(action).unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_bonuses(c: &mut Criterion) {
+    let samples = sample_turns(5);
+    let (game, _) = samples[5].last().unwrap();
+    c.bench_function("calculate_bonuses", |b| {
+        b.iter(|| {
+            (0..MAX_NUM_CHAINS)
+                .map(|i| calculate_bonuses(i, black_box(600), &game.players))
+                .collect::<Vec<Vec<usize>>>()
+        })
+    });
+}
+
+fn bench_playout(c: &mut Criterion) {
+    let mut group = c.benchmark_group("random_playout");
+    group.throughput(Throughput::Elements(1));
+    for num_players in 2..=6 {
+        let mut seed = 0;
+        group.bench_with_input(
+            BenchmarkId::from_parameter(num_players),
+            &num_players,
+            |b, &n| {
+                b.iter_batched(
+                    || {
+                        seed += 1;
+                        (new_game(n, seed), create_seeded_agent(0, seed))
+                    },
+                    |(mut game, ai)| while !game.take_turn(ai.choose_action(&game)).unwrap() {},
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_setup,
+    bench_take_turn,
+    bench_bonuses,
+    bench_playout
+);
+criterion_main!(benches);
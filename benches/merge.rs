@@ -0,0 +1,50 @@
+// Times the turn that relabels a three-way merger's hotels, on a fixed
+// position parsed from the Display format.
+//
+// This only uses APIs that predate per-chain cell tracking, so it can be
+// compared against the grid scan it replaced. From a checkout of the
+// revision before that change (with criterion and this [[bench]] added to
+// its Cargo.toml, and CARGO_TARGET_DIR pointing at this tree's target), run
+//   cargo bench --bench merge -- --save-baseline scan
+// and then this benchmark here with `-- --baseline scan`.
+use acquire::game::{GameState, TurnAction};
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+const STATE: &str = "\
+Player 0: value = $10900
+  Cash: $200, Stocks: [K: 1, L: 2, M: 3, N: 2, O: 1, P: 4, Q: 8], Tiles: [9-F, 8-E, 5-B, 1-E, 9-D, 1-C]
+Player 1: value = $11200
+  Cash: $1600, Stocks: [K: 1, L: 1, N: 1, O: 3, P: 4, Q: 6], Tiles: [11-B, 9-I, 9-B, 2-H, 11-A]
+Player 2: value = $12700
+  Cash: $5200, Stocks: [M: 2, N: 3, O: 3, P: 2, Q: 6], Tiles: [8-H, 4-A, 10-D, 6-B, 9-C, 2-I]
+Player 3: value = $8900
+  Cash: $1500, Stocks: [M: 2, N: 1, O: 3, P: 3, Q: 4], Tiles: [3-F, 10-A, 12-B, 5-C, 8-G, 3-H]
+0123456789012
+AK_____O_____
+BKK____O_____
+C__PP__OO____
+D_PPP__OO____
+E___PPP____L_
+F____PPP__LLL
+G_____PP_LLLL
+H___QQX____L_
+I___Q_MM____*
+Stock market: [23, 22, 18, 18, 15, 12, 1]
+Chain sizes: [3, 9, 2, 0, 6, 13, 3]
+TurnState { player: 1, phase: PickWinningChain([5], [5, 6, 2]) }
+
+";
+
+fn bench_merge(c: &mut Criterion) {
+    let game = STATE.parse::<GameState>().unwrap();
+    c.bench_function("merge", |b| {
+        b.iter_batched(
+            || game.clone(),
+            |mut game| game.take_turn(TurnAction::PickWinningChain(5)).unwrap(),
+            BatchSize::SmallInput,
+        )
+    });
+}
+
+criterion_group!(benches, bench_merge);
+criterion_main!(benches);
@@ -1,155 +1,140 @@
-use acquire::game::{GameState, TurnAction, TurnPhase, MAX_NUM_CHAINS};
-use std::error::Error;
+// Plays a game in the terminal, with each seat taken by a human or an AI
+// agent. Humans enter commands at the prompt (see acquire::input), or 'q' to
+// quit, while bots act on their own and have their actions announced. For
+// example, to play against two bots of difficulty 1 and 2:
+//
+//   cargo run --example cli -- --seats human,1,2
+use acquire::agent::{create_agent, Agent};
+use acquire::game::{GameState, TurnAction, TurnPhase, MAX_NUM_CHAINS, MAX_NUM_PLAYERS};
+use acquire::input::{parse_input, prompt};
+use acquire::notation::write_action;
+use clap::Parser;
 
-fn main() {
-    let chain_names = [
-        chain_name(0).to_owned(),
-        chain_name(1).to_owned(),
-        chain_name(2).to_owned(),
-        chain_name(3).to_owned(),
-        chain_name(4).to_owned(),
-        chain_name(5).to_owned(),
-        chain_name(6).to_owned(),
-    ];
-    let mut rng = rand::thread_rng();
-    let mut game = GameState::new(4, &mut rng, chain_names);
-    print!("{}", game);
+const CHAIN_NAMES: [&str; MAX_NUM_CHAINS] =
+    ["Alice", "Bob", "Charlie", "Dave", "Eve", "Frank", "George"];
+
+#[derive(Parser)]
+struct Args {
+    // Who plays each seat: "human" (or "h"), or an agent difficulty.
+    #[clap(short, long, value_delimiter = ',', default_value = "human,1,1,1")]
+    seats: Vec<String>,
+    // Deal the tiles from this seed, instead of at random.
+    #[clap(long)]
+    seed: Option<u64>,
+}
+
+enum Seat {
+    Human,
+    Bot(Box<dyn Agent + Send>),
+}
+
+fn main() -> Result<(), String> {
+    let args = Args::parse();
+    let seats = args
+        .seats
+        .iter()
+        .map(|s| match s.as_str() {
+            "human" | "h" => Ok(Seat::Human),
+            _ => s
+                .parse::<usize>()
+                .map(|d| Seat::Bot(create_agent(d)))
+                .map_err(|_| format!("Invalid seat: {}", s)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if !(2..=MAX_NUM_PLAYERS).contains(&seats.len()) {
+        return Err(format!(
+            "--seats needs between 2 and {} seats, got {}",
+            MAX_NUM_PLAYERS,
+            seats.len()
+        ));
+    }
+    let chain_names = CHAIN_NAMES.map(|s| s.to_owned());
+    let mut game = match args.seed {
+        Some(seed) => GameState::from_seed(seats.len(), seed, chain_names)?,
+        None => GameState::new(seats.len(), &mut rand::thread_rng(), chain_names)?,
+    };
 
-    // Super-janky CLI for testing.
     let mut input = String::new();
+    // Whether the last command was rejected, so the prompt is being repeated.
+    let mut retry = false;
     loop {
-        match handle_turn(&mut game, &mut input) {
-            Ok(Some(action)) => match game.take_turn(action) {
-                Ok(true) => {
-                    println!("Game over!");
-                    break;
+        let player = game.acting_player();
+        let action = match &seats[player] {
+            Seat::Bot(agent) => {
+                let action = agent.choose_action(&game);
+                println!("{}", announce(&game, &action)?);
+                action
+            }
+            Seat::Human => {
+                if let (TurnPhase::PlaceTile(_), false) = (&game.turn_state.phase, retry) {
+                    show(&game, player);
                 }
-                Ok(false) => {
-                    print!("\n{}", game);
+                println!("{}", prompt(&game));
+                input.clear();
+                let read = std::io::stdin()
+                    .read_line(&mut input)
+                    .map_err(|e| e.to_string())?;
+                if read == 0 || input.trim() == "q" {
+                    return Ok(());
                 }
-                Err(e) => {
-                    println!("Error: {}", e);
+                match parse_input(&game, &input) {
+                    Ok(action) => action,
+                    Err(e) => {
+                        println!("Error: {}", e);
+                        retry = true;
+                        continue;
+                    }
                 }
-            },
-            Ok(None) => {
-                break;
             }
+        };
+        retry = false;
+        match game.take_turn(action) {
+            Ok(true) => break,
+            Ok(false) => {}
             Err(e) => {
                 println!("Error: {}", e);
+                retry = true;
             }
         }
-        input.clear();
     }
+    print!("\n{}", game.board);
+    println!("{}", prompt(&game));
+    Ok(())
 }
 
-fn chain_name(idx: usize) -> &'static str {
-    match idx {
-        0 => "Alice",
-        1 => "Bob",
-        2 => "Charlie",
-        3 => "Dave",
-        4 => "Eve",
-        5 => "Frank",
-        6 => "George",
-        _ => panic!("Invalid chain index"),
+// Describes a bot's action in the game's notation, like "P2 found Dave".
+fn announce(game: &GameState, action: &TurnAction) -> Result<String, String> {
+    let text = write_action(game, action)?;
+    match action {
+        // These already name the player.
+        TurnAction::ResolveMerger(_, _) => Ok(text),
+        _ => Ok(format!("P{} {}", game.turn_state.player, text)),
     }
 }
 
-fn handle_turn(
-    game: &mut GameState,
-    input: &mut String,
-) -> Result<Option<TurnAction>, Box<dyn Error>> {
-    match &game.turn_state.phase {
-        TurnPhase::PlaceTile(_) => {
-            println!("Choose a tile (index) to play, or 'q' to quit:");
-        }
-        TurnPhase::CreateChain(_, chain_inds) => {
-            println!("Choose a chain (index) to create, or 'q' to quit:");
-            for idx in chain_inds {
-                println!("{}: {}", idx, chain_name(*idx));
-            }
-        }
-        TurnPhase::PickWinningChain(choices, _) => {
-            if choices.len() == 1 {
-                println!("Press enter to merge chains, or 'q' to quit:");
-            } else {
-                println!("Choose a chain (index) to win the merger, or 'q' to quit:");
-                for idx in choices {
-                    println!("{}: {}", idx, chain_name(*idx));
-                }
-            }
-        }
-        TurnPhase::DistributeBonuses(_, chains, bonus_cash) => {
-            println!(
-                "Merging chain {} awards bonus cash: {:?}",
-                chain_name(chains[0]),
-                bonus_cash
-            );
-            println!("Press enter to continue, or 'q' to quit:")
-        }
-        TurnPhase::ResolveMerger(_, chains, player_idx) => {
-            println!(
-                "Player {}: Choose 'sell,trade' amounts of {} stock, or 'q' to quit:",
-                player_idx,
-                chain_name(chains[0])
-            );
-        }
-        TurnPhase::BuyStock(buyable_amounts) => {
-            println!("Choose up to 3 stocks (comma-sep indices), or 'q' to quit:");
-            for (i, &amount) in buyable_amounts.iter().enumerate() {
-                if amount > 0 {
-                    println!("{}: {} buyable in {}", i, amount, chain_name(i));
-                }
-            }
-        }
-        TurnPhase::GameOver(final_values) => {
-            println!("Game over! Final values: {:?}", final_values);
-            return Ok(None);
-        }
+// Shows the board, what everyone owns, and the given player's tiles.
+fn show(game: &GameState, player: usize) {
+    print!("\n{}", game.board);
+    for (i, p) in game.players.iter().enumerate() {
+        let stocks = (0..MAX_NUM_CHAINS)
+            .filter(|&c| p.stocks[c] > 0)
+            .map(|c| format!("{}: {}", game.board.chain_name(c), p.stocks[c]))
+            .collect::<Vec<_>>();
+        println!(
+            "P{}: value = ${}, cash = ${}, stocks = [{}]",
+            i,
+            game.player_value(i),
+            p.cash,
+            stocks.join(", ")
+        );
     }
-    std::io::stdin().read_line(input)?;
-    if input.trim() == "q" {
-        return Ok(None);
-    }
-    let action = match &game.turn_state.phase {
-        TurnPhase::PlaceTile(_) => {
-            let tile_idx = input.trim().parse::<usize>()?;
-            TurnAction::PlaceTile(tile_idx)
-        }
-        TurnPhase::CreateChain(_, _) => {
-            let chain_idx = input.trim().parse::<usize>()?;
-            TurnAction::CreateChain(chain_idx)
-        }
-        TurnPhase::PickWinningChain(choices, _) => {
-            let chain_idx = if choices.len() > 1 {
-                input.trim().parse::<usize>()?
-            } else {
-                choices[0]
-            };
-            TurnAction::PickWinningChain(chain_idx)
-        }
-        TurnPhase::DistributeBonuses(_, _, _) => TurnAction::AcceptBonus,
-        TurnPhase::ResolveMerger(_, _, _) => {
-            let mut sell_trade = [0, 0];
-            for (i, s) in input.trim().split(',').enumerate() {
-                sell_trade[i] = s.parse::<usize>()?;
-            }
-            TurnAction::ResolveMerger(sell_trade[0], sell_trade[1])
-        }
-        TurnPhase::BuyStock(_) => {
-            let mut buy_order = [0; MAX_NUM_CHAINS];
-            for s in input.trim().split(',') {
-                if s.is_empty() {
-                    continue;
-                }
-                let idx = s.parse::<usize>()?;
-                buy_order[idx] += 1;
-            }
-            TurnAction::BuyStock(buy_order)
-        }
-        TurnPhase::GameOver(_) => {
-            panic!("Game is over, this should be unreachable");
-        }
-    };
-    Ok(Some(action))
+    let tiles = game.players[player]
+        .tiles
+        .iter()
+        .map(|t| format!("{:?}", t));
+    println!(
+        "P{}'s tiles: {}",
+        player,
+        tiles.collect::<Vec<_>>().join(", ")
+    );
 }
@@ -0,0 +1,135 @@
+// Stand-in player for acquire-server, which joins a game and plays it with
+// server::simple_action. For example, to host a 3-player game locally:
+//
+//   cargo run --bin acquire-server &
+//   cargo run --example client -- --create 3 &
+//   cargo run --example client -- --game 0 &
+//   cargo run --example client -- --game 0
+//
+// Or to play against bots: cargo run --example client -- --create 4 --fill 1
+// And to watch game 0: cargo run --example client -- --game 0 --watch
+use acquire::clock::TimeControl;
+use acquire::game::TurnPhase;
+use acquire::server::{simple_action, Client, ClientMessage, ServerMessage, TableConfig};
+use acquire::spectator::SpectatorView;
+use clap::Parser;
+
+#[derive(Parser)]
+struct Args {
+    #[clap(short, long, default_value = "127.0.0.1:7878")]
+    addr: String,
+    // Create a game with this many players, then join it.
+    #[clap(short, long)]
+    create: Option<usize>,
+    // Fill the created table's empty seats with bots of this difficulty.
+    #[clap(short, long)]
+    fill: Option<usize>,
+    // Seconds before a bot takes over for a disconnected player at the
+    // created table.
+    #[clap(long)]
+    takeover_secs: Option<u64>,
+    // Actions the created table's omniscient spectator feed lags behind.
+    #[clap(long)]
+    spectator_delay: Option<usize>,
+    // Give each player at the created table a clock with this much time, in
+    // milliseconds.
+    #[clap(long)]
+    time_ms: Option<u64>,
+    // Time added to a player's clock after each of their actions.
+    #[clap(long, default_value_t = 0)]
+    increment_ms: u64,
+    // Join an existing game.
+    #[clap(short, long, default_value_t = 0)]
+    game: u64,
+    // Rejoin the game with the token from an earlier connection.
+    #[clap(long)]
+    token: Option<String>,
+    // Watch the game instead of playing.
+    #[clap(short, long, default_value_t = false)]
+    watch: bool,
+    // When watching, use the delayed omniscient feed.
+    #[clap(long, default_value_t = false)]
+    full: bool,
+    #[clap(short, long, default_value = "bot")]
+    name: String,
+    #[clap(short, long, default_value_t = false)]
+    verbose: bool,
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let mut client = Client::connect(&args.addr)?;
+    let mut game_id = args.game;
+    if let Some(num_players) = args.create {
+        let config = TableConfig {
+            takeover_secs: args.takeover_secs,
+            spectator_delay: args.spectator_delay,
+            time_control: args.time_ms.map(|initial_ms| TimeControl {
+                initial_ms,
+                increment_ms: args.increment_ms,
+            }),
+            ..TableConfig::new(num_players)
+        };
+        client.send(&ClientMessage::Create(config))?;
+        match client.recv()? {
+            ServerMessage::Created { game_id: id } => game_id = id,
+            ServerMessage::Error { message } => panic!("{}", message),
+            _ => panic!("Unexpected reply to Create"),
+        }
+        println!("Created game {}", game_id);
+    }
+    match args.token {
+        _ if args.watch => client.send(&ClientMessage::Spectate {
+            game_id,
+            full: args.full,
+        })?,
+        Some(token) => client.send(&ClientMessage::Rejoin { game_id, token })?,
+        None => {
+            let name = args.name.clone();
+            client.send(&ClientMessage::Join { game_id, name })?;
+        }
+    }
+    if let Some(difficulty) = args.fill {
+        client.send(&ClientMessage::FillWithBots {
+            game_id,
+            difficulty,
+        })?;
+    }
+    loop {
+        match client.recv()? {
+            ServerMessage::Joined { player, token, .. } => {
+                println!("Joined as P{} (rejoin with --token {})", player, token)
+            }
+            ServerMessage::Started { names, .. } => println!("Started: {}", names.join(", ")),
+            ServerMessage::View { view, to_act, .. } => {
+                if args.verbose {
+                    println!("{:?}", view.turn_state);
+                }
+                if to_act {
+                    let action = simple_action(&view);
+                    println!("Sending {:?}", action);
+                    client.send(&ClientMessage::Action { action })?;
+                }
+            }
+            ServerMessage::GameOver { scores, .. } => {
+                println!("Final scores: {:?}", scores);
+                return Ok(());
+            }
+            ServerMessage::Spectator { update, .. } => {
+                if let Some((player, action)) = update.last_action {
+                    println!("{}: P{} {}", update.move_number, player, action);
+                }
+                let turn_state = match &update.view {
+                    SpectatorView::Public(view) => &view.turn_state,
+                    SpectatorView::Full(game) => &game.turn_state,
+                };
+                if let TurnPhase::GameOver(scores) = &turn_state.phase {
+                    println!("Final scores: {:?}", scores);
+                    return Ok(());
+                }
+            }
+            ServerMessage::Error { message } => println!("Error: {}", message),
+            ServerMessage::Created { .. } | ServerMessage::Tables { .. } => {}
+        }
+    }
+}
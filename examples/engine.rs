@@ -0,0 +1,24 @@
+// Runs one of the built-in agents as an engine on stdin/stdout, speaking the
+// protocol in src/engine.rs. For example, to check it by hand:
+//
+//   cargo run --example engine -- --difficulty 1
+//
+// Then type "acquire", "isready", a view line and "go".
+use acquire::agent::create_agent;
+use acquire::engine::run_engine;
+use clap::Parser;
+
+#[derive(Parser)]
+struct Args {
+    #[clap(short, long, default_value_t = 1)]
+    difficulty: usize,
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    let agent = create_agent(args.difficulty);
+    let name = format!("acquire-{}", args.difficulty);
+    let stdin = std::io::stdin().lock();
+    let stdout = std::io::stdout().lock();
+    run_engine(&*agent, &name, stdin, stdout, &mut rand::thread_rng())
+}
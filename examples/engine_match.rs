@@ -0,0 +1,75 @@
+// Plays an external engine against one of the built-in agents, alternating
+// seats, and prints the engine's and the agent's scores for each game. E.g.:
+//
+//   cargo build --example engine
+//   cargo run --example engine_match -- target/debug/examples/engine
+use acquire::agent::Agent;
+use acquire::engine::ExternalAgent;
+use acquire::game::{GameState, TurnAction};
+use acquire::tournament::{builtin_agent, AgentFactory, Tournament};
+use clap::Parser;
+use std::time::Duration;
+
+#[derive(Parser)]
+struct Args {
+    // The engine program, followed by its arguments.
+    #[clap(required = true, num_args = 1.., trailing_var_arg = true)]
+    command: Vec<String>,
+    #[clap(short, long, default_value_t = 10)]
+    games: usize,
+    // Difficulty of the built-in opponent.
+    #[clap(short, long, default_value_t = 1)]
+    difficulty: usize,
+    // Time budget per move to send to the engine, in milliseconds.
+    #[clap(short, long)]
+    time_ms: Option<u64>,
+    #[clap(long, default_value_t = 0)]
+    seed: u64,
+    // Games to play at once, each with its own engine process.
+    #[clap(long, default_value_t = 1)]
+    threads: usize,
+}
+
+// Reports each action the engine failed to give, which was replaced by the
+// first legal action.
+struct Checked(ExternalAgent);
+impl Checked {
+    fn check(&self, action: TurnAction) -> TurnAction {
+        if let Some(e) = self.0.last_error() {
+            eprintln!("{}: {}", self.0.name(), e);
+        }
+        action
+    }
+}
+impl Agent for Checked {
+    fn choose_action(&self, game: &GameState) -> TurnAction {
+        self.check(self.0.choose_action(game))
+    }
+    fn choose_action_within(&self, game: &GameState, budget: Duration) -> TurnAction {
+        self.check(self.0.choose_action_within(game, budget))
+    }
+}
+
+fn main() -> Result<(), String> {
+    let args = Args::parse();
+    // Each game starts its own engine, but check that it runs first.
+    let name = ExternalAgent::spawn(&args.command[0], &args.command[1..])?
+        .name()
+        .to_owned();
+    let (command, time_ms) = (args.command.clone(), args.time_ms);
+    let engine: AgentFactory = Box::new(move |_| {
+        let mut engine = ExternalAgent::spawn(&command[0], &command[1..])?;
+        engine.time_ms = time_ms;
+        Ok(Box::new(Checked(engine)))
+    });
+    let mut tournament = Tournament::new(vec![engine, builtin_agent(args.difficulty)], args.games);
+    tournament.seed = args.seed;
+    tournament.num_threads = args.threads;
+    let results = tournament.run()?;
+    println!("{},acquire-{}", name, args.difficulty);
+    for result in &results.games {
+        let scores = result.agent_scores();
+        println!("{},{}", scores[0], scores[1]);
+    }
+    Ok(())
+}
@@ -0,0 +1,8 @@
+#!/bin/sh
+# Builds the C API and runs the C test program against it.
+set -e
+cd "$(dirname "$0")/../.."
+cargo build --release --lib --features ffi
+cc -Wall -Wextra -Werror -o target/release/test_acquire examples/ffi/test_acquire.c \
+  -Iinclude -Ltarget/release -lacquire
+LD_LIBRARY_PATH=target/release DYLD_LIBRARY_PATH=target/release target/release/test_acquire
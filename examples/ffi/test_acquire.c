@@ -0,0 +1,63 @@
+// Plays a seeded game between two built-in agents through the C API, checking
+// error handling along the way. Build and run it with examples/ffi/run.sh.
+#include <assert.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "acquire.h"
+
+int main(void) {
+  const char *names[] = {"Sackson", "Zeta", "America", "Fusion",
+                         "Hydra",   "Quantum", "Phoenix"};
+  assert(acquire_num_chains() == 7);
+  assert(acquire_game_new(1, 0, NULL) == NULL);
+
+  AcquireGame *game = acquire_game_new(2, 42, names);
+  assert(game != NULL);
+
+  // Accepting a bonus isn't valid at the start of the game.
+  assert(acquire_game_take_turn(game, "\"AcceptBonus\"") == -1);
+  const char *err = acquire_game_last_error(game);
+  assert(err != NULL && strncmp(err, "Wrong phase", 11) == 0);
+  assert(acquire_game_take_turn(game, "not json") == -1);
+
+  char *legal = acquire_game_legal_actions_json(game);
+  printf("Legal actions: %s\n", legal);
+  acquire_string_free(legal);
+
+  AcquireAgent *agents[] = {acquire_agent_new(1, 1), acquire_agent_new(0, 2)};
+  int status = 0, turns = 0;
+  while (status == 0) {
+    int player = acquire_game_acting_player(game);
+    char *action = acquire_agent_choose_action_json(agents[player], game);
+    status = acquire_game_take_turn(game, action);
+    acquire_string_free(action);
+    assert(status != -1);
+    turns++;
+  }
+  assert(acquire_game_last_error(game) == NULL);
+
+  // Round-trip the final state through JSON.
+  char *json = acquire_game_state_json(game);
+  AcquireGame *copy = acquire_game_from_json(json);
+  assert(copy != NULL);
+  char *text = acquire_game_to_string(game);
+  char *copy_text = acquire_game_to_string(copy);
+  assert(strcmp(text, copy_text) == 0);
+  printf("Game over after %d actions:\n%s", turns, text);
+
+  char *view = acquire_game_player_view_json(game, 1);
+  assert(view != NULL && strstr(view, "\"Sackson\"") != NULL);
+  assert(acquire_game_player_view_json(game, 2) == NULL);
+
+  acquire_string_free(view);
+  acquire_string_free(copy_text);
+  acquire_string_free(text);
+  acquire_string_free(json);
+  acquire_agent_free(agents[0]);
+  acquire_agent_free(agents[1]);
+  acquire_game_free(copy);
+  acquire_game_free(game);
+  printf("OK\n");
+  return 0;
+}
@@ -0,0 +1,28 @@
+#!/usr/bin/env python3
+# Plays games between the built-in agents through the Python bindings.
+# Build them first with `maturin develop --release` (see pyproject.toml).
+import sys
+import acquire
+
+
+def play(agents, seed=None):
+    game = acquire.GameState(len(agents), seed=seed)
+    while not game.is_over:
+        agent = agents[game.acting_player]
+        action = agent.choose_action(game)
+        if isinstance(action, int):
+            game.take_turn(action)
+        else:
+            game.take_turn_json(action)
+    return game.scores
+
+
+def main():
+    num_games = int(sys.argv[1]) if len(sys.argv) > 1 else 10
+    agents = [acquire.Agent(1), acquire.Agent(0), acquire.Agent(0)]
+    for seed in range(num_games):
+        print(",".join(map(str, play(agents, seed))))
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,55 @@
+// Plays a game between bots, and draws the final board (or the board after a
+// given number of actions) to an image file. PNG output needs the "png"
+// feature:
+//
+//   cargo run --example render -- --seed 7 board.svg
+//   cargo run --example render --features png -- --seed 7 board.png
+use acquire::agent::create_agent;
+use acquire::game::{default_chain_names, GameState, TurnAction};
+use acquire::render::render_svg;
+use clap::Parser;
+
+#[derive(Parser)]
+struct Args {
+    // Path to write, ending in .svg or .png.
+    path: String,
+    #[clap(short, long, default_value_t = 0)]
+    seed: u64,
+    #[clap(short, long, default_value_t = 4)]
+    players: usize,
+    // Stop after this many actions.
+    #[clap(short, long)]
+    actions: Option<usize>,
+}
+
+fn main() -> Result<(), String> {
+    let args = Args::parse();
+    let mut game = GameState::from_seed(args.players, args.seed, default_chain_names())?;
+    let agent = create_agent(1);
+    let mut last_tile = Vec::new();
+    for _ in 0..args.actions.unwrap_or(usize::MAX) {
+        let action = agent.choose_action(&game);
+        if let TurnAction::PlaceTile(idx) = action {
+            last_tile = vec![game.players[game.turn_state.player].tiles[idx]];
+        }
+        if game.take_turn(action)? {
+            break;
+        }
+    }
+    let data = if args.path.ends_with(".png") {
+        png(&game, &last_tile)?
+    } else {
+        render_svg(&game.board, &last_tile).into_bytes()
+    };
+    std::fs::write(&args.path, data).map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "png")]
+fn png(game: &GameState, highlight: &[acquire::game::Tile]) -> Result<Vec<u8>, String> {
+    acquire::render::render_png(&game.board, highlight)
+}
+
+#[cfg(not(feature = "png"))]
+fn png(_: &GameState, _: &[acquire::game::Tile]) -> Result<Vec<u8>, String> {
+    Err("PNG output needs the png feature".to_owned())
+}
@@ -0,0 +1,53 @@
+// Exports a game as an HTML page with step-through controls. The game is
+// either read from a record in acquire::notation (dealt from the given seed),
+// or played by bots:
+//
+//   cargo run --example replay -- --seed 7 replay.html
+//   cargo run --example replay -- --seed 7 --record game.txt replay.html
+use acquire::agent::create_agent;
+use acquire::game::{default_chain_names, GameState};
+use acquire::notation::parse_game;
+use acquire::replay::replay_html;
+use clap::Parser;
+
+#[derive(Parser)]
+struct Args {
+    // Path of the HTML page to write.
+    path: String,
+    #[clap(short, long, default_value_t = 0)]
+    seed: u64,
+    #[clap(short, long, default_value_t = 4)]
+    players: usize,
+    // Game record to replay, instead of playing a new game.
+    #[clap(short, long)]
+    record: Option<String>,
+    // Difficulty of the bots playing a new game.
+    #[clap(short, long, default_value_t = 1)]
+    difficulty: usize,
+}
+
+fn main() -> Result<(), String> {
+    let args = Args::parse();
+    let initial = GameState::from_seed(args.players, args.seed, default_chain_names())?;
+    let actions = match &args.record {
+        Some(path) => {
+            let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+            parse_game(&initial, &text)?
+        }
+        None => {
+            let agent = create_agent(args.difficulty);
+            let mut game = initial.clone();
+            let mut actions = Vec::new();
+            loop {
+                let action = agent.choose_action(&game);
+                actions.push(action.clone());
+                if game.take_turn(action)? {
+                    break;
+                }
+            }
+            actions
+        }
+    };
+    let html = replay_html(&initial, &actions)?;
+    std::fs::write(&args.path, html).map_err(|e| e.to_string())
+}
@@ -1,6 +1,16 @@
-use acquire::agent;
-use acquire::game::{GameState, TurnPhase};
-use clap::Parser;
+use acquire::clock::TimeControl;
+use acquire::sprt::{Sprt, SprtMatch};
+use acquire::stats;
+use acquire::tournament::{builtin_agent, Seating, Tournament};
+use clap::{Parser, ValueEnum};
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SeatingArg {
+    Fixed,
+    Rotated,
+    Random,
+    Duplicate,
+}
 
 #[derive(Parser)]
 struct Args {
@@ -8,57 +18,125 @@ struct Args {
     games: usize,
     #[clap(short, long, value_delimiter = ',', default_value = "0,0,0,0")]
     agents: Vec<usize>,
+    #[clap(short, long, value_enum, default_value = "rotated")]
+    seating: SeatingArg,
+    // Defaults to the number of available cores.
+    #[clap(short, long)]
+    threads: Option<usize>,
+    #[clap(long)]
+    seed: Option<u64>,
     #[clap(short, long, default_value_t = false)]
     verbose: bool,
+    // Print summary statistics instead of per-game scores.
+    #[clap(short, long, default_value_t = false)]
+    report: bool,
+    // Play paired games between the first two agents (candidate, baseline)
+    // until an SPRT on the candidate's Elo gain concludes.
+    #[clap(long, default_value_t = false)]
+    sprt: bool,
+    #[clap(long, default_value_t = 0.0)]
+    elo0: f64,
+    #[clap(long, default_value_t = 10.0)]
+    elo1: f64,
+    #[clap(long, default_value_t = 0.05)]
+    alpha: f64,
+    #[clap(long, default_value_t = 0.05)]
+    beta: f64,
+    #[clap(long, default_value_t = 100_000)]
+    max_pairs: usize,
+    // Give each player a clock with this much time, in milliseconds.
+    #[clap(long)]
+    time_ms: Option<u64>,
+    // Time added to a player's clock after each of their actions.
+    #[clap(long, default_value_t = 0)]
+    increment_ms: u64,
 }
 
 fn main() {
-    let chain_names = [
-        "K".to_owned(),
-        "L".to_owned(),
-        "M".to_owned(),
-        "N".to_owned(),
-        "O".to_owned(),
-        "P".to_owned(),
-        "Q".to_owned(),
-    ];
     let args = Args::parse();
-    let mut rng = rand::thread_rng();
+    if args.sprt {
+        run_sprt(&args);
+        return;
+    }
+    let agents = args.agents.iter().map(|&d| builtin_agent(d)).collect();
+    let mut tournament = Tournament::new(agents, args.games);
+    tournament.seating = match args.seating {
+        SeatingArg::Fixed => Seating::Fixed,
+        SeatingArg::Rotated => Seating::Rotated,
+        SeatingArg::Random => Seating::Random,
+        SeatingArg::Duplicate => Seating::Duplicate,
+    };
+    if let Some(threads) = args.threads {
+        tournament.num_threads = threads;
+    }
+    if let Some(seed) = args.seed {
+        tournament.seed = seed;
+    }
+    tournament.keep_states = args.verbose;
+    tournament.time_control = args.time_ms.map(|initial_ms| TimeControl {
+        initial_ms,
+        increment_ms: args.increment_ms,
+    });
+    let results = tournament.run().unwrap();
 
-    let num_players = args.agents.len();
-    for game_idx in 0..args.games {
-        let mut game = GameState::new(num_players, &mut rng, chain_names.clone());
-        let agents = args
-            .agents
-            .iter()
-            .map(|&i| agent::create_agent(i))
-            .collect::<Vec<_>>();
-        loop {
-            let ai = &agents[player_idx(&game)];
-            let action = ai.choose_action(&game);
-            if game.take_turn(action).unwrap() {
-                break;
-            }
-        }
-        if args.verbose {
-            println!("Game {}:\n{}", game_idx, game);
-        } else if let TurnPhase::GameOver(scores) = game.turn_state.phase {
+    if args.verbose {
+        for result in &results.games {
+            let game = result.final_state.as_ref().unwrap();
             println!(
-                "{}",
-                scores
-                    .into_iter()
-                    .map(|s| s.to_string())
-                    .collect::<Vec<_>>()
-                    .join(",")
+                "Game {} (seats: {:?}):\n{}",
+                result.game_index, result.seats, game
             );
         }
+        return;
+    }
+    // Scores are in agent order, regardless of seating. In duplicate mode,
+    // each row holds the agents' total scores over all seats of one deal.
+    let scores = if tournament.seating == Seating::Duplicate {
+        results.deal_totals()
+    } else {
+        results.games.iter().map(|r| r.agent_scores()).collect()
+    };
+    if args.report {
+        print!("{}", stats::report(&scores));
+        return;
+    }
+    for row in scores {
+        println!(
+            "{}",
+            row.into_iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        );
     }
 }
 
-fn player_idx(game: &GameState) -> usize {
-    if let TurnPhase::ResolveMerger(_, _, idx) = game.turn_state.phase {
-        idx
-    } else {
-        game.turn_state.player
+fn run_sprt(args: &Args) {
+    if args.agents.len() != 2 {
+        eprintln!("SPRT mode needs exactly two agents: candidate,baseline");
+        std::process::exit(1);
+    }
+    let sprt = Sprt::new(args.elo0, args.elo1, args.alpha, args.beta);
+    let candidate = builtin_agent(args.agents[0]);
+    let mut sprt_match = SprtMatch::new(candidate, builtin_agent(args.agents[1]), sprt);
+    sprt_match.max_pairs = args.max_pairs;
+    if let Some(threads) = args.threads {
+        sprt_match.tournament.num_threads = threads;
+    }
+    if let Some(seed) = args.seed {
+        sprt_match.tournament.seed = seed;
     }
+    let (lower, upper) = sprt.bounds();
+    let result = sprt_match
+        .run(|p| {
+            eprintln!(
+                "Pairs: {}, score: {:.3}, LLR: {:.3} [{:.3}, {:.3}]",
+                p.num_pairs, p.score, p.llr, lower, upper
+            )
+        })
+        .unwrap();
+    println!(
+        "{:?} after {} pairs (score {:.3}, LLR {:.3})",
+        result.result, result.num_pairs, result.score, result.llr
+    );
 }
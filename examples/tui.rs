@@ -0,0 +1,534 @@
+// Terminal UI for hot-seat games: a colored board, the acting player's hand,
+// the stock market, and a dialog for each decision. Arrow keys move the
+// selection (and adjust amounts when buying or resolving a merger), Enter
+// confirms, and q quits. Tiles can also be played by clicking them, either in
+// the hand or on the board.
+//
+//   cargo run --example tui -- --players 3
+use acquire::game::{GameState, Tile, TurnAction, TurnPhase, MAX_NUM_CHAINS, MAX_NUM_PLAYERS};
+use acquire::notation::write_action;
+use clap::Parser;
+use ratatui::crossterm::event::{
+    self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, MouseButton,
+    MouseEventKind,
+};
+use ratatui::crossterm::execute;
+use ratatui::layout::{Constraint, Flex, Layout, Position, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Clear, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+
+const WIDTH: usize = 12;
+const HEIGHT: usize = 9;
+// Columns taken by each board cell and each tile in the hand.
+const CELL_WIDTH: u16 = 5;
+const HAND_WIDTH: u16 = 6;
+const BUY_LIMIT: usize = 3;
+const CHAIN_NAMES: [&str; MAX_NUM_CHAINS] =
+    ["Alice", "Bob", "Charlie", "Dave", "Eve", "Frank", "George"];
+const CHAIN_COLORS: [Color; MAX_NUM_CHAINS] = [
+    Color::Red,
+    Color::Yellow,
+    Color::Green,
+    Color::Cyan,
+    Color::Blue,
+    Color::Magenta,
+    Color::LightRed,
+];
+
+#[derive(Parser)]
+struct Args {
+    #[clap(short, long, default_value_t = 4)]
+    players: usize,
+    // Deal the tiles from this seed, instead of at random.
+    #[clap(short, long)]
+    seed: Option<u64>,
+}
+
+struct App {
+    game: GameState,
+    // The selected hand tile, menu entry, or merger field (0 = sell,
+    // 1 = trade), depending on the phase.
+    selected: usize,
+    buy_order: [usize; MAX_NUM_CHAINS],
+    sell: usize,
+    trade: usize,
+    log: Vec<String>,
+    error: String,
+    // Inner areas of the board and hand, as last drawn, for mouse clicks.
+    board_area: Rect,
+    hand_area: Rect,
+}
+
+fn main() -> std::io::Result<()> {
+    let args = Args::parse();
+    if !(2..=MAX_NUM_PLAYERS).contains(&args.players) {
+        return Err(std::io::Error::other(format!(
+            "--players must be between 2 and {}, got {}",
+            MAX_NUM_PLAYERS, args.players
+        )));
+    }
+    let chain_names = CHAIN_NAMES.map(|s| s.to_owned());
+    let game = match args.seed {
+        Some(seed) => GameState::from_seed(args.players, seed, chain_names),
+        None => GameState::new(args.players, &mut rand::thread_rng(), chain_names),
+    }
+    .map_err(std::io::Error::other)?;
+    let mut app = App::new(game);
+    let mut terminal = ratatui::init();
+    execute!(std::io::stdout(), EnableMouseCapture)?;
+    let result = run(&mut terminal, &mut app);
+    execute!(std::io::stdout(), DisableMouseCapture)?;
+    ratatui::restore();
+    for line in &app.log {
+        println!("{}", line);
+    }
+    result
+}
+
+fn run(terminal: &mut DefaultTerminal, app: &mut App) -> std::io::Result<()> {
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                code => app.on_key(code),
+            },
+            Event::Mouse(mouse) if mouse.kind == MouseEventKind::Down(MouseButton::Left) => {
+                app.on_click(Position::new(mouse.column, mouse.row))
+            }
+            _ => {}
+        }
+    }
+}
+
+impl App {
+    fn new(game: GameState) -> Self {
+        let mut app = Self {
+            game,
+            selected: 0,
+            buy_order: [0; MAX_NUM_CHAINS],
+            sell: 0,
+            trade: 0,
+            log: Vec::new(),
+            error: String::new(),
+            board_area: Rect::default(),
+            hand_area: Rect::default(),
+        };
+        app.reset();
+        app
+    }
+    // Clears the selection for a new phase, starting on a playable tile.
+    fn reset(&mut self) {
+        self.selected = match &self.game.turn_state.phase {
+            TurnPhase::PlaceTile(valid) => valid.first().copied().unwrap_or(0),
+            _ => 0,
+        };
+        self.buy_order = [0; MAX_NUM_CHAINS];
+        self.sell = 0;
+        self.trade = 0;
+    }
+    fn hand(&self) -> &[Tile] {
+        &self.game.players[self.game.acting_player()].tiles
+    }
+    fn buyable_chains(&self) -> Vec<usize> {
+        match &self.game.turn_state.phase {
+            TurnPhase::BuyStock(buyable) => {
+                (0..MAX_NUM_CHAINS).filter(|&i| buyable[i] > 0).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+    fn num_choices(&self) -> usize {
+        match &self.game.turn_state.phase {
+            TurnPhase::PlaceTile(_) => self.hand().len(),
+            TurnPhase::CreateChain(_, chains) => chains.len(),
+            TurnPhase::PickWinningChain(choices, _) => choices.len(),
+            TurnPhase::ResolveMerger(_, _, _) => 2,
+            TurnPhase::BuyStock(_) => self.buyable_chains().len(),
+            TurnPhase::DistributeBonuses(_, _, _) | TurnPhase::GameOver(_) => 1,
+        }
+    }
+
+    fn on_key(&mut self, code: KeyCode) {
+        let n = self.num_choices().max(1);
+        let placing = matches!(self.game.turn_state.phase, TurnPhase::PlaceTile(_));
+        match code {
+            KeyCode::Up => self.selected = (self.selected + n - 1) % n,
+            KeyCode::Down => self.selected = (self.selected + 1) % n,
+            KeyCode::Left if placing => self.selected = (self.selected + n - 1) % n,
+            KeyCode::Right if placing => self.selected = (self.selected + 1) % n,
+            KeyCode::Left => self.adjust(false),
+            KeyCode::Right => self.adjust(true),
+            KeyCode::Enter => self.confirm(),
+            _ => {}
+        }
+    }
+    fn on_click(&mut self, pos: Position) {
+        if !matches!(self.game.turn_state.phase, TurnPhase::PlaceTile(_)) {
+            return;
+        }
+        let idx = if self.hand_area.contains(pos) {
+            Some(((pos.x - self.hand_area.x) / HAND_WIDTH) as usize)
+        } else if self.board_area.contains(pos) {
+            let col = ((pos.x - self.board_area.x) / CELL_WIDTH) as usize;
+            let row = (pos.y - self.board_area.y) as usize;
+            Tile::from_index(row * WIDTH + col)
+                .filter(|_| col < WIDTH)
+                .and_then(|tile| self.hand().iter().position(|&t| t == tile))
+        } else {
+            None
+        };
+        if let Some(idx) = idx.filter(|&i| i < self.hand().len()) {
+            self.selected = idx;
+            self.confirm();
+        }
+    }
+    // Changes the selected amount when resolving a merger or buying stock.
+    fn adjust(&mut self, up: bool) {
+        match &self.game.turn_state.phase {
+            TurnPhase::ResolveMerger(_, losers, player) => {
+                let held = self.game.players[*player].stocks[losers[0]];
+                if self.selected == 0 {
+                    let max = held - self.trade;
+                    self.sell = if up {
+                        (self.sell + 1).min(max)
+                    } else {
+                        self.sell.saturating_sub(1)
+                    };
+                } else {
+                    // Shares are traded two-for-one.
+                    let max = (held - self.sell) / 2 * 2;
+                    self.trade = if up {
+                        (self.trade + 2).min(max)
+                    } else {
+                        self.trade.saturating_sub(2)
+                    };
+                }
+            }
+            TurnPhase::BuyStock(buyable) => {
+                let Some(&chain) = self.buyable_chains().get(self.selected) else {
+                    return;
+                };
+                let total = self.buy_order.iter().sum::<usize>();
+                let amount = &mut self.buy_order[chain];
+                if !up {
+                    *amount = amount.saturating_sub(1);
+                } else if total < BUY_LIMIT && *amount < buyable[chain] {
+                    *amount += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    fn confirm(&mut self) {
+        let action = match &self.game.turn_state.phase {
+            TurnPhase::PlaceTile(_) => TurnAction::PlaceTile(self.selected),
+            TurnPhase::CreateChain(_, chains) => TurnAction::CreateChain(chains[self.selected]),
+            TurnPhase::PickWinningChain(choices, _) => {
+                TurnAction::PickWinningChain(choices[self.selected])
+            }
+            TurnPhase::DistributeBonuses(_, _, _) => TurnAction::AcceptBonus,
+            TurnPhase::ResolveMerger(_, _, _) => TurnAction::ResolveMerger(self.sell, self.trade),
+            TurnPhase::BuyStock(_) => TurnAction::BuyStock(self.buy_order),
+            TurnPhase::GameOver(_) => return,
+        };
+        let text = match write_action(&self.game, &action) {
+            // Merger steps already name the player.
+            Ok(text) if matches!(action, TurnAction::ResolveMerger(_, _)) => text,
+            Ok(text) => format!("P{} {}", self.game.turn_state.player, text),
+            Err(e) => e,
+        };
+        match self.game.take_turn(action) {
+            Ok(_) => {
+                self.log.push(text);
+                self.error.clear();
+                self.reset();
+            }
+            Err(e) => self.error = e,
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [top, hand, bottom] = Layout::vertical([
+            Constraint::Length(HEIGHT as u16 + 2),
+            Constraint::Length(3),
+            Constraint::Min(3),
+        ])
+        .areas(frame.area());
+        let [board, side] = Layout::horizontal([
+            Constraint::Length(WIDTH as u16 * CELL_WIDTH + 2),
+            Constraint::Min(40),
+        ])
+        .areas(top);
+        let [market, players] = Layout::vertical([
+            Constraint::Length(MAX_NUM_CHAINS as u16 + 3),
+            Constraint::Min(0),
+        ])
+        .areas(side);
+        let [log, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(bottom);
+        self.board_area = Block::bordered().inner(board);
+        self.hand_area = Block::bordered().inner(hand);
+
+        frame.render_widget(self.board(), board);
+        frame.render_widget(self.hand_panel(), hand);
+        frame.render_widget(self.market(), market);
+        frame.render_widget(self.players(), players);
+        let lines = self
+            .log
+            .len()
+            .saturating_sub(log.height.saturating_sub(2) as usize);
+        let log_lines = self.log[lines..].iter().map(|l| Line::raw(l.as_str()));
+        frame.render_widget(
+            Paragraph::new(log_lines.collect::<Vec<_>>()).block(Block::bordered().title("Log")),
+            log,
+        );
+        let status_line = if self.error.is_empty() {
+            Line::styled(
+                "Arrows: select/adjust  Enter: confirm  Click: play tile  q: quit",
+                Style::new().fg(Color::DarkGray),
+            )
+        } else {
+            Line::styled(self.error.as_str(), Style::new().fg(Color::Red))
+        };
+        frame.render_widget(status_line, status);
+
+        if let Some((title, lines)) = self.dialog() {
+            let width = lines
+                .iter()
+                .map(|l| l.width())
+                .chain([title.len()])
+                .max()
+                .unwrap_or(0);
+            let area = centered(frame.area(), width as u16 + 4, lines.len() as u16 + 2);
+            frame.render_widget(Clear, area);
+            frame.render_widget(
+                Paragraph::new(lines).block(Block::bordered().title(title)),
+                area,
+            );
+        }
+    }
+
+    fn board(&self) -> Paragraph<'static> {
+        let board = &self.game.board;
+        let placing = matches!(self.game.turn_state.phase, TurnPhase::PlaceTile(_));
+        let hand = self.hand();
+        let rows = (0..HEIGHT).map(|row| {
+            let cells = (0..WIDTH).map(|col| {
+                let tile = Tile::from_index(row * WIDTH + col).unwrap();
+                let mut style = match board.chain_at(tile) {
+                    Some(chain) => Style::new().bg(CHAIN_COLORS[chain]).fg(Color::Black),
+                    None if board.is_occupied(tile) => {
+                        Style::new().bg(Color::Gray).fg(Color::Black)
+                    }
+                    None => Style::new().fg(Color::DarkGray),
+                };
+                match hand.iter().position(|&t| t == tile) {
+                    Some(i) if placing && i == self.selected => {
+                        style = style.fg(Color::White).add_modifier(Modifier::REVERSED)
+                    }
+                    Some(_) => style = style.fg(Color::White).add_modifier(Modifier::BOLD),
+                    None => {}
+                }
+                Span::styled(format!("{:<4} ", format!("{:?}", tile)), style)
+            });
+            Line::from(cells.collect::<Vec<_>>())
+        });
+        Paragraph::new(rows.collect::<Vec<_>>()).block(Block::bordered().title("Board"))
+    }
+    fn hand_panel(&self) -> Paragraph<'static> {
+        let player = self.game.acting_player();
+        let valid = match &self.game.turn_state.phase {
+            TurnPhase::PlaceTile(valid) => valid.clone(),
+            _ => Vec::new(),
+        };
+        let tiles = self.hand().iter().enumerate().map(|(i, tile)| {
+            let mut style = if valid.contains(&i) {
+                Style::new().fg(Color::Green).add_modifier(Modifier::BOLD)
+            } else {
+                Style::new().fg(Color::DarkGray)
+            };
+            if !valid.is_empty() && i == self.selected {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            Span::styled(format!(" {:<4} ", format!("{:?}", tile)), style)
+        });
+        let title = format!("P{}'s tiles", player);
+        Paragraph::new(Line::from(tiles.collect::<Vec<_>>())).block(Block::bordered().title(title))
+    }
+    fn market(&self) -> Table<'static> {
+        let board = &self.game.board;
+        let stocks = &self.game.players[self.game.acting_player()].stocks;
+        let rows = (0..MAX_NUM_CHAINS).map(|i| {
+            let size = board.chain_size(i);
+            let price = if size > 0 {
+                format!("${}", board.stock_price(i))
+            } else {
+                "-".to_owned()
+            };
+            let safe = if board.is_safe(i) { "safe" } else { "" };
+            Row::new([
+                Span::styled(
+                    board.chain_name(i).to_owned(),
+                    Style::new().fg(CHAIN_COLORS[i]),
+                ),
+                Span::raw(size.to_string()),
+                Span::raw(price),
+                Span::raw(board.stocks_left(i).to_string()),
+                Span::raw(stocks[i].to_string()),
+                Span::raw(safe),
+            ])
+        });
+        let widths = [
+            Constraint::Length(8),
+            Constraint::Length(5),
+            Constraint::Length(6),
+            Constraint::Length(5),
+            Constraint::Length(5),
+            Constraint::Length(5),
+        ];
+        let header = Row::new(["Chain", "Size", "Price", "Left", "Held", ""])
+            .style(Style::new().add_modifier(Modifier::BOLD));
+        Table::new(rows, widths)
+            .header(header)
+            .block(Block::bordered().title("Stock market"))
+    }
+    fn players(&self) -> Table<'static> {
+        let acting = self.game.acting_player();
+        let rows = self.game.players.iter().enumerate().map(|(i, p)| {
+            let stocks = (0..MAX_NUM_CHAINS)
+                .filter(|&c| p.stocks[c] > 0)
+                .map(|c| format!("{} {}", self.game.board.chain_name(c), p.stocks[c]))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let marker = if i == acting { ">" } else { " " };
+            Row::new([
+                format!("{}P{}", marker, i),
+                format!("${}", p.cash),
+                format!("${}", self.game.player_value(i)),
+                stocks,
+            ])
+        });
+        let widths = [
+            Constraint::Length(4),
+            Constraint::Length(7),
+            Constraint::Length(7),
+            Constraint::Min(10),
+        ];
+        let header = Row::new(["", "Cash", "Value", "Stocks"])
+            .style(Style::new().add_modifier(Modifier::BOLD));
+        let title = format!("Players ({} tiles left)", self.game.num_unclaimed_tiles());
+        Table::new(rows, widths)
+            .header(header)
+            .block(Block::bordered().title(title))
+    }
+
+    // The title and contents of the dialog for the current decision, if any.
+    fn dialog(&self) -> Option<(String, Vec<Line<'static>>)> {
+        let game = &self.game;
+        let board = &game.board;
+        let chain = |i: usize| {
+            Span::styled(
+                board.chain_name(i).to_owned(),
+                Style::new().fg(CHAIN_COLORS[i]),
+            )
+        };
+        let marker = |selected: bool| Span::raw(if selected { "> " } else { "  " });
+        let dialog = match &game.turn_state.phase {
+            TurnPhase::PlaceTile(_) => return None,
+            TurnPhase::CreateChain(tile, chains) => {
+                let lines = chains
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &c)| Line::from(vec![marker(i == self.selected), chain(c)]));
+                (format!("Found a chain at {:?}", tile), lines.collect())
+            }
+            TurnPhase::PickWinningChain(choices, _) => {
+                let lines = choices.iter().enumerate().map(|(i, &c)| {
+                    let size = Span::raw(format!(" ({} tiles)", board.chain_size(c)));
+                    Line::from(vec![marker(i == self.selected), chain(c), size])
+                });
+                ("Pick the surviving chain".to_owned(), lines.collect())
+            }
+            TurnPhase::DistributeBonuses(_, losers, bonuses) => {
+                let mut lines = bonuses
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &b)| b > 0)
+                    .map(|(p, b)| Line::raw(format!("P{}: ${}", p, b)))
+                    .collect::<Vec<_>>();
+                lines.push(Line::raw("Enter to accept"));
+                let title = format!("Bonuses for {}", board.chain_name(losers[0]));
+                (title, lines)
+            }
+            TurnPhase::ResolveMerger(winner, losers, player) => {
+                let loser = losers[0];
+                let held = game.players[*player].stocks[loser];
+                let lines = vec![
+                    Line::from(vec![
+                        Span::raw(format!("Holding {} shares of ", held)),
+                        chain(loser),
+                    ]),
+                    Line::from(vec![
+                        marker(self.selected == 0),
+                        Span::raw(format!(
+                            "Sell {} at ${}",
+                            self.sell,
+                            game.stock_price(loser)
+                        )),
+                    ]),
+                    Line::from(vec![
+                        marker(self.selected == 1),
+                        Span::raw(format!("Trade {} for {} ", self.trade, self.trade / 2)),
+                        chain(*winner),
+                    ]),
+                    Line::raw(format!("  Keep {}", held - self.sell - self.trade)),
+                ];
+                (format!("P{}: resolve the merger", player), lines)
+            }
+            TurnPhase::BuyStock(_) => {
+                let mut lines = self
+                    .buyable_chains()
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, c)| {
+                        let order = format!(" ${} × {}", game.stock_price(c), self.buy_order[c]);
+                        Line::from(vec![marker(i == self.selected), chain(c), Span::raw(order)])
+                    })
+                    .collect::<Vec<_>>();
+                let cost = (0..MAX_NUM_CHAINS)
+                    .map(|c| self.buy_order[c] * game.stock_price(c))
+                    .sum::<usize>();
+                let cash = game.players[game.turn_state.player].cash;
+                lines.push(Line::raw(format!("Cost ${} of ${}", cost, cash)));
+                (
+                    format!(
+                        "P{}: buy up to {} shares",
+                        game.turn_state.player, BUY_LIMIT
+                    ),
+                    lines,
+                )
+            }
+            TurnPhase::GameOver(scores) => {
+                let lines = scores
+                    .iter()
+                    .enumerate()
+                    .map(|(p, s)| Line::raw(format!("P{}: ${}", p, s)));
+                ("Game over (q to quit)".to_owned(), lines.collect())
+            }
+        };
+        Some(dialog)
+    }
+}
+
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let [area] = Layout::horizontal([Constraint::Length(width)])
+        .flex(Flex::Center)
+        .areas(area);
+    let [area] = Layout::vertical([Constraint::Length(height)])
+        .flex(Flex::Center)
+        .areas(area);
+    area
+}
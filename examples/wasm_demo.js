@@ -0,0 +1,21 @@
+// Plays a game between two seeded bots through the WebAssembly bindings.
+// Build them for node first:
+//
+//   cargo build --lib --release --target wasm32-unknown-unknown \
+//     --no-default-features --features wasm
+//   wasm-bindgen --target nodejs --out-dir pkg \
+//     target/wasm32-unknown-unknown/release/acquire.wasm
+//   node examples/wasm_demo.js
+const { Game, Bot } = require("../pkg/acquire.js");
+
+const game = new Game(2, 42n);
+const bots = [new Bot(1, 1n), new Bot(0, 2n)];
+console.log(game.validateAction('{"PlaceTile": 9}'));
+while (!game.isOver()) {
+  const action = bots[game.actingPlayer()].chooseActionJson(game);
+  if (game.phase() !== "PlaceTile") {
+    console.log(`P${game.actingPlayer()} ${game.describeAction(action)}`);
+  }
+  game.takeTurn(action);
+}
+console.log(game.toString());
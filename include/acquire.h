@@ -0,0 +1,52 @@
+// Generated by cbindgen from src/ffi.rs. Do not edit by hand.
+
+#ifndef ACQUIRE_H
+#define ACQUIRE_H
+
+#include <stdint.h>
+
+typedef struct AcquireAgent AcquireAgent;
+
+typedef struct AcquireGame AcquireGame;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+AcquireGame *acquire_game_new(uint32_t num_players, uint64_t seed, const char *const *chain_names);
+
+AcquireGame *acquire_game_from_json(const char *json);
+
+void acquire_game_free(AcquireGame *game);
+
+AcquireGame *acquire_game_clone(const AcquireGame *game);
+
+int32_t acquire_game_take_turn(AcquireGame *game, const char *action_json);
+
+const char *acquire_game_last_error(const AcquireGame *game);
+
+int32_t acquire_game_acting_player(const AcquireGame *game);
+
+char *acquire_game_state_json(const AcquireGame *game);
+
+char *acquire_game_player_view_json(const AcquireGame *game, uint32_t player);
+
+char *acquire_game_legal_actions_json(const AcquireGame *game);
+
+char *acquire_game_to_string(const AcquireGame *game);
+
+void acquire_string_free(char *s);
+
+AcquireAgent *acquire_agent_new(uint32_t difficulty, uint64_t seed);
+
+void acquire_agent_free(AcquireAgent *agent);
+
+char *acquire_agent_choose_action_json(const AcquireAgent *agent, const AcquireGame *game);
+
+uint32_t acquire_num_chains(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ACQUIRE_H */
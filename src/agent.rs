@@ -1,75 +1,117 @@
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::Rng;
+use rand::{Rng, RngCore, SeedableRng};
+use std::cell::RefCell;
+use std::time::Duration;
 
 use crate::game::{GameState, TurnAction, TurnPhase, MAX_NUM_CHAINS};
 
 pub trait Agent {
     fn choose_action(&self, game: &GameState) -> TurnAction;
+    // Like choose_action, for agents that can make use of a time budget for
+    // the move. Others ignore it.
+    fn choose_action_within(&self, game: &GameState, _budget: Duration) -> TurnAction {
+        self.choose_action(game)
+    }
 }
 
+#[cfg(feature = "thread_rng")]
 pub fn create_agent(difficulty: usize) -> Box<dyn Agent + Send> {
+    make_agent(difficulty, AgentRng::Thread)
+}
+
+// Like create_agent, but with a reproducible source of randomness that
+// doesn't need the thread-local rng (e.g. on wasm32-unknown-unknown).
+pub fn create_seeded_agent(difficulty: usize, seed: u64) -> Box<dyn Agent + Send> {
+    make_agent(
+        difficulty,
+        AgentRng::Seeded(Box::new(RefCell::new(StdRng::seed_from_u64(seed)))),
+    )
+}
+
+fn make_agent(difficulty: usize, rng: AgentRng) -> Box<dyn Agent + Send> {
+    let random = RandomAgent { rng };
     match difficulty {
         // Random (valid) actions.
-        0 => Box::<RandomAgent>::default(),
+        0 => Box::new(random),
         // Simple heuristics on top of random actions.
-        _ => Box::<BasicAgent>::default(),
+        _ => Box::new(BasicAgent { random }),
+    }
+}
+
+enum AgentRng {
+    #[cfg(feature = "thread_rng")]
+    Thread,
+    Seeded(Box<RefCell<StdRng>>),
+}
+impl AgentRng {
+    fn with<T>(&self, f: impl FnOnce(&mut dyn RngCore) -> T) -> T {
+        match self {
+            #[cfg(feature = "thread_rng")]
+            AgentRng::Thread => f(&mut rand::thread_rng()),
+            AgentRng::Seeded(rng) => f(&mut *rng.borrow_mut()),
+        }
     }
 }
 
-#[derive(Default)]
-struct RandomAgent;
+struct RandomAgent {
+    rng: AgentRng,
+}
 impl Agent for RandomAgent {
     fn choose_action(&self, game: &GameState) -> TurnAction {
-        let mut rng = rand::thread_rng();
-        match &game.turn_state.phase {
-            TurnPhase::PlaceTile(tile_inds) => {
-                let tile_idx = tile_inds.choose(&mut rng).unwrap();
-                TurnAction::PlaceTile(*tile_idx)
-            }
-            TurnPhase::CreateChain(_, chain_inds) => {
-                let chain_idx = chain_inds.choose(&mut rng).unwrap();
-                TurnAction::CreateChain(*chain_idx)
-            }
-            TurnPhase::PickWinningChain(choices, _) => {
-                let chain_idx = choices.choose(&mut rng).unwrap();
-                TurnAction::PickWinningChain(*chain_idx)
-            }
-            TurnPhase::DistributeBonuses(_, _, _) => TurnAction::AcceptBonus,
-            TurnPhase::ResolveMerger(_winner_idx, loser_inds, player_idx) => {
-                let loser_idx = loser_inds[0];
-                let loser_shares = game.players[*player_idx].stocks[loser_idx];
-                // TODO: Enable trading shares as well as selling them.
-                let num_sold = rng.gen_range(0..=loser_shares);
-                TurnAction::ResolveMerger(num_sold, 0)
-            }
-            TurnPhase::BuyStock(buyable_amounts) => {
-                let my_cash = game.players[game.turn_state.player].cash;
-                // Add one index for each buyable share.
-                let mut buyable_shares = Vec::new();
-                for (i, &amount) in buyable_amounts.iter().enumerate() {
-                    let price = game.stock_price(i);
-                    if amount > 0 && price < my_cash {
-                        let max_shares = (my_cash / price).min(3).min(amount);
-                        for _ in 0..max_shares {
-                            buyable_shares.push(i);
-                        }
+        self.rng.with(|rng| random_action(game, rng))
+    }
+}
+
+fn random_action(game: &GameState, mut rng: &mut dyn RngCore) -> TurnAction {
+    match &game.turn_state.phase {
+        TurnPhase::PlaceTile(tile_inds) => {
+            let tile_idx = tile_inds.choose(&mut rng).unwrap();
+            TurnAction::PlaceTile(*tile_idx)
+        }
+        TurnPhase::CreateChain(_, chain_inds) => {
+            let chain_idx = chain_inds.choose(&mut rng).unwrap();
+            TurnAction::CreateChain(*chain_idx)
+        }
+        TurnPhase::PickWinningChain(choices, _) => {
+            let chain_idx = choices.choose(&mut rng).unwrap();
+            TurnAction::PickWinningChain(*chain_idx)
+        }
+        TurnPhase::DistributeBonuses(_, _, _) => TurnAction::AcceptBonus,
+        TurnPhase::ResolveMerger(_winner_idx, loser_inds, player_idx) => {
+            let loser_idx = loser_inds[0];
+            let loser_shares = game.players[*player_idx].stocks[loser_idx];
+            // TODO: Enable trading shares as well as selling them.
+            let num_sold = rng.gen_range(0..=loser_shares);
+            TurnAction::ResolveMerger(num_sold, 0)
+        }
+        TurnPhase::BuyStock(buyable_amounts) => {
+            let my_cash = game.players[game.turn_state.player].cash;
+            // Add one index for each buyable share.
+            let mut buyable_shares = Vec::new();
+            for (i, &amount) in buyable_amounts.iter().enumerate() {
+                let price = game.stock_price(i);
+                if amount > 0 && price < my_cash {
+                    let max_shares = (my_cash / price).min(3).min(amount);
+                    for _ in 0..max_shares {
+                        buyable_shares.push(i);
                     }
                 }
-                // Pick up to 3 random buyable shares and buy them, unless we
-                // run out of cash first.
-                let mut buy_order = [0; MAX_NUM_CHAINS];
-                let mut buy_price = 0;
-                for &chain_idx in buyable_shares.choose_multiple(&mut rng, 3) {
-                    buy_price += game.stock_price(chain_idx);
-                    if buy_price > my_cash {
-                        break;
-                    }
-                    buy_order[chain_idx] += 1;
+            }
+            // Pick up to 3 random buyable shares and buy them, unless we
+            // run out of cash first.
+            let mut buy_order = [0; MAX_NUM_CHAINS];
+            let mut buy_price = 0;
+            for &chain_idx in buyable_shares.choose_multiple(&mut rng, 3) {
+                buy_price += game.stock_price(chain_idx);
+                if buy_price > my_cash {
+                    break;
                 }
-                TurnAction::BuyStock(buy_order)
+                buy_order[chain_idx] += 1;
             }
-            TurnPhase::GameOver(_) => TurnAction::PlaceTile(0),
+            TurnAction::BuyStock(buy_order)
         }
+        TurnPhase::GameOver(_) => TurnAction::PlaceTile(0),
     }
 }
 
@@ -78,8 +120,9 @@ fn chain_with_most_shares(game: &GameState, chain_inds: &[usize]) -> usize {
     *chain_inds.iter().max_by_key(|&&i| my_stocks[i]).unwrap()
 }
 
-#[derive(Default)]
-struct BasicAgent;
+struct BasicAgent {
+    random: RandomAgent,
+}
 impl Agent for BasicAgent {
     fn choose_action(&self, game: &GameState) -> TurnAction {
         match &game.turn_state.phase {
@@ -98,7 +141,7 @@ impl Agent for BasicAgent {
             TurnPhase::PickWinningChain(choices, _) => {
                 TurnAction::PickWinningChain(chain_with_most_shares(game, choices))
             }
-            _ => RandomAgent.choose_action(game),
+            _ => self.random.choose_action(game),
         }
     }
 }
@@ -119,7 +162,7 @@ mod tests {
             "Q".to_owned(),
         ];
         let mut rng = rand::thread_rng();
-        GameState::new(2, &mut rng, chain_names)
+        GameState::new(2, &mut rng, chain_names).unwrap()
     }
 
     #[test]
@@ -145,4 +188,23 @@ mod tests {
         let action = ai.choose_action(&game);
         assert!(matches!(action, TurnAction::PlaceTile(0)), "{:?}", action);
     }
+
+    #[test]
+    fn seeded_agents_are_deterministic() {
+        let play = |seed| {
+            let mut game = GameState::from_seed(3, 9, crate::game::default_chain_names()).unwrap();
+            let ai = create_seeded_agent(0, seed);
+            let mut actions = Vec::new();
+            loop {
+                let action = ai.choose_action(&game);
+                actions.push(action.clone());
+                if game.take_turn(action).unwrap() {
+                    break;
+                }
+            }
+            actions
+        };
+        assert_eq!(play(1), play(1));
+        assert_ne!(play(1), play(2));
+    }
 }
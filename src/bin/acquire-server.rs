@@ -0,0 +1,14 @@
+// Hosts Acquire games over TCP. See src/server.rs for the protocol.
+//
+// Usage: acquire-server [ADDRESS], where ADDRESS defaults to 127.0.0.1:7878.
+use acquire::server::Server;
+use std::net::TcpListener;
+
+fn main() -> std::io::Result<()> {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:7878".to_owned());
+    let listener = TcpListener::bind(&addr)?;
+    eprintln!("Listening on {}", listener.local_addr()?);
+    Server::new().serve(listener)
+}
@@ -0,0 +1,139 @@
+// A set of grid cells, packed into the low NUM_TILES bits of a u128.
+// Bit i corresponds to Tile::from_index(i), so rows are runs of GRID_WIDTH bits.
+use crate::game::{Tile, GRID_WIDTH, NUM_TILES};
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not};
+
+const ALL_CELLS: u128 = (1 << NUM_TILES) - 1;
+// Cells in the first and last column of every row.
+const FIRST_COLUMN: u128 = column_mask(0);
+const LAST_COLUMN: u128 = column_mask(GRID_WIDTH - 1);
+
+const fn column_mask(col: usize) -> u128 {
+    let mut mask = 0;
+    let mut idx = col;
+    while idx < NUM_TILES {
+        mask |= 1 << idx;
+        idx += GRID_WIDTH;
+    }
+    mask
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct Bitboard(u128);
+impl Bitboard {
+    pub const EMPTY: Self = Bitboard(0);
+    pub const ALL: Self = Bitboard(ALL_CELLS);
+
+    pub fn from_tile(tile: Tile) -> Self {
+        Bitboard(1 << tile.to_index())
+    }
+    pub fn contains(self, tile: Tile) -> bool {
+        self.0 & (1 << tile.to_index()) != 0
+    }
+    pub fn insert(&mut self, tile: Tile) {
+        self.0 |= 1 << tile.to_index();
+    }
+    pub fn remove(&mut self, tile: Tile) {
+        self.0 &= !(1 << tile.to_index());
+    }
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+    pub fn count(self) -> usize {
+        self.0.count_ones() as usize
+    }
+    // Cells orthogonally adjacent to any cell in the set, not including the
+    // cells of the set itself (unless they neighbor another cell in the set).
+    pub fn neighbors(self) -> Self {
+        let b = self.0;
+        let up = b >> GRID_WIDTH;
+        let down = (b << GRID_WIDTH) & ALL_CELLS;
+        let left = (b & !FIRST_COLUMN) >> 1;
+        let right = (b & !LAST_COLUMN) << 1;
+        Bitboard(up | down | left | right)
+    }
+    // Iterates over the cells in the set, in row-major order.
+    pub fn tiles(self) -> impl Iterator<Item = Tile> {
+        let mut bits = self.0;
+        std::iter::from_fn(move || {
+            if bits == 0 {
+                return None;
+            }
+            let idx = bits.trailing_zeros() as usize;
+            bits &= bits - 1;
+            Tile::from_index(idx)
+        })
+    }
+}
+impl BitAnd for Bitboard {
+    type Output = Self;
+    fn bitand(self, rhs: Self) -> Self {
+        Bitboard(self.0 & rhs.0)
+    }
+}
+impl BitAndAssign for Bitboard {
+    fn bitand_assign(&mut self, rhs: Self) {
+        self.0 &= rhs.0;
+    }
+}
+impl BitOr for Bitboard {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Bitboard(self.0 | rhs.0)
+    }
+}
+impl BitOrAssign for Bitboard {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+impl Not for Bitboard {
+    type Output = Self;
+    fn not(self) -> Self {
+        Bitboard(!self.0 & ALL_CELLS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tiles(names: &[&str]) -> Bitboard {
+        let mut b = Bitboard::EMPTY;
+        for name in names {
+            b.insert(name.parse().unwrap());
+        }
+        b
+    }
+
+    #[test]
+    fn finds_neighbors() {
+        assert_eq!(tiles(&["1-A"]).neighbors(), tiles(&["2-A", "1-B"]));
+        assert_eq!(
+            tiles(&["5-E"]).neighbors(),
+            tiles(&["5-D", "4-E", "6-E", "5-F"])
+        );
+        // No wrapping between rows, or past the edges of the grid.
+        assert_eq!(tiles(&["12-A"]).neighbors(), tiles(&["11-A", "12-B"]));
+        assert_eq!(tiles(&["1-B"]).neighbors(), tiles(&["1-A", "2-B", "1-C"]));
+        assert_eq!(tiles(&["12-I"]).neighbors(), tiles(&["12-H", "11-I"]));
+        assert_eq!(Bitboard::ALL.neighbors(), Bitboard::ALL);
+    }
+
+    #[test]
+    fn set_operations() {
+        let mut b = tiles(&["3-C", "7-G"]);
+        assert_eq!(b.count(), 2);
+        assert!(b.contains("3-C".parse().unwrap()));
+        b.remove("3-C".parse().unwrap());
+        assert_eq!(b, tiles(&["7-G"]));
+        assert_eq!((!b).count(), NUM_TILES - 1);
+        assert!((b & !b).is_empty());
+        assert_eq!(
+            tiles(&["1-A", "12-I", "6-E"])
+                .tiles()
+                .collect::<Vec<Tile>>(),
+            ["1-A", "6-E", "12-I"].map(|s| s.parse::<Tile>().unwrap())
+        );
+    }
+}
@@ -0,0 +1,195 @@
+// Time controls for timed games. Like a chess clock, each player has a bank
+// of time that runs down while they're the acting player, and gains an
+// increment after each of their actions. A player who runs out of time has
+// an action chosen for them, and plays on with only the increment.
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::game::{GameState, TurnAction, TurnPhase, TurnState, MAX_NUM_CHAINS};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct TimeControl {
+    pub initial_ms: u64,
+    pub increment_ms: u64,
+}
+
+// What to play for a player who runs out of time.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub enum TimeoutAction {
+    // Place the first playable tile, found or merge into the first chain
+    // offered, accept bonuses, keep all shares and buy nothing.
+    #[default]
+    Passive,
+    // Like Passive, but sell every share of a defunct chain.
+    SellAll,
+}
+
+impl TimeoutAction {
+    // The action for the acting player, given the turn state and their
+    // stocks.
+    pub fn action_for(
+        self,
+        turn_state: &TurnState,
+        stocks: &[usize; MAX_NUM_CHAINS],
+    ) -> TurnAction {
+        match &turn_state.phase {
+            TurnPhase::PlaceTile(valid) => TurnAction::PlaceTile(valid[0]),
+            TurnPhase::CreateChain(_, valid) => TurnAction::CreateChain(valid[0]),
+            TurnPhase::PickWinningChain(valid, _) => TurnAction::PickWinningChain(valid[0]),
+            TurnPhase::DistributeBonuses(_, _, _) => TurnAction::AcceptBonus,
+            TurnPhase::ResolveMerger(_, losers, _) => match self {
+                TimeoutAction::Passive => TurnAction::ResolveMerger(0, 0),
+                TimeoutAction::SellAll => TurnAction::ResolveMerger(stocks[losers[0]], 0),
+            },
+            TurnPhase::BuyStock(_) | TurnPhase::GameOver(_) => {
+                TurnAction::BuyStock(Default::default())
+            }
+        }
+    }
+    pub fn action(self, game: &GameState) -> TurnAction {
+        let stocks = &game.players[game.acting_player()].stocks;
+        self.action_for(&game.turn_state, stocks)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Clock {
+    control: TimeControl,
+    remaining: Vec<Duration>,
+    // The player whose time is running, and since when.
+    running: Option<(usize, Instant)>,
+    // Number of times each player has run out of time.
+    timeouts: Vec<usize>,
+}
+
+impl Clock {
+    // Starts the clock of the game's acting player.
+    pub fn new(game: &GameState, control: TimeControl, now: Instant) -> Self {
+        let n = game.players.len();
+        let mut clock = Self {
+            control,
+            remaining: vec![Duration::from_millis(control.initial_ms); n],
+            running: None,
+            timeouts: vec![0; n],
+        };
+        clock.start(game, now);
+        clock
+    }
+    fn start(&mut self, game: &GameState, now: Instant) {
+        let over = matches!(game.turn_state.phase, TurnPhase::GameOver(_));
+        self.running = (!over).then_some((game.acting_player(), now));
+    }
+    // Time the player has left, counting down if it's their clock running.
+    pub fn remaining(&self, player: usize, now: Instant) -> Duration {
+        match self.running {
+            Some((p, since)) if p == player => {
+                self.remaining[p].saturating_sub(now.saturating_duration_since(since))
+            }
+            _ => self.remaining[player],
+        }
+    }
+    pub fn remaining_ms(&self, now: Instant) -> Vec<u64> {
+        (0..self.remaining.len())
+            .map(|p| self.remaining(p, now).as_millis() as u64)
+            .collect()
+    }
+    pub fn timeouts(&self) -> &[usize] {
+        &self.timeouts
+    }
+    // The player whose time is running, if they've run out of it.
+    pub fn flagged(&self, now: Instant) -> Option<usize> {
+        let (player, _) = self.running?;
+        self.remaining(player, now).is_zero().then_some(player)
+    }
+    // Applies an action for the acting player, if they still have time, then
+    // starts the clock of whoever acts next.
+    pub fn take_turn(
+        &mut self,
+        game: &mut GameState,
+        action: TurnAction,
+        now: Instant,
+    ) -> Result<bool, String> {
+        if let Some(player) = self.flagged(now) {
+            return Err(format!("P{} is out of time", player));
+        }
+        let over = game.take_turn(action)?;
+        self.switch(game, now);
+        Ok(over)
+    }
+    // Plays the timeout action if the acting player has run out of time,
+    // returning the action played.
+    pub fn check_timeout(
+        &mut self,
+        game: &mut GameState,
+        on_timeout: TimeoutAction,
+        now: Instant,
+    ) -> Result<Option<TurnAction>, String> {
+        let Some(player) = self.flagged(now) else {
+            return Ok(None);
+        };
+        let action = on_timeout.action(game);
+        game.take_turn(action.clone())?;
+        self.timeouts[player] += 1;
+        self.switch(game, now);
+        Ok(Some(action))
+    }
+    fn switch(&mut self, game: &GameState, now: Instant) {
+        if let Some((player, _)) = self.running {
+            let increment = Duration::from_millis(self.control.increment_ms);
+            self.remaining[player] = self.remaining(player, now) + increment;
+        }
+        self.start(game, now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::default_chain_names;
+
+    #[test]
+    fn runs_down_and_times_out() {
+        let mut game = GameState::from_seed(2, 6, default_chain_names()).unwrap();
+        let control = TimeControl {
+            initial_ms: 1000,
+            increment_ms: 100,
+        };
+        let start = Instant::now();
+        let mut clock = Clock::new(&game, control, start);
+        let p = game.acting_player();
+        let later = start + Duration::from_millis(300);
+        assert_eq!(clock.remaining(p, later), Duration::from_millis(700));
+        assert_eq!(clock.remaining(1 - p, later), Duration::from_millis(1000));
+        let action = TimeoutAction::Passive.action(&game);
+        clock.take_turn(&mut game, action, later).unwrap();
+        assert_eq!(clock.remaining_ms(later)[p], 800);
+
+        // Run the next acting player out of time.
+        let q = game.acting_player();
+        let flag = later + Duration::from_millis(clock.remaining_ms(later)[q]);
+        assert_eq!(
+            clock.check_timeout(&mut game, TimeoutAction::Passive, later),
+            Ok(None)
+        );
+        let action = TimeoutAction::Passive.action(&game);
+        let err = clock.take_turn(&mut game.clone(), action.clone(), flag);
+        assert_eq!(err, Err(format!("P{} is out of time", q)));
+        let played = clock.check_timeout(&mut game, TimeoutAction::Passive, flag);
+        assert_eq!(played, Ok(Some(action)));
+        assert_eq!(clock.remaining_ms(flag)[q], 100);
+        assert_eq!(clock.timeouts()[q], 1);
+    }
+
+    #[test]
+    fn sells_all_on_timeout() {
+        let game = GameState::from_seed(2, 6, default_chain_names()).unwrap();
+        let mut turn_state = game.turn_state.clone();
+        turn_state.phase = TurnPhase::ResolveMerger(0, vec![3], 1);
+        let mut stocks = [0; MAX_NUM_CHAINS];
+        stocks[3] = 5;
+        let sell = TimeoutAction::SellAll.action_for(&turn_state, &stocks);
+        assert_eq!(sell, TurnAction::ResolveMerger(5, 0));
+        let keep = TimeoutAction::Passive.action_for(&turn_state, &stocks);
+        assert_eq!(keep, TurnAction::ResolveMerger(0, 0));
+    }
+}
@@ -0,0 +1,607 @@
+// Compact, fixed-size binary encodings of GameState and PlayerView.
+//
+// Chain names are not encoded, so they must be supplied when decoding.
+// All multi-byte integers are little-endian, and unused list slots are filled
+// with NONE. The layouts are:
+//
+// GameState (GAME_STATE_BYTES):
+//   grid           54  two cells per byte, low nibble first, row-major
+//   chain sizes     7
+//   stock market    7
+//   num players     1
+//   turn player     1
+//   players        72  MAX_NUM_PLAYERS x (cash u32, stocks 7, num tiles 1)
+//   turn phase     25  tag, then a tag-specific payload (see encode_phase)
+//   unclaimed       1  count
+//   tiles         108  each player's hand in turn, then the unclaimed tiles
+//                      as stored (drawn from the end)
+//
+// Hands usually hold 6 tiles, but grow by one every turn a player can't
+// place a tile, so they're sized by their count rather than given fixed
+// slots. No tile can be in two places, so they all fit in NUM_TILES.
+//
+// PlayerView (PLAYER_VIEW_BYTES):
+//   grid, chain sizes, stock market, num players, turn player (as above)
+//   viewer          1
+//   players        72  as above
+//   own tiles     108
+//   turn phase     25
+//   unclaimed       1  count only
+use crate::game::{
+    BoardState, GameState, GridCell, Player, PlayerView, PublicPlayer, Tile, TurnPhase, TurnState,
+    DUMMY_CHAIN_INDEX, GRID_HEIGHT, GRID_WIDTH, MAX_NUM_CHAINS, MAX_NUM_PLAYERS, NUM_TILES,
+};
+
+const NONE: u8 = 0xFF;
+const BOARD_BYTES: usize = NUM_TILES / 2 + 2 * MAX_NUM_CHAINS + 2;
+const PLAYER_BYTES: usize = 4 + MAX_NUM_CHAINS + 1;
+const PHASE_BYTES: usize = 25;
+pub const GAME_STATE_BYTES: usize =
+    BOARD_BYTES + MAX_NUM_PLAYERS * PLAYER_BYTES + PHASE_BYTES + 1 + NUM_TILES;
+pub const PLAYER_VIEW_BYTES: usize =
+    BOARD_BYTES + 1 + MAX_NUM_PLAYERS * PLAYER_BYTES + NUM_TILES + PHASE_BYTES + 1;
+
+pub fn encode_game_state(game: &GameState) -> [u8; GAME_STATE_BYTES] {
+    let mut w = Writer::new();
+    encode_board(
+        &mut w,
+        &game.board,
+        game.players.len(),
+        game.turn_state.player,
+    );
+    for i in 0..MAX_NUM_PLAYERS {
+        match game.players.get(i) {
+            Some(p) => {
+                w.u32(p.cash);
+                w.counts(&p.stocks);
+                w.u8(p.tiles.len());
+            }
+            None => w.skip(PLAYER_BYTES),
+        }
+    }
+    encode_phase(&mut w, &game.turn_state.phase);
+    w.u8(game.unclaimed_tiles.len());
+    let tiles = game
+        .players
+        .iter()
+        .flat_map(|p| &p.tiles)
+        .chain(&game.unclaimed_tiles)
+        .copied()
+        .collect::<Vec<Tile>>();
+    w.tiles(&tiles, NUM_TILES);
+    w.finish()
+}
+
+pub fn decode_game_state(
+    bytes: &[u8],
+    chain_names: [String; MAX_NUM_CHAINS],
+) -> Result<GameState, String> {
+    let mut r = Reader::new(bytes, GAME_STATE_BYTES)?;
+    let (board, num_players, turn_player) = decode_board(&mut r, chain_names)?;
+    let mut players = Vec::with_capacity(num_players);
+    let mut hand_sizes = Vec::with_capacity(num_players);
+    for i in 0..MAX_NUM_PLAYERS {
+        let cash = r.u32();
+        let stocks = r.counts();
+        let num_tiles = r.u8();
+        if i < num_players {
+            players.push(Player {
+                cash,
+                stocks,
+                tiles: Vec::new(),
+            });
+            hand_sizes.push(num_tiles);
+        }
+    }
+    let phase = decode_phase(&mut r, num_players)?;
+    let num_unclaimed = r.u8();
+    let mut tiles = r.tiles(NUM_TILES)?.into_iter();
+    let expected = hand_sizes.iter().sum::<usize>() + num_unclaimed;
+    if tiles.len() != expected {
+        return Err(format!(
+            "Expected {} tiles in hands or unclaimed, found {}",
+            expected,
+            tiles.len()
+        ));
+    }
+    for (player, num_tiles) in players.iter_mut().zip(hand_sizes) {
+        player.tiles = tiles.by_ref().take(num_tiles).collect();
+    }
+    let unclaimed_tiles = tiles.collect();
+    let turn_state = TurnState {
+        player: turn_player,
+        phase,
+    };
+    GameState::from_parts(board, players, turn_state, unclaimed_tiles)
+}
+
+pub fn encode_player_view(view: &PlayerView) -> [u8; PLAYER_VIEW_BYTES] {
+    let mut w = Writer::new();
+    encode_board(
+        &mut w,
+        &view.board,
+        view.players.len(),
+        view.turn_state.player,
+    );
+    w.u8(view.player);
+    for i in 0..MAX_NUM_PLAYERS {
+        match view.players.get(i) {
+            Some(p) => {
+                w.u32(p.cash);
+                w.counts(&p.stocks);
+                w.u8(p.num_tiles);
+            }
+            None => w.skip(PLAYER_BYTES),
+        }
+    }
+    w.tiles(&view.tiles, NUM_TILES);
+    encode_phase(&mut w, &view.turn_state.phase);
+    w.u8(view.num_unclaimed_tiles);
+    w.finish()
+}
+
+pub fn decode_player_view(
+    bytes: &[u8],
+    chain_names: [String; MAX_NUM_CHAINS],
+) -> Result<PlayerView, String> {
+    let mut r = Reader::new(bytes, PLAYER_VIEW_BYTES)?;
+    let (board, num_players, turn_player) = decode_board(&mut r, chain_names)?;
+    let player = r.u8();
+    if player >= num_players {
+        return Err(format!("Invalid viewing player: {}", player));
+    }
+    let mut players = Vec::with_capacity(num_players);
+    for i in 0..MAX_NUM_PLAYERS {
+        let cash = r.u32();
+        let stocks = r.counts();
+        let num_tiles = r.u8();
+        if i < num_players {
+            players.push(PublicPlayer {
+                cash,
+                stocks,
+                num_tiles,
+            });
+        }
+    }
+    let tiles = r.tiles(NUM_TILES)?;
+    if tiles.len() != players[player].num_tiles {
+        return Err(format!(
+            "Expected {} tiles in hand, found {}",
+            players[player].num_tiles,
+            tiles.len()
+        ));
+    }
+    let phase = decode_phase(&mut r, num_players)?;
+    let view = PlayerView {
+        player,
+        board,
+        players,
+        tiles,
+        turn_state: TurnState {
+            player: turn_player,
+            phase,
+        },
+        num_unclaimed_tiles: r.u8(),
+    };
+    view.validate()?;
+    Ok(view)
+}
+
+fn encode_board(w: &mut Writer, board: &BoardState, num_players: usize, turn_player: usize) {
+    let cells = board.grid.iter().flatten().collect::<Vec<_>>();
+    for pair in cells.chunks(2) {
+        w.u8(pair[0].ordinal() | (pair[1].ordinal() << 4));
+    }
+    w.counts(&board.chain_sizes);
+    w.counts(&board.stock_market);
+    w.u8(num_players);
+    w.u8(turn_player);
+}
+
+fn decode_board(
+    r: &mut Reader,
+    chain_names: [String; MAX_NUM_CHAINS],
+) -> Result<(BoardState, usize, usize), String> {
+    let mut cells = Vec::with_capacity(NUM_TILES);
+    for _ in 0..NUM_TILES / 2 {
+        let byte = r.u8();
+        cells.push(nibble_to_cell(byte & 0xF)?);
+        cells.push(nibble_to_cell(byte >> 4)?);
+    }
+    let mut grid = [[GridCell::Empty; GRID_WIDTH]; GRID_HEIGHT];
+    for (i, cell) in cells.into_iter().enumerate() {
+        let tile = Tile::from_index(i).unwrap();
+        grid[tile.0][tile.1] = cell;
+    }
+    let chain_sizes = r.counts();
+    let board = BoardState::from_parts(grid, chain_sizes, r.counts(), chain_names)?;
+    let num_players = r.u8();
+    let turn_player = r.u8();
+    if !(2..=MAX_NUM_PLAYERS).contains(&num_players) || turn_player >= num_players {
+        return Err(format!(
+            "Invalid players: {} (turn player {})",
+            num_players, turn_player
+        ));
+    }
+    Ok((board, num_players, turn_player))
+}
+
+fn nibble_to_cell(nibble: usize) -> Result<GridCell, String> {
+    match nibble {
+        0 => Ok(GridCell::Empty),
+        1 => Ok(GridCell::Hotel),
+        2..=8 => Ok(GridCell::from_chain_idx(nibble - 2)),
+        9 => Ok(GridCell::Dummy),
+        _ => Err(format!("Invalid grid cell: {}", nibble)),
+    }
+}
+
+// Phase tags, followed by their payloads:
+//   0 PlaceTile          playable hand indices, as a bitmask (14)
+//   1 CreateChain        tile, available chains (7)
+//   2 PickWinningChain   winner choices (7), merging chains (7)
+//   3 DistributeBonuses  winner, remaining chains (7), bonuses in $100s (6 x u16)
+//   4 ResolveMerger      winner, remaining chains (7), player
+//   5 BuyStock           buyable amounts (7)
+//   6 GameOver           final values (6 x u32)
+// The winner of the end-of-game "merger" is encoded as NONE.
+fn encode_phase(w: &mut Writer, phase: &TurnPhase) {
+    let start = w.pos;
+    match phase {
+        TurnPhase::PlaceTile(tile_inds) => {
+            w.u8(0);
+            w.bits(tile_inds, NUM_TILES);
+        }
+        TurnPhase::CreateChain(tile, chains) => {
+            w.u8(1);
+            w.u8(tile.to_index());
+            w.list(chains, MAX_NUM_CHAINS);
+        }
+        TurnPhase::PickWinningChain(choices, merging) => {
+            w.u8(2);
+            w.list(choices, MAX_NUM_CHAINS);
+            w.list(merging, MAX_NUM_CHAINS);
+        }
+        TurnPhase::DistributeBonuses(winner, chains, bonuses) => {
+            w.u8(3);
+            w.chain(*winner);
+            w.list(chains, MAX_NUM_CHAINS);
+            for i in 0..MAX_NUM_PLAYERS {
+                let bonus = bonuses.get(i).copied().unwrap_or(0);
+                assert!(bonus.is_multiple_of(100), "Bonus is not a multiple of $100");
+                w.u16(bonus / 100);
+            }
+        }
+        TurnPhase::ResolveMerger(winner, chains, player) => {
+            w.u8(4);
+            w.chain(*winner);
+            w.list(chains, MAX_NUM_CHAINS);
+            w.u8(*player);
+        }
+        TurnPhase::BuyStock(buyable) => {
+            w.u8(5);
+            w.counts(buyable);
+        }
+        TurnPhase::GameOver(values) => {
+            w.u8(6);
+            for i in 0..MAX_NUM_PLAYERS {
+                w.u32(values.get(i).copied().unwrap_or(0));
+            }
+        }
+    }
+    w.skip(PHASE_BYTES - (w.pos - start));
+}
+
+fn decode_phase(r: &mut Reader, num_players: usize) -> Result<TurnPhase, String> {
+    let start = r.pos;
+    let phase = match r.u8() {
+        0 => TurnPhase::PlaceTile(r.bits(NUM_TILES)),
+        1 => {
+            let tile = Tile::from_index(r.u8()).ok_or("Invalid tile in phase")?;
+            TurnPhase::CreateChain(tile, r.list(MAX_NUM_CHAINS))
+        }
+        2 => TurnPhase::PickWinningChain(r.list(MAX_NUM_CHAINS), r.list(MAX_NUM_CHAINS)),
+        3 => {
+            let winner = r.chain();
+            let chains = r.list(MAX_NUM_CHAINS);
+            let bonuses = (0..MAX_NUM_PLAYERS).map(|_| r.u16() * 100).collect();
+            TurnPhase::DistributeBonuses(winner, chains, truncated(bonuses, num_players))
+        }
+        4 => {
+            let winner = r.chain();
+            TurnPhase::ResolveMerger(winner, r.list(MAX_NUM_CHAINS), r.u8())
+        }
+        5 => TurnPhase::BuyStock(r.counts()),
+        6 => {
+            let values = (0..MAX_NUM_PLAYERS).map(|_| r.u32()).collect();
+            TurnPhase::GameOver(truncated(values, num_players))
+        }
+        tag => return Err(format!("Invalid phase tag: {}", tag)),
+    };
+    r.pos = start + PHASE_BYTES;
+    Ok(phase)
+}
+
+fn truncated(mut values: Vec<usize>, len: usize) -> Vec<usize> {
+    values.truncate(len);
+    values
+}
+
+struct Writer {
+    buf: Vec<u8>,
+    pos: usize,
+}
+impl Writer {
+    fn new() -> Self {
+        Self {
+            buf: Vec::with_capacity(GAME_STATE_BYTES),
+            pos: 0,
+        }
+    }
+    fn u8(&mut self, x: usize) {
+        self.buf
+            .push(u8::try_from(x).expect("Value too large to encode"));
+        self.pos += 1;
+    }
+    fn u16(&mut self, x: usize) {
+        let x = u16::try_from(x).expect("Value too large to encode");
+        self.buf.extend_from_slice(&x.to_le_bytes());
+        self.pos += 2;
+    }
+    fn u32(&mut self, x: usize) {
+        let x = u32::try_from(x).expect("Value too large to encode");
+        self.buf.extend_from_slice(&x.to_le_bytes());
+        self.pos += 4;
+    }
+    fn skip(&mut self, n: usize) {
+        self.buf.resize(self.buf.len() + n, NONE);
+        self.pos += n;
+    }
+    fn chain(&mut self, chain_idx: usize) {
+        if chain_idx == DUMMY_CHAIN_INDEX {
+            self.skip(1);
+        } else {
+            self.u8(chain_idx);
+        }
+    }
+    fn counts(&mut self, counts: &[usize; MAX_NUM_CHAINS]) {
+        for &x in counts {
+            self.u8(x);
+        }
+    }
+    fn list(&mut self, values: &[usize], len: usize) {
+        assert!(values.len() <= len, "Too many values to encode");
+        for &x in values {
+            self.u8(x);
+        }
+        self.skip(len - values.len());
+    }
+    fn tiles(&mut self, tiles: &[Tile], len: usize) {
+        let inds = tiles.iter().map(|t| t.to_index()).collect::<Vec<usize>>();
+        self.list(&inds, len);
+    }
+    // Writes the set of values below len, one bit each, low bit first.
+    fn bits(&mut self, values: &[usize], len: usize) {
+        let mut bytes = vec![0; len.div_ceil(8)];
+        for &x in values {
+            assert!(x < len, "Value too large to encode");
+            bytes[x / 8] |= 1 << (x % 8);
+        }
+        for byte in bytes {
+            self.u8(byte);
+        }
+    }
+    fn finish<const N: usize>(self) -> [u8; N] {
+        self.buf.try_into().expect("Encoded size mismatch")
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8], expected_len: usize) -> Result<Self, String> {
+        if bytes.len() != expected_len {
+            return Err(format!(
+                "Expected {} bytes, got {}",
+                expected_len,
+                bytes.len()
+            ));
+        }
+        Ok(Self { bytes, pos: 0 })
+    }
+    fn u8(&mut self) -> usize {
+        self.pos += 1;
+        self.bytes[self.pos - 1] as usize
+    }
+    fn u16(&mut self) -> usize {
+        self.pos += 2;
+        u16::from_le_bytes([self.bytes[self.pos - 2], self.bytes[self.pos - 1]]) as usize
+    }
+    fn u32(&mut self) -> usize {
+        self.pos += 4;
+        u32::from_le_bytes(self.bytes[self.pos - 4..self.pos].try_into().unwrap()) as usize
+    }
+    fn chain(&mut self) -> usize {
+        match self.u8() {
+            x if x == NONE as usize => DUMMY_CHAIN_INDEX,
+            x => x,
+        }
+    }
+    fn counts(&mut self) -> [usize; MAX_NUM_CHAINS] {
+        let mut counts = [0; MAX_NUM_CHAINS];
+        for x in counts.iter_mut() {
+            *x = self.u8();
+        }
+        counts
+    }
+    fn list(&mut self, len: usize) -> Vec<usize> {
+        (0..len)
+            .map(|_| self.u8())
+            .filter(|&x| x != NONE as usize)
+            .collect()
+    }
+    fn bits(&mut self, len: usize) -> Vec<usize> {
+        let bytes = (0..len.div_ceil(8)).map(|_| self.u8()).collect::<Vec<_>>();
+        (0..len)
+            .filter(|&x| bytes[x / 8] & (1 << (x % 8)) != 0)
+            .collect()
+    }
+    fn tiles(&mut self, len: usize) -> Result<Vec<Tile>, String> {
+        self.list(len)
+            .into_iter()
+            .map(|i| Tile::from_index(i).ok_or_else(|| format!("Invalid tile index: {}", i)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::create_agent;
+    use crate::game::tests::MERGER_STATE;
+    use crate::game::{default_chain_names, TurnAction};
+
+    // The shared merger position, after 4-C joins K and L under a dummy cell.
+    fn merger_game() -> GameState {
+        let mut game = MERGER_STATE.parse::<GameState>().unwrap();
+        game.take_turn(TurnAction::PlaceTile(1)).unwrap();
+        game
+    }
+
+    #[test]
+    fn layout_sizes() {
+        assert_eq!(BOARD_BYTES, 70);
+        assert_eq!(GAME_STATE_BYTES, 276);
+        assert_eq!(PLAYER_VIEW_BYTES, 277);
+    }
+
+    #[test]
+    fn game_state_layout() {
+        let game = merger_game();
+        let bytes = encode_game_state(&game);
+        // Row C starts at cell 24, so 1-C and 2-C share byte 12.
+        assert_eq!(bytes[12..15], [0x20, 0x92, 0x33]);
+        // Chain sizes, then stock market.
+        assert_eq!(bytes[54..61], [2, 2, 0, 0, 0, 0, 0]);
+        assert_eq!(bytes[61..68], [24, 23, 24, 25, 25, 25, 25]);
+        // Num players, turn player.
+        assert_eq!(bytes[68..70], [2, 0]);
+        // Player 0: cash, stocks, num tiles.
+        assert_eq!(bytes[70..74], 6000u32.to_le_bytes());
+        assert_eq!(bytes[74..81], [1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(bytes[81], 2);
+        assert_eq!(bytes[93], 2);
+        // Unused player slots.
+        assert!(bytes[94..142].iter().all(|&b| b == NONE));
+        // PickWinningChain phase: the choices, then the merging chains.
+        assert_eq!(bytes[142..146], [2, 0, 1, NONE]);
+        assert_eq!(bytes[150..153], [0, 1, NONE]);
+        // Unclaimed tiles: everything not on the board or in a hand.
+        assert_eq!(bytes[167] as usize, NUM_TILES - 5 - 4);
+        // Both hands, then the unclaimed tiles.
+        assert_eq!(bytes[168..176], [0, 104, 13, 91, 1, 2, 3, 4]);
+        assert!(bytes[271..].iter().all(|&b| b == NONE));
+    }
+
+    #[test]
+    fn player_view_layout() {
+        let game = merger_game();
+        let bytes = encode_player_view(&game.player_view(1));
+        // Same board prefix as the full game state.
+        assert_eq!(bytes[..70], encode_game_state(&game)[..70]);
+        // Viewer.
+        assert_eq!(bytes[70], 1);
+        // Player 0: cash, stocks, num tiles.
+        assert_eq!(bytes[71..75], 6000u32.to_le_bytes());
+        assert_eq!(bytes[75..82], [1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(bytes[82], 2);
+        // Own tiles.
+        assert_eq!(bytes[143..147], [13, 91, NONE, NONE]);
+        // Phase and unclaimed tile count.
+        assert_eq!(bytes[251], 2);
+        assert_eq!(bytes[276] as usize, NUM_TILES - 5 - 4);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let names = default_chain_names;
+        assert!(decode_game_state(&[0; 10], names()).is_err());
+        assert!(decode_player_view(&[0; GAME_STATE_BYTES], names()).is_err());
+        let game = merger_game();
+        let mut bytes = encode_game_state(&game);
+        bytes[0] = 0x0A;
+        assert_eq!(
+            decode_game_state(&bytes, names()).err(),
+            Some("Invalid grid cell: 10".to_owned())
+        );
+        // Views are checked like game states.
+        let view = game.player_view(1);
+        let phases = [
+            (
+                TurnPhase::ResolveMerger(0, vec![], 1),
+                "No chains left to merge",
+            ),
+            (
+                TurnPhase::ResolveMerger(0, vec![1], 5),
+                "Invalid merger player: 5",
+            ),
+            (TurnPhase::PlaceTile(vec![2]), "Invalid tile index: 2"),
+        ];
+        for (phase, error) in phases {
+            let mut bad = view.clone();
+            bad.turn_state.phase = phase;
+            let decoded = decode_player_view(&encode_player_view(&bad), names());
+            assert_eq!(decoded.err().as_deref(), Some(error));
+        }
+        let mut bytes = encode_player_view(&view);
+        bytes[68..70].copy_from_slice(&[1, 0]);
+        let decoded = decode_player_view(&bytes, names());
+        assert_eq!(
+            decoded.err().as_deref(),
+            Some("Invalid players: 1 (turn player 0)")
+        );
+    }
+
+    #[test]
+    fn encodes_large_hands() {
+        // Players who can't place a tile still draw one, so hands can grow
+        // past 6 tiles. This deal gets to 7.
+        let mut game = GameState::from_seed(4, 113, default_chain_names()).unwrap();
+        let ai = crate::agent::create_seeded_agent(0, 113);
+        let largest = loop {
+            let largest = (0..4).max_by_key(|&p| game.players[p].tiles.len()).unwrap();
+            if game.players[largest].tiles.len() > 6 {
+                break largest;
+            }
+            assert!(!game.take_turn(ai.choose_action(&game)).unwrap());
+        };
+        let bytes = encode_game_state(&game);
+        let decoded = decode_game_state(&bytes, default_chain_names()).unwrap();
+        assert_eq!(decoded.to_string(), game.to_string());
+        let view = game.player_view(largest);
+        let decoded = decode_player_view(&encode_player_view(&view), default_chain_names());
+        assert_eq!(decoded.unwrap().tiles, view.tiles);
+    }
+
+    #[test]
+    fn full_game_roundtrip() {
+        let mut game = GameState::new(4, &mut rand::thread_rng(), default_chain_names()).unwrap();
+        let ai = create_agent(1);
+        loop {
+            let bytes = encode_game_state(&game);
+            let decoded = decode_game_state(&bytes, default_chain_names()).unwrap();
+            assert_eq!(decoded.to_string(), game.to_string());
+            assert_eq!(encode_game_state(&decoded), bytes);
+            let view = game.player_view(game.turn_state.player);
+            let bytes = encode_player_view(&view);
+            let decoded = decode_player_view(&bytes, default_chain_names()).unwrap();
+            assert_eq!(decoded.players, view.players);
+            assert_eq!(encode_player_view(&decoded), bytes);
+            if game.take_turn(ai.choose_action(&game)).unwrap() {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,518 @@
+// A line-based protocol for bots running as separate programs, modelled on
+// UCI. The host writes commands to the engine's stdin, and the engine replies
+// on its stdout:
+//
+//   host: acquire                   engine: id name <name>, then acquireok
+//   host: isready                   engine: readyok
+//   host: newgame <GameConfig JSON>
+//   host: view <PlayerView JSON>
+//   host: go [time <ms>]            engine: action <TurnAction JSON>
+//   host: quit
+//
+// A view is sent before every go, for the player who needs to act. The time
+// is the engine's budget for that move, if the host has one. Either side
+// ignores lines it doesn't understand, and engines may send "info <text>"
+// lines at any time, e.g. for logging.
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::agent::Agent;
+use crate::env;
+use crate::game::{GameState, PlayerView, TurnAction, MAX_NUM_CHAINS};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct GameConfig {
+    pub num_players: usize,
+    pub chain_names: [String; MAX_NUM_CHAINS],
+}
+
+pub enum HostCommand {
+    Acquire,
+    IsReady,
+    NewGame(GameConfig),
+    View(Box<PlayerView>),
+    Go { time_ms: Option<u64> },
+    Quit,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum EngineReply {
+    Id { name: String },
+    AcquireOk,
+    ReadyOk,
+    Action(TurnAction),
+    Info(String),
+}
+
+fn to_json(value: &impl Serialize) -> String {
+    // Game types always serialize.
+    serde_json::to_string(value).unwrap()
+}
+
+fn from_json<T: serde::de::DeserializeOwned>(s: &str) -> Result<T, String> {
+    serde_json::from_str(s).map_err(|e| e.to_string())
+}
+
+impl std::fmt::Display for HostCommand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HostCommand::Acquire => write!(f, "acquire"),
+            HostCommand::IsReady => write!(f, "isready"),
+            HostCommand::NewGame(config) => write!(f, "newgame {}", to_json(config)),
+            HostCommand::View(view) => write!(f, "view {}", to_json(view)),
+            HostCommand::Go { time_ms: None } => write!(f, "go"),
+            HostCommand::Go { time_ms: Some(ms) } => write!(f, "go time {}", ms),
+            HostCommand::Quit => write!(f, "quit"),
+        }
+    }
+}
+
+impl std::str::FromStr for HostCommand {
+    type Err = String;
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        let (cmd, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match (cmd, rest.trim()) {
+            ("acquire", _) => Ok(HostCommand::Acquire),
+            ("isready", _) => Ok(HostCommand::IsReady),
+            ("newgame", json) => Ok(HostCommand::NewGame(from_json(json)?)),
+            ("view", json) => Ok(HostCommand::View(from_json(json)?)),
+            ("go", "") => Ok(HostCommand::Go { time_ms: None }),
+            ("go", args) => match args.split_once(' ') {
+                Some(("time", ms)) => Ok(HostCommand::Go {
+                    time_ms: Some(ms.trim().parse().map_err(|_| "Invalid time")?),
+                }),
+                _ => Err(format!("Invalid go arguments: {}", args)),
+            },
+            ("quit", _) => Ok(HostCommand::Quit),
+            _ => Err(format!("Unknown command: {}", line)),
+        }
+    }
+}
+
+impl std::fmt::Display for EngineReply {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineReply::Id { name } => write!(f, "id name {}", name),
+            EngineReply::AcquireOk => write!(f, "acquireok"),
+            EngineReply::ReadyOk => write!(f, "readyok"),
+            EngineReply::Action(action) => write!(f, "action {}", to_json(action)),
+            EngineReply::Info(text) => write!(f, "info {}", text),
+        }
+    }
+}
+
+impl std::str::FromStr for EngineReply {
+    type Err = String;
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let line = line.trim();
+        let (cmd, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match (cmd, rest.trim()) {
+            ("id", args) => match args.split_once(' ') {
+                Some(("name", name)) => Ok(EngineReply::Id {
+                    name: name.trim().to_owned(),
+                }),
+                _ => Err(format!("Invalid id: {}", args)),
+            },
+            ("acquireok", _) => Ok(EngineReply::AcquireOk),
+            ("readyok", _) => Ok(EngineReply::ReadyOk),
+            ("action", json) => Ok(EngineReply::Action(from_json(json)?)),
+            ("info", text) => Ok(EngineReply::Info(text.to_owned())),
+            _ => Err(format!("Unknown reply: {}", line)),
+        }
+    }
+}
+
+// Runs an agent as an engine until the host quits or closes the input. The
+// agent plays on a full game dealt from each view, so the tiles it can't see
+// are random.
+pub fn run_engine(
+    agent: &dyn Agent,
+    name: &str,
+    input: impl BufRead,
+    mut output: impl Write,
+    rng: &mut impl Rng,
+) -> std::io::Result<()> {
+    let mut view = None;
+    for line in input.lines() {
+        let reply = match line?.parse() {
+            Ok(HostCommand::Acquire) => {
+                let name = name.to_owned();
+                writeln!(output, "{}", EngineReply::Id { name })?;
+                EngineReply::AcquireOk
+            }
+            Ok(HostCommand::IsReady) => EngineReply::ReadyOk,
+            Ok(HostCommand::NewGame(_)) => {
+                view = None;
+                continue;
+            }
+            Ok(HostCommand::View(v)) => {
+                view = Some(v);
+                continue;
+            }
+            Ok(HostCommand::Go { time_ms }) => match &view {
+                Some(v) => match GameState::from_view(v, rng) {
+                    Ok(game) => EngineReply::Action(match time_ms {
+                        Some(ms) => agent.choose_action_within(&game, Duration::from_millis(ms)),
+                        None => agent.choose_action(&game),
+                    }),
+                    Err(e) => EngineReply::Info(format!("error: {}", e)),
+                },
+                None => EngineReply::Info("error: go without a view".to_owned()),
+            },
+            Ok(HostCommand::Quit) => return Ok(()),
+            Err(e) => EngineReply::Info(format!("error: {}", e)),
+        };
+        writeln!(output, "{}", reply)?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+// How long to wait for a reply beyond the engine's time budget, and for
+// replies that have no budget: the handshake, and moves without a time.
+const REPLY_GRACE: Duration = Duration::from_secs(1);
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct EngineProcess {
+    child: Child,
+    stdin: ChildStdin,
+    // Lines from the engine's stdout, read on their own thread so that
+    // replies can time out.
+    lines: Receiver<Result<String, String>>,
+    // Tiles on the board at the last move, to tell when a new game starts.
+    num_placed: usize,
+    // Set when a reply timed out, as the engine may still send it later.
+    stale: bool,
+    last_error: Option<String>,
+}
+
+impl EngineProcess {
+    fn send(&mut self, cmd: &HostCommand) -> Result<(), String> {
+        writeln!(self.stdin, "{}", cmd)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|e| format!("Failed to write to engine: {}", e))
+    }
+    // Reads the next reply, skipping info and unrecognized lines.
+    fn recv(&mut self, timeout: Duration) -> Result<EngineReply, String> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let wait = deadline.saturating_duration_since(Instant::now());
+            let line = match self.lines.recv_timeout(wait) {
+                Ok(line) => line?,
+                Err(RecvTimeoutError::Timeout) => {
+                    self.stale = true;
+                    return Err(format!("Engine didn't reply within {:?}", timeout));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err("Engine closed its output".to_owned());
+                }
+            };
+            match line.parse() {
+                Err(e) if line.starts_with("action") => {
+                    return Err(format!("Invalid action from engine: {}", e));
+                }
+                Ok(EngineReply::Info(_)) | Err(_) => continue,
+                Ok(reply) => return Ok(reply),
+            }
+        }
+    }
+    // After a timeout, skips any late replies by waiting for a readyok.
+    fn sync(&mut self) -> Result<(), String> {
+        self.send(&HostCommand::IsReady)?;
+        while self.recv(REPLY_TIMEOUT)? != EngineReply::ReadyOk {}
+        self.stale = false;
+        Ok(())
+    }
+    fn choose_action(
+        &mut self,
+        game: &GameState,
+        time_ms: Option<u64>,
+    ) -> Result<TurnAction, String> {
+        if self.stale {
+            self.sync()?;
+        }
+        let num_placed = game.board.occupied().count();
+        if num_placed < self.num_placed || self.num_placed == 0 {
+            let config = GameConfig {
+                num_players: game.players.len(),
+                chain_names: game.board.chain_names.clone(),
+            };
+            self.send(&HostCommand::NewGame(config))?;
+        }
+        self.num_placed = num_placed;
+        let view = game.player_view(game.acting_player());
+        self.send(&HostCommand::View(Box::new(view)))?;
+        self.send(&HostCommand::Go { time_ms })?;
+        let timeout = time_ms.map_or(REPLY_TIMEOUT, |ms| Duration::from_millis(ms) + REPLY_GRACE);
+        loop {
+            if let EngineReply::Action(action) = self.recv(timeout)? {
+                return Ok(action);
+            }
+        }
+    }
+}
+
+// An agent that asks an engine subprocess for its actions.
+pub struct ExternalAgent {
+    name: String,
+    // Time budget sent with each go command.
+    pub time_ms: Option<u64>,
+    process: Mutex<EngineProcess>,
+}
+
+impl ExternalAgent {
+    // Starts the engine and waits for it to complete the handshake.
+    pub fn spawn(program: &str, args: &[String]) -> Result<Self, String> {
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to start {}: {}", program, e))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        let (sender, lines) = mpsc::channel();
+        // Stops when the engine closes its output, or when the agent is dropped.
+        std::thread::spawn(move || {
+            for line in stdout.lines() {
+                let failed = line.is_err();
+                let line = line.map_err(|e| format!("Failed to read from engine: {}", e));
+                if sender.send(line).is_err() || failed {
+                    break;
+                }
+            }
+        });
+        let mut process = EngineProcess {
+            child,
+            stdin,
+            lines,
+            num_placed: 0,
+            stale: false,
+            last_error: None,
+        };
+        let mut name = program.to_owned();
+        process.send(&HostCommand::Acquire)?;
+        loop {
+            match process.recv(REPLY_TIMEOUT)? {
+                EngineReply::Id { name: n } => name = n,
+                EngineReply::AcquireOk => break,
+                reply => return Err(format!("Unexpected reply to acquire: {}", reply)),
+            }
+        }
+        process.send(&HostCommand::IsReady)?;
+        match process.recv(REPLY_TIMEOUT)? {
+            EngineReply::ReadyOk => {}
+            reply => return Err(format!("Unexpected reply to isready: {}", reply)),
+        }
+        Ok(Self {
+            name,
+            time_ms: None,
+            process: Mutex::new(process),
+        })
+    }
+    // The name the engine gave in its handshake.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    // Why the last action came from the fallback rather than the engine.
+    pub fn last_error(&self) -> Option<String> {
+        self.process.lock().unwrap().last_error.clone()
+    }
+    // If the engine fails, doesn't reply in time, or replies with an action
+    // that isn't legal, this falls back to the first legal action, so the
+    // game can go on. Legal means in env::legal_actions, which leaves out
+    // trading an odd number of shares.
+    fn choose(&self, game: &GameState, time_ms: Option<u64>) -> TurnAction {
+        let legal = env::legal_actions(game);
+        let mut process = self.process.lock().unwrap();
+        let result =
+            process
+                .choose_action(game, time_ms)
+                .and_then(|action| match env::action_id(&action) {
+                    Some(id) if legal.contains(&id) => Ok(action),
+                    _ => Err(format!("Illegal action from engine: {:?}", action)),
+                });
+        match result {
+            Ok(action) => {
+                process.last_error = None;
+                action
+            }
+            Err(e) => {
+                process.last_error = Some(e);
+                // Agents are only asked to act before the game is over, when
+                // there's always a legal action.
+                env::action_from_id(legal[0]).unwrap()
+            }
+        }
+    }
+}
+
+impl Agent for ExternalAgent {
+    fn choose_action(&self, game: &GameState) -> TurnAction {
+        self.choose(game, self.time_ms)
+    }
+    // Sends the smaller of the budget and the agent's own time limit.
+    fn choose_action_within(&self, game: &GameState, budget: Duration) -> TurnAction {
+        let budget_ms = budget.as_millis() as u64;
+        self.choose(
+            game,
+            Some(self.time_ms.map_or(budget_ms, |ms| ms.min(budget_ms))),
+        )
+    }
+}
+
+impl Drop for ExternalAgent {
+    fn drop(&mut self) {
+        let process = self.process.get_mut().unwrap();
+        let _ = process.send(&HostCommand::Quit);
+        // Give the engine a moment to exit cleanly.
+        for _ in 0..100 {
+            if let Ok(Some(_)) = process.child.try_wait() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let _ = process.child.kill();
+        let _ = process.child.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::create_seeded_agent;
+    use crate::game::default_chain_names;
+    use rand::SeedableRng;
+
+    #[test]
+    fn parses_commands() {
+        let view = GameState::from_seed(2, 3, default_chain_names())
+            .unwrap()
+            .player_view(0);
+        let commands = [
+            HostCommand::Acquire,
+            HostCommand::NewGame(GameConfig {
+                num_players: 2,
+                chain_names: default_chain_names(),
+            }),
+            HostCommand::View(Box::new(view)),
+            HostCommand::Go { time_ms: None },
+            HostCommand::Go { time_ms: Some(250) },
+        ];
+        for cmd in commands {
+            let line = cmd.to_string();
+            assert_eq!(line.parse::<HostCommand>().unwrap().to_string(), line);
+        }
+        assert!("go fast".parse::<HostCommand>().is_err());
+        let reply = "action {\"ResolveMerger\":[2,4]}".parse::<EngineReply>();
+        assert_eq!(
+            reply,
+            Ok(EngineReply::Action(TurnAction::ResolveMerger(2, 4)))
+        );
+        let reply = "id name  Basic Bot ".parse::<EngineReply>();
+        assert_eq!(
+            reply,
+            Ok(EngineReply::Id {
+                name: "Basic Bot".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn runs_agent_as_engine() {
+        let game = GameState::from_seed(3, 8, default_chain_names()).unwrap();
+        let view = Box::new(game.player_view(game.acting_player()));
+        let input = [
+            "acquire".to_owned(),
+            "isready".to_owned(),
+            "bogus".to_owned(),
+            HostCommand::View(view).to_string(),
+            "go time 100".to_owned(),
+            "quit".to_owned(),
+            "isready".to_owned(),
+        ]
+        .join("\n");
+        let agent = create_seeded_agent(1, 8);
+        let mut output = Vec::new();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(8);
+        run_engine(&*agent, "basic", input.as_bytes(), &mut output, &mut rng).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines[..3], ["id name basic", "acquireok", "readyok"]);
+        assert!(lines[3].starts_with("info error: Unknown command"));
+        assert_eq!(lines.len(), 5);
+        let EngineReply::Action(action) = lines[4].parse().unwrap() else {
+            panic!("Expected an action: {}", lines[4]);
+        };
+        game.clone().take_turn(action).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn plays_external_engine() {
+        // An engine that always places its first tile, which is legal on the
+        // first turn of a new game.
+        let script = r#"while read cmd rest; do case $cmd in
+            acquire) echo "id name first tile"; echo acquireok;;
+            isready) echo readyok;;
+            go) echo "info thinking"; echo 'action {"PlaceTile":0}';;
+            quit) exit;;
+            esac; done"#;
+        let args = ["-c".to_owned(), script.to_owned()];
+        let agent = ExternalAgent::spawn("sh", &args).unwrap();
+        assert_eq!(agent.name(), "first tile");
+        let game = GameState::from_seed(2, 1, default_chain_names()).unwrap();
+        assert_eq!(agent.choose_action(&game), TurnAction::PlaceTile(0));
+        assert_eq!(agent.last_error(), None);
+        assert!(ExternalAgent::spawn("/nonexistent/engine", &[]).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rejects_illegal_actions() {
+        let script = r#"while read cmd rest; do case $cmd in
+            acquire) echo acquireok;;
+            isready) echo readyok;;
+            go) echo 'action {"CreateChain":0}';;
+            quit) exit;;
+            esac; done"#;
+        let args = ["-c".to_owned(), script.to_owned()];
+        let agent = ExternalAgent::spawn("sh", &args).unwrap();
+        let game = GameState::from_seed(2, 1, default_chain_names()).unwrap();
+        assert_eq!(agent.choose_action(&game), TurnAction::PlaceTile(0));
+        assert_eq!(
+            agent.last_error(),
+            Some("Illegal action from engine: CreateChain(0)".to_owned())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn times_out_slow_engine() {
+        // Replies to the first go too late, and to the rest right away.
+        let script = r#"n=0; while read cmd rest; do case $cmd in
+            acquire) echo acquireok;;
+            isready) echo readyok;;
+            go) n=$((n + 1)); if [ $n = 1 ]; then sleep 2; echo 'action {"PlaceTile":1}';
+                else echo 'action {"PlaceTile":2}'; fi;;
+            quit) exit;;
+            esac; done"#;
+        let args = ["-c".to_owned(), script.to_owned()];
+        let mut agent = ExternalAgent::spawn("sh", &args).unwrap();
+        agent.time_ms = Some(10);
+        let game = GameState::from_seed(2, 1, default_chain_names()).unwrap();
+        let start = Instant::now();
+        assert_eq!(agent.choose_action(&game), TurnAction::PlaceTile(0));
+        assert!(start.elapsed() < Duration::from_secs(2));
+        assert!(agent.last_error().unwrap().contains("didn't reply"));
+        // The late reply is skipped.
+        assert_eq!(agent.choose_action(&game), TurnAction::PlaceTile(2));
+        assert_eq!(agent.last_error(), None);
+    }
+}
@@ -0,0 +1,354 @@
+// Reinforcement-learning environment with fixed-size observations and a flat
+// discrete action space.
+//
+// Observations are from the perspective of the acting player (the player
+// resolving a merger, if there is one). The other players follow in turn
+// order, and missing seats are zero-filled. The layout is:
+//
+//   9 grid planes: unchained hotels, chains 0-6, and merging (dummy) cells
+//   hand plane: tiles in the acting player's hand
+//   pending plane: the tile founding a new chain, in the CreateChain phase
+//   chain sizes / NUM_TILES, market stocks / STOCKS_PER_CHAIN
+//   holdings / STOCKS_PER_CHAIN for each seat, then cash / CASH_SCALE
+//   one-hots for the phase, the merger's winning chain and its next loser
+//   unclaimed tiles / NUM_TILES
+//
+// Each plane holds NUM_TILES cells in Tile::to_index order.
+use rand::seq::SliceRandom;
+use std::sync::OnceLock;
+
+use crate::game::{
+    default_chain_names, GameState, Tile, TurnAction, TurnPhase, BUY_LIMIT, DUMMY_CHAIN_INDEX,
+    MAX_NUM_CHAINS, MAX_NUM_PLAYERS, NUM_TILES, STOCKS_PER_CHAIN,
+};
+
+const NUM_PHASES: usize = 7;
+const NUM_GRID_PLANES: usize = MAX_NUM_CHAINS + 2;
+const CASH_SCALE: f32 = 10_000.0;
+
+pub const OBSERVATION_SIZE: usize = (NUM_GRID_PLANES + 2) * NUM_TILES
+    + 2 * MAX_NUM_CHAINS
+    + MAX_NUM_PLAYERS * (MAX_NUM_CHAINS + 1)
+    + NUM_PHASES
+    + 2 * MAX_NUM_CHAINS
+    + 1;
+
+// Actions are laid out as: place tile (by hand index), create chain, pick
+// winning chain, accept bonus, resolve merger, then buy stock. Hands grow by
+// a tile every turn their owner can't place one, so there's a place action
+// for every tile that could be in a hand, not just the usual 6. Merger actions
+// cover every (sell, trade) pair with an even trade amount, since an odd
+// share would be given up for nothing. Buy actions cover every multiset of up
+// to BUY_LIMIT chains.
+pub const NUM_ACTIONS: usize =
+    NUM_TILES + 2 * MAX_NUM_CHAINS + 1 + NUM_RESOLVE_ACTIONS + NUM_BUY_ACTIONS;
+const NUM_RESOLVE_ACTIONS: usize = 182;
+const NUM_BUY_ACTIONS: usize = 120;
+
+fn action_table() -> &'static [TurnAction] {
+    static TABLE: OnceLock<Vec<TurnAction>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = Vec::with_capacity(NUM_ACTIONS);
+        table.extend((0..NUM_TILES).map(TurnAction::PlaceTile));
+        table.extend((0..MAX_NUM_CHAINS).map(TurnAction::CreateChain));
+        table.extend((0..MAX_NUM_CHAINS).map(TurnAction::PickWinningChain));
+        table.push(TurnAction::AcceptBonus);
+        for trade in (0..=STOCKS_PER_CHAIN).step_by(2) {
+            for sell in 0..=STOCKS_PER_CHAIN - trade {
+                table.push(TurnAction::ResolveMerger(sell, trade));
+            }
+        }
+        let mut orders = vec![[0; MAX_NUM_CHAINS]];
+        for size in 1..=BUY_LIMIT {
+            let prev = orders.clone();
+            for order in prev
+                .into_iter()
+                .filter(|o| o.iter().sum::<usize>() == size - 1)
+            {
+                // Only add chains at or after the last one bought, so each
+                // multiset is generated once.
+                let last = order.iter().rposition(|&n| n > 0).unwrap_or(0);
+                for i in last..MAX_NUM_CHAINS {
+                    let mut next = order;
+                    next[i] += 1;
+                    orders.push(next);
+                }
+            }
+        }
+        table.extend(orders.into_iter().map(TurnAction::BuyStock));
+        debug_assert_eq!(table.len(), NUM_ACTIONS);
+        table
+    })
+}
+
+// The action with the given id, if it's in range.
+pub fn action_from_id(action_id: usize) -> Option<TurnAction> {
+    action_table().get(action_id).cloned()
+}
+
+// The id of an action, if it's part of the action space.
+pub fn action_id(action: &TurnAction) -> Option<usize> {
+    action_table().iter().position(|a| a == action)
+}
+
+pub struct Step {
+    pub observation: Vec<f32>,
+    // Reward for each player: zero until the game ends, then an equal share
+    // of 1.0 for each player tied for the highest final value.
+    pub rewards: Vec<f64>,
+    pub done: bool,
+}
+
+pub struct Env {
+    num_players: usize,
+    game: GameState,
+}
+
+impl Env {
+    pub fn new(num_players: usize, seed: u64) -> Result<Self, String> {
+        Ok(Self {
+            num_players,
+            game: GameState::from_seed(num_players, seed, default_chain_names())?,
+        })
+    }
+    pub fn reset(&mut self, seed: u64) -> Vec<f32> {
+        self.game = GameState::from_seed(self.num_players, seed, default_chain_names())
+            .expect("Player count was checked by Env::new");
+        self.observation()
+    }
+    pub fn game(&self) -> &GameState {
+        &self.game
+    }
+    pub fn acting_player(&self) -> usize {
+        self.game.acting_player()
+    }
+    pub fn is_done(&self) -> bool {
+        matches!(self.game.turn_state.phase, TurnPhase::GameOver(_))
+    }
+    pub fn step(&mut self, action_id: usize) -> Result<Step, String> {
+        if self.is_done() {
+            return Err("Game is over".to_owned());
+        }
+        if !self.action_mask().get(action_id).copied().unwrap_or(false) {
+            return Err(format!("Illegal action: {}", action_id));
+        }
+        let done = self.game.take_turn(action_table()[action_id].clone())?;
+        let mut rewards = vec![0.0; self.num_players];
+        if let TurnPhase::GameOver(scores) = &self.game.turn_state.phase {
+            let best = scores.iter().max().unwrap();
+            let num_winners = scores.iter().filter(|&s| s == best).count();
+            for (reward, score) in rewards.iter_mut().zip(scores) {
+                if score == best {
+                    *reward = 1.0 / num_winners as f64;
+                }
+            }
+        }
+        Ok(Step {
+            observation: self.observation(),
+            rewards,
+            done,
+        })
+    }
+    // Picks a uniformly random legal action.
+    pub fn sample_action(&self, rng: &mut impl rand::Rng) -> Option<usize> {
+        self.legal_actions().choose(rng).copied()
+    }
+    pub fn legal_actions(&self) -> Vec<usize> {
+        legal_actions(&self.game)
+    }
+    pub fn action_mask(&self) -> Vec<bool> {
+        action_mask(&self.game)
+    }
+    pub fn observation(&self) -> Vec<f32> {
+        observation(&self.game)
+    }
+}
+
+// Ids of the legal actions in the given state.
+pub fn legal_actions(game: &GameState) -> Vec<usize> {
+    action_mask(game)
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &legal)| legal.then_some(i))
+        .collect()
+}
+
+// Whether each action id is legal in the given state.
+pub fn action_mask(game: &GameState) -> Vec<bool> {
+    let board = &game.board;
+    action_table()
+        .iter()
+        .map(|action| match (&game.turn_state.phase, action) {
+            (TurnPhase::PlaceTile(valid), TurnAction::PlaceTile(idx)) => valid.contains(idx),
+            (TurnPhase::CreateChain(_, valid), TurnAction::CreateChain(idx)) => valid.contains(idx),
+            (TurnPhase::PickWinningChain(valid, _), TurnAction::PickWinningChain(idx)) => {
+                valid.contains(idx)
+            }
+            (TurnPhase::DistributeBonuses(_, _, _), TurnAction::AcceptBonus) => true,
+            (
+                TurnPhase::ResolveMerger(winner, losers, player),
+                TurnAction::ResolveMerger(sell, trade),
+            ) => {
+                let held = game.players[*player].stocks[losers[0]];
+                sell + trade <= held && trade / 2 <= board.stock_market[*winner]
+            }
+            (TurnPhase::BuyStock(available), TurnAction::BuyStock(order)) => {
+                let cost = (0..MAX_NUM_CHAINS)
+                    .map(|i| order[i] * game.stock_price(i))
+                    .sum::<usize>();
+                order.iter().zip(available).all(|(n, a)| n <= a)
+                    && cost <= game.players[game.turn_state.player].cash
+            }
+            _ => false,
+        })
+        .collect()
+}
+
+// The observation for the acting player, laid out as described above.
+pub fn observation(game: &GameState) -> Vec<f32> {
+    let num_players = game.players.len();
+    let board = &game.board;
+    let mut obs = Vec::with_capacity(OBSERVATION_SIZE);
+    let push_plane = |tiles: &mut dyn Iterator<Item = Tile>, obs: &mut Vec<f32>| {
+        let start = obs.len();
+        obs.resize(start + NUM_TILES, 0.0);
+        for tile in tiles {
+            obs[start + tile.to_index()] = 1.0;
+        }
+    };
+    push_plane(&mut board.hotels().tiles(), &mut obs);
+    for i in 0..=MAX_NUM_CHAINS {
+        push_plane(&mut board.chain_mask(i).tiles(), &mut obs);
+    }
+    let me = game.acting_player();
+    push_plane(&mut game.players[me].tiles.iter().copied(), &mut obs);
+    let pending = match &game.turn_state.phase {
+        TurnPhase::CreateChain(tile, _) => Some(*tile),
+        _ => None,
+    };
+    push_plane(&mut pending.into_iter(), &mut obs);
+
+    for i in 0..MAX_NUM_CHAINS {
+        obs.push(board.chain_size(i) as f32 / NUM_TILES as f32);
+    }
+    for &n in &board.stock_market {
+        obs.push(n as f32 / STOCKS_PER_CHAIN as f32);
+    }
+    for seat in 0..MAX_NUM_PLAYERS {
+        if seat < num_players {
+            let player = &game.players[(me + seat) % num_players];
+            obs.extend(
+                player
+                    .stocks
+                    .iter()
+                    .map(|&n| n as f32 / STOCKS_PER_CHAIN as f32),
+            );
+            obs.push(player.cash as f32 / CASH_SCALE);
+        } else {
+            obs.extend([0.0; MAX_NUM_CHAINS + 1]);
+        }
+    }
+
+    let (phase, winner, loser) = match &game.turn_state.phase {
+        TurnPhase::PlaceTile(_) => (0, None, None),
+        TurnPhase::CreateChain(_, _) => (1, None, None),
+        TurnPhase::PickWinningChain(_, _) => (2, None, None),
+        TurnPhase::DistributeBonuses(w, losers, _) => (3, Some(*w), Some(losers[0])),
+        TurnPhase::ResolveMerger(w, losers, _) => (4, Some(*w), Some(losers[0])),
+        TurnPhase::BuyStock(_) => (5, None, None),
+        TurnPhase::GameOver(_) => (6, None, None),
+    };
+    let mut push_one_hot = |idx: Option<usize>, len: usize| {
+        let start = obs.len();
+        obs.resize(start + len, 0.0);
+        if let Some(i) = idx.filter(|&i| i < len) {
+            obs[start + i] = 1.0;
+        }
+    };
+    push_one_hot(Some(phase), NUM_PHASES);
+    // The end-of-game bonus payout has no winning chain.
+    push_one_hot(winner.filter(|&w| w != DUMMY_CHAIN_INDEX), MAX_NUM_CHAINS);
+    push_one_hot(loser, MAX_NUM_CHAINS);
+    obs.push(game.num_unclaimed_tiles() as f32 / NUM_TILES as f32);
+    debug_assert_eq!(obs.len(), OBSERVATION_SIZE);
+    obs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn action_table_roundtrip() {
+        assert_eq!(NUM_ACTIONS, 425);
+        for id in 0..NUM_ACTIONS {
+            assert_eq!(action_id(&action_from_id(id).unwrap()), Some(id));
+        }
+        assert_eq!(action_from_id(NUM_ACTIONS), None);
+        assert_eq!(action_id(&TurnAction::ResolveMerger(0, 1)), None);
+        assert_eq!(
+            action_from_id(NUM_ACTIONS - 1),
+            Some(TurnAction::BuyStock([0, 0, 0, 0, 0, 0, 3]))
+        );
+    }
+
+    #[test]
+    fn mask_matches_rules() {
+        let mut env = Env::new(3, 11).unwrap();
+        let mut rng = StdRng::seed_from_u64(11);
+        loop {
+            assert_eq!(env.observation().len(), OBSERVATION_SIZE);
+            let mask = env.action_mask();
+            for (id, action) in action_table().iter().enumerate() {
+                let mut game = env.game().clone();
+                assert_eq!(
+                    mask[id],
+                    game.take_turn(action.clone()).is_ok(),
+                    "{:?}\n{}",
+                    action,
+                    env.game()
+                );
+            }
+            let step = env.step(env.sample_action(&mut rng).unwrap()).unwrap();
+            if step.done {
+                assert_eq!(step.rewards.iter().sum::<f64>(), 1.0);
+                break;
+            }
+            assert!(step.rewards.iter().all(|&r| r == 0.0));
+        }
+        assert!(env.step(0).is_err());
+        assert!(env.legal_actions().is_empty());
+    }
+
+    #[test]
+    fn places_from_large_hands() {
+        // In this deal, a player gets a 7th tile and can later place it.
+        let mut game = GameState::from_seed(2, 15, default_chain_names()).unwrap();
+        let ai = crate::agent::create_seeded_agent(0, 15);
+        let valid = loop {
+            match &game.turn_state.phase {
+                TurnPhase::PlaceTile(valid) if valid.contains(&6) => break valid.clone(),
+                _ => assert!(!game.take_turn(ai.choose_action(&game)).unwrap()),
+            }
+        };
+        let mask = action_mask(&game);
+        let placeable = (0..NUM_TILES).filter(|&i| mask[i]).collect::<Vec<_>>();
+        assert_eq!(placeable, valid);
+        let mut env = Env::new(2, 0).unwrap();
+        env.game = game;
+        assert!(env
+            .step(action_id(&TurnAction::PlaceTile(6)).unwrap())
+            .is_ok());
+    }
+
+    #[test]
+    fn reset_is_deterministic() {
+        let mut env = Env::new(4, 0).unwrap();
+        let obs = env.reset(5);
+        assert_eq!(env.reset(5), obs);
+        assert_ne!(env.reset(6), obs);
+        assert!(env.step(NUM_ACTIONS).is_err());
+    }
+}
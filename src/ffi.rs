@@ -0,0 +1,361 @@
+// C API, built with the "ffi" feature. The header is include/acquire.h,
+// generated with `cbindgen --config cbindgen.toml --output include/acquire.h`.
+//
+// Games and agents are opaque handles, created by the *_new functions and
+// released with the matching *_free function. States, views and actions are
+// exchanged as JSON, in the serde format of the corresponding Rust types.
+// Strings returned by this API are owned by the caller and must be released
+// with acquire_string_free.
+//
+// Safety: every pointer argument must either be null or valid for the
+// duration of the call (handles from this API, NUL-terminated UTF-8 strings),
+// and a handle must not be used from two threads at once.
+//
+// Panics never unwind into the caller: a function that panics returns null
+// (or -1) instead.
+#![allow(clippy::missing_safety_doc)]
+
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::ptr;
+
+use crate::agent::{create_seeded_agent, Agent};
+use crate::env;
+use crate::game::{default_chain_names, GameState, TurnAction, MAX_NUM_CHAINS};
+
+pub struct AcquireGame {
+    game: GameState,
+    // Message for the last failed call on this handle.
+    last_error: Option<CString>,
+}
+
+pub struct AcquireAgent {
+    agent: Box<dyn Agent + Send>,
+}
+
+fn into_c_string(s: String) -> *mut c_char {
+    // JSON and error messages never contain NUL bytes.
+    CString::new(s).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+unsafe fn str_arg<'a>(s: *const c_char) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err("Null string argument".to_owned());
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|e| format!("Invalid UTF-8: {}", e))
+}
+
+// Runs the body of an exported function, returning `fallback` if it panics,
+// since unwinding across the C boundary aborts the process.
+fn guard<T>(fallback: T, body: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or(fallback)
+}
+
+impl AcquireGame {
+    fn record<T>(&mut self, result: Result<T, String>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                self.last_error = None;
+                Some(value)
+            }
+            Err(e) => {
+                self.last_error = CString::new(e).ok();
+                None
+            }
+        }
+    }
+}
+
+// Deals a new game. chain_names may be null for the default names, or point
+// to 7 distinct, non-empty strings. Returns null if the arguments are
+// invalid.
+#[no_mangle]
+pub unsafe extern "C" fn acquire_game_new(
+    num_players: u32,
+    seed: u64,
+    chain_names: *const *const c_char,
+) -> *mut AcquireGame {
+    guard(ptr::null_mut(), || {
+        let mut names = default_chain_names();
+        if !chain_names.is_null() {
+            for (i, name) in names.iter_mut().enumerate() {
+                match str_arg(*chain_names.add(i)) {
+                    Ok(s) => *name = s.to_owned(),
+                    Err(_) => return ptr::null_mut(),
+                }
+            }
+        }
+        let Ok(game) = GameState::from_seed(num_players as usize, seed, names) else {
+            return ptr::null_mut();
+        };
+        Box::into_raw(Box::new(AcquireGame {
+            game,
+            last_error: None,
+        }))
+    })
+}
+
+// Restores a game from acquire_game_state_json output. Returns null if the
+// JSON is invalid.
+#[no_mangle]
+pub unsafe extern "C" fn acquire_game_from_json(json: *const c_char) -> *mut AcquireGame {
+    guard(ptr::null_mut(), || {
+        let game = str_arg(json).and_then(|s| serde_json::from_str(s).map_err(|e| e.to_string()));
+        match game {
+            Ok(game) => Box::into_raw(Box::new(AcquireGame {
+                game,
+                last_error: None,
+            })),
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn acquire_game_free(game: *mut AcquireGame) {
+    guard((), || {
+        if !game.is_null() {
+            drop(Box::from_raw(game));
+        }
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn acquire_game_clone(game: *const AcquireGame) -> *mut AcquireGame {
+    guard(ptr::null_mut(), || match game.as_ref() {
+        Some(g) => Box::into_raw(Box::new(AcquireGame {
+            game: g.game.clone(),
+            last_error: None,
+        })),
+        None => ptr::null_mut(),
+    })
+}
+
+// Applies a JSON-encoded TurnAction, e.g. {"PlaceTile":2} or "AcceptBonus".
+// Returns 1 if the game is now over, 0 if not, and -1 if the action was
+// rejected (see acquire_game_last_error), leaving the game unchanged.
+#[no_mangle]
+pub unsafe extern "C" fn acquire_game_take_turn(
+    game: *mut AcquireGame,
+    action_json: *const c_char,
+) -> i32 {
+    guard(-1, || {
+        let Some(g) = game.as_mut() else {
+            return -1;
+        };
+        let result = str_arg(action_json)
+            .and_then(|s| serde_json::from_str::<TurnAction>(s).map_err(|e| e.to_string()))
+            .and_then(|action| g.game.take_turn(action));
+        match g.record(result) {
+            Some(true) => 1,
+            Some(false) => 0,
+            None => -1,
+        }
+    })
+}
+
+// The error message from the last failed call on this handle, or null. The
+// string is owned by the handle, and is valid until the next call with it.
+#[no_mangle]
+pub unsafe extern "C" fn acquire_game_last_error(game: *const AcquireGame) -> *const c_char {
+    guard(ptr::null(), || {
+        match game.as_ref().and_then(|g| g.last_error.as_ref()) {
+            Some(e) => e.as_ptr(),
+            None => ptr::null(),
+        }
+    })
+}
+
+// The player who needs to act next, or -1 for a null handle.
+#[no_mangle]
+pub unsafe extern "C" fn acquire_game_acting_player(game: *const AcquireGame) -> i32 {
+    guard(-1, || {
+        game.as_ref().map_or(-1, |g| g.game.acting_player() as i32)
+    })
+}
+
+// The full game state, including hidden information.
+#[no_mangle]
+pub unsafe extern "C" fn acquire_game_state_json(game: *const AcquireGame) -> *mut c_char {
+    guard(ptr::null_mut(), || match game.as_ref() {
+        Some(g) => serde_json::to_string(&g.game).map_or(ptr::null_mut(), into_c_string),
+        None => ptr::null_mut(),
+    })
+}
+
+// What the given player is allowed to see, or null for an invalid player.
+#[no_mangle]
+pub unsafe extern "C" fn acquire_game_player_view_json(
+    game: *const AcquireGame,
+    player: u32,
+) -> *mut c_char {
+    guard(ptr::null_mut(), || match game.as_ref() {
+        Some(g) if (player as usize) < g.game.players.len() => {
+            serde_json::to_string(&g.game.player_view(player as usize))
+                .map_or(ptr::null_mut(), into_c_string)
+        }
+        _ => ptr::null_mut(),
+    })
+}
+
+// A JSON list of the legal TurnActions (every legal action, except odd
+// trade amounts when resolving a merger).
+#[no_mangle]
+pub unsafe extern "C" fn acquire_game_legal_actions_json(game: *const AcquireGame) -> *mut c_char {
+    guard(ptr::null_mut(), || match game.as_ref() {
+        Some(g) => {
+            let actions = env::legal_actions(&g.game)
+                .into_iter()
+                .filter_map(env::action_from_id)
+                .collect::<Vec<TurnAction>>();
+            serde_json::to_string(&actions).map_or(ptr::null_mut(), into_c_string)
+        }
+        None => ptr::null_mut(),
+    })
+}
+
+// The game in its human-readable Display format.
+#[no_mangle]
+pub unsafe extern "C" fn acquire_game_to_string(game: *const AcquireGame) -> *mut c_char {
+    guard(ptr::null_mut(), || match game.as_ref() {
+        Some(g) => into_c_string(g.game.to_string()),
+        None => ptr::null_mut(),
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn acquire_string_free(s: *mut c_char) {
+    guard((), || {
+        if !s.is_null() {
+            drop(CString::from_raw(s));
+        }
+    })
+}
+
+// One of the built-in agents, by difficulty, with its own seeded rng.
+#[no_mangle]
+pub extern "C" fn acquire_agent_new(difficulty: u32, seed: u64) -> *mut AcquireAgent {
+    guard(ptr::null_mut(), || {
+        Box::into_raw(Box::new(AcquireAgent {
+            agent: create_seeded_agent(difficulty as usize, seed),
+        }))
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn acquire_agent_free(agent: *mut AcquireAgent) {
+    guard((), || {
+        if !agent.is_null() {
+            drop(Box::from_raw(agent));
+        }
+    })
+}
+
+// The agent's chosen action for the acting player, as JSON.
+#[no_mangle]
+pub unsafe extern "C" fn acquire_agent_choose_action_json(
+    agent: *const AcquireAgent,
+    game: *const AcquireGame,
+) -> *mut c_char {
+    guard(ptr::null_mut(), || match (agent.as_ref(), game.as_ref()) {
+        (Some(a), Some(g)) => serde_json::to_string(&a.agent.choose_action(&g.game))
+            .map_or(ptr::null_mut(), into_c_string),
+        _ => ptr::null_mut(),
+    })
+}
+
+// Number of chains, for sizing arrays of chain names.
+#[no_mangle]
+pub extern "C" fn acquire_num_chains() -> u32 {
+    guard(0, || MAX_NUM_CHAINS as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    unsafe fn take_string(s: *mut c_char) -> String {
+        let owned = CStr::from_ptr(s).to_str().unwrap().to_owned();
+        acquire_string_free(s);
+        owned
+    }
+
+    #[test]
+    fn plays_game_through_handles() {
+        unsafe {
+            let game = acquire_game_new(3, 7, ptr::null());
+            let agent = acquire_agent_new(1, 7);
+            loop {
+                let action = acquire_agent_choose_action_json(agent, game);
+                assert!(!action.is_null());
+                let status = acquire_game_take_turn(game, action);
+                acquire_string_free(action);
+                assert_ne!(status, -1);
+                if status == 1 {
+                    break;
+                }
+            }
+            let json = take_string(acquire_game_state_json(game));
+            assert!(json.contains("GameOver"), "{}", json);
+            let copy = acquire_game_from_json(CString::new(json).unwrap().as_ptr());
+            assert_eq!(
+                take_string(acquire_game_to_string(copy)),
+                take_string(acquire_game_to_string(game))
+            );
+            acquire_game_free(copy);
+            acquire_agent_free(agent);
+            acquire_game_free(game);
+        }
+    }
+
+    #[test]
+    fn reports_errors() {
+        unsafe {
+            assert!(acquire_game_new(1, 0, ptr::null()).is_null());
+            let names = ["A", "B", "C", "D", "E", "F", "G"].map(|n| CString::new(n).unwrap());
+            let name_ptrs = names.each_ref().map(|n| n.as_ptr());
+            let game = acquire_game_new(2, 0, name_ptrs.as_ptr());
+            assert!(acquire_game_last_error(game).is_null());
+            let bad = CString::new(r#""AcceptBonus""#).unwrap();
+            assert_eq!(acquire_game_take_turn(game, bad.as_ptr()), -1);
+            let err = CStr::from_ptr(acquire_game_last_error(game));
+            assert!(err.to_str().unwrap().starts_with("Wrong phase"));
+            let view = take_string(acquire_game_player_view_json(game, 1));
+            assert!(view.contains(r#""chain_names":["A","B","#), "{}", view);
+            assert!(acquire_game_player_view_json(game, 2).is_null());
+            let legal = take_string(acquire_game_legal_actions_json(game));
+            assert!(legal.starts_with(r#"[{"PlaceTile":0}"#), "{}", legal);
+            acquire_game_free(game);
+            assert!(acquire_game_from_json(bad.as_ptr()).is_null());
+        }
+    }
+
+    #[test]
+    fn rejects_bad_chain_names() {
+        unsafe {
+            let mut names = ["A", "B", "C", "D", "E", "F", ""].map(|n| CString::new(n).unwrap());
+            let name_ptrs = names.each_ref().map(|n| n.as_ptr());
+            assert!(acquire_game_new(2, 0, name_ptrs.as_ptr()).is_null());
+            names[6] = CString::new("A").unwrap();
+            let name_ptrs = names.each_ref().map(|n| n.as_ptr());
+            assert!(acquire_game_new(2, 0, name_ptrs.as_ptr()).is_null());
+            // A state that would once have panicked while being hashed.
+            let game = acquire_game_new(2, 0, ptr::null());
+            let json = take_string(acquire_game_state_json(game)).replacen(
+                r#""stocks":[0,"#,
+                r#""stocks":[26,"#,
+                1,
+            );
+            assert!(acquire_game_from_json(CString::new(json).unwrap().as_ptr()).is_null());
+            acquire_game_free(game);
+        }
+    }
+
+    #[test]
+    fn catches_panics() {
+        assert_eq!(guard(-1, || panic!("oops")), -1);
+        assert!(guard(ptr::null_mut::<AcquireGame>(), || unreachable!()).is_null());
+    }
+}
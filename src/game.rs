@@ -1,19 +1,39 @@
 use rand::seq::SliceRandom;
+use rand::SeedableRng;
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
+use crate::bitboard::Bitboard;
+use crate::zobrist;
+
 // Grid cells named from 1-A to 12-I.
-const GRID_WIDTH: usize = 12;
-const GRID_HEIGHT: usize = 9;
+pub(crate) const GRID_WIDTH: usize = 12;
+pub(crate) const GRID_HEIGHT: usize = 9;
+pub const NUM_TILES: usize = GRID_WIDTH * GRID_HEIGHT;
 pub const MAX_NUM_CHAINS: usize = 7;
-const STOCKS_PER_CHAIN: usize = 25;
-const BUY_LIMIT: usize = 3;
+pub const MAX_NUM_PLAYERS: usize = 6;
+pub(crate) const STOCKS_PER_CHAIN: usize = 25;
+pub(crate) const BUY_LIMIT: usize = 3;
 const SAFE_CHAIN_SIZE: usize = 11;
-const DUMMY_CHAIN_INDEX: usize = 999;
+pub(crate) const DUMMY_CHAIN_INDEX: usize = 999;
+pub(crate) const NUM_CELL_STATES: usize = MAX_NUM_CHAINS + 3;
 
 // Contains (row, col) indices.
 #[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
-pub struct Tile(usize, usize);
+pub struct Tile(pub(crate) usize, pub(crate) usize);
+impl Tile {
+    // Row-major index, from 0 (1-A) to NUM_TILES - 1 (12-I).
+    pub fn to_index(self) -> usize {
+        self.0 * GRID_WIDTH + self.1
+    }
+    pub fn from_index(idx: usize) -> Option<Self> {
+        if idx < NUM_TILES {
+            Some(Tile(idx / GRID_WIDTH, idx % GRID_WIDTH))
+        } else {
+            None
+        }
+    }
+}
 impl std::fmt::Debug for Tile {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let row = (b'A' + self.0 as u8) as char;
@@ -21,6 +41,27 @@ impl std::fmt::Debug for Tile {
         write!(f, "{}-{}", col, row)
     }
 }
+impl std::str::FromStr for Tile {
+    type Err = String;
+    // Parses the "7-C" format produced by Debug.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (col, row) = s
+            .trim()
+            .split_once('-')
+            .ok_or_else(|| format!("Invalid tile: {}", s))?;
+        let col = col
+            .parse::<usize>()
+            .map_err(|_| format!("Invalid tile column: {}", s))?;
+        let row = match row.as_bytes() {
+            [r] if r.is_ascii_alphabetic() => (r.to_ascii_uppercase() - b'A') as usize,
+            _ => return Err(format!("Invalid tile row: {}", s)),
+        };
+        if col == 0 || col > GRID_WIDTH || row >= GRID_HEIGHT {
+            return Err(format!("Tile out of bounds: {}", s));
+        }
+        Ok(Tile(row, col - 1))
+    }
+}
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Player {
@@ -64,7 +105,7 @@ impl Player {
 
 #[derive(Clone, Copy, PartialEq, Debug, Serialize_repr, Deserialize_repr)]
 #[repr(u8)]
-enum GridCell {
+pub(crate) enum GridCell {
     Empty = 0,
     Hotel = 1,
     Chain0 = 2,
@@ -77,7 +118,7 @@ enum GridCell {
     Dummy = 99,
 }
 impl GridCell {
-    fn from_chain_idx(chain_idx: usize) -> Self {
+    pub(crate) fn from_chain_idx(chain_idx: usize) -> Self {
         match chain_idx {
             0 => GridCell::Chain0,
             1 => GridCell::Chain1,
@@ -90,7 +131,14 @@ impl GridCell {
             _ => panic!("Invalid chain index"),
         }
     }
-    fn to_chain_index(self) -> Option<usize> {
+    // Dense index from 0 (Empty) to NUM_CELL_STATES - 1 (Dummy).
+    pub(crate) fn ordinal(self) -> usize {
+        match self {
+            GridCell::Dummy => NUM_CELL_STATES - 1,
+            _ => self as usize,
+        }
+    }
+    pub(crate) fn to_chain_index(self) -> Option<usize> {
         match self {
             GridCell::Chain0 => Some(0),
             GridCell::Chain1 => Some(1),
@@ -126,7 +174,7 @@ pub enum TurnPhase {
     GameOver(Vec<usize>),
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub enum TurnAction {
     // Payload: tile index.
     PlaceTile(usize),
@@ -147,6 +195,15 @@ pub struct TurnState {
     pub player: usize,
     pub phase: TurnPhase,
 }
+impl TurnState {
+    // See GameState::acting_player.
+    pub fn acting_player(&self) -> usize {
+        match self.phase {
+            TurnPhase::ResolveMerger(_, _, player) => player,
+            _ => self.player,
+        }
+    }
+}
 
 enum TilePlayability {
     Playable,
@@ -155,12 +212,34 @@ enum TilePlayability {
 }
 
 #[derive(Serialize, Deserialize, Clone)]
+#[serde(try_from = "BoardFields")]
 pub struct BoardState {
+    pub(crate) grid: [[GridCell; GRID_WIDTH]; GRID_HEIGHT],
+    pub(crate) chain_sizes: [usize; MAX_NUM_CHAINS],
+    pub(crate) stock_market: [usize; MAX_NUM_CHAINS],
+    pub(crate) chain_names: [String; MAX_NUM_CHAINS],
+    // Bitboards of all occupied cells, and of the cells belonging to each
+    // chain (with the dummy chain last). These are derived from the grid, so
+    // they aren't serialized.
+    #[serde(skip)]
+    occupied: Bitboard,
+    #[serde(skip)]
+    chain_masks: [Bitboard; MAX_NUM_CHAINS + 1],
+}
+// The serialized form of BoardState.
+#[derive(Deserialize)]
+struct BoardFields {
     grid: [[GridCell; GRID_WIDTH]; GRID_HEIGHT],
     chain_sizes: [usize; MAX_NUM_CHAINS],
     stock_market: [usize; MAX_NUM_CHAINS],
     chain_names: [String; MAX_NUM_CHAINS],
 }
+impl TryFrom<BoardFields> for BoardState {
+    type Error = String;
+    fn try_from(f: BoardFields) -> Result<Self, Self::Error> {
+        BoardState::from_parts(f.grid, f.chain_sizes, f.stock_market, f.chain_names)
+    }
+}
 impl std::fmt::Display for BoardState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for col in 0..=GRID_WIDTH {
@@ -187,17 +266,511 @@ impl std::fmt::Display for BoardState {
     }
 }
 impl BoardState {
+    pub(crate) fn from_parts(
+        grid: [[GridCell; GRID_WIDTH]; GRID_HEIGHT],
+        chain_sizes: [usize; MAX_NUM_CHAINS],
+        stock_market: [usize; MAX_NUM_CHAINS],
+        chain_names: [String; MAX_NUM_CHAINS],
+    ) -> Result<Self, String> {
+        for (i, name) in chain_names.iter().enumerate() {
+            if name.is_empty() {
+                return Err(format!("Chain {} has no name", i));
+            }
+            if chain_names[..i].contains(name) {
+                return Err(format!("Duplicate chain name: {}", name));
+            }
+        }
+        // Chains of size 1 don't exist, and have no stock price.
+        for (i, (&size, &num_stocks)) in chain_sizes.iter().zip(&stock_market).enumerate() {
+            if size == 1 || size > NUM_TILES {
+                return Err(format!("Invalid size for chain {}: {}", i, size));
+            }
+            if num_stocks > STOCKS_PER_CHAIN {
+                return Err(format!(
+                    "Invalid stock count for chain {}: {}",
+                    i, num_stocks
+                ));
+            }
+        }
+        let mut occupied = Bitboard::EMPTY;
+        let mut chain_masks = [Bitboard::EMPTY; MAX_NUM_CHAINS + 1];
+        for (row, cells) in grid.iter().enumerate() {
+            for (col, &cell) in cells.iter().enumerate() {
+                if cell != GridCell::Empty {
+                    occupied.insert(Tile(row, col));
+                }
+                if let Some(i) = chain_mask_index(cell) {
+                    chain_masks[i].insert(Tile(row, col));
+                }
+            }
+        }
+        Ok(Self {
+            grid,
+            chain_sizes,
+            stock_market,
+            chain_names,
+            occupied,
+            chain_masks,
+        })
+    }
     pub fn num_neighbors(&self, tile: Tile) -> usize {
-        grid_neighbors(tile, &self.grid).len()
+        self.occupied_neighbors(tile).count()
+    }
+    pub fn occupied(&self) -> Bitboard {
+        self.occupied
+    }
+    pub fn chain_mask(&self, chain_index: usize) -> Bitboard {
+        self.chain_masks[chain_index]
+    }
+    // Occupied cells that aren't (yet) part of any chain.
+    pub fn hotels(&self) -> Bitboard {
+        let mut chained = Bitboard::EMPTY;
+        for &mask in &self.chain_masks {
+            chained |= mask;
+        }
+        self.occupied & !chained
+    }
+    fn occupied_neighbors(&self, tile: Tile) -> Bitboard {
+        Bitboard::from_tile(tile).neighbors() & self.occupied
+    }
+    // Chains with at least one cell in the given set, in index order.
+    fn chains_touching(&self, cells: Bitboard) -> impl Iterator<Item = usize> + '_ {
+        (0..MAX_NUM_CHAINS).filter(move |&i| !(self.chain_masks[i] & cells).is_empty())
+    }
+    pub fn chain_size(&self, chain_index: usize) -> usize {
+        self.chain_sizes[chain_index]
+    }
+    pub fn stock_price(&self, chain_index: usize) -> usize {
+        chain_stock_price(chain_index, self.chain_sizes[chain_index])
+    }
+    // Safe chains can't be merged into another chain.
+    pub fn is_safe(&self, chain_index: usize) -> bool {
+        self.chain_sizes[chain_index] >= SAFE_CHAIN_SIZE
+    }
+    // Shares of each chain left to buy.
+    pub fn stocks_left(&self, chain_index: usize) -> usize {
+        self.stock_market[chain_index]
+    }
+    pub fn is_occupied(&self, tile: Tile) -> bool {
+        self.grid[tile.0][tile.1] != GridCell::Empty
+    }
+    // Index of the chain a tile belongs to, if any.
+    pub fn chain_at(&self, tile: Tile) -> Option<usize> {
+        self.grid[tile.0][tile.1]
+            .to_chain_index()
+            .filter(|&i| i != DUMMY_CHAIN_INDEX)
+    }
+    // Cells currently labeled with a chain. While a merger is being resolved,
+    // the losing chains' cells already belong to the winner.
+    pub fn chain_cells(&self, chain_index: usize) -> impl Iterator<Item = Tile> {
+        self.chain_masks[chain_index].tiles()
+    }
+    fn set_cell(&mut self, tile: Tile, cell: GridCell) {
+        let old = std::mem::replace(&mut self.grid[tile.0][tile.1], cell);
+        if let Some(i) = chain_mask_index(old) {
+            self.chain_masks[i].remove(tile);
+        }
+        if let Some(i) = chain_mask_index(cell) {
+            self.chain_masks[i].insert(tile);
+        }
+        if cell == GridCell::Empty {
+            self.occupied.remove(tile);
+        } else {
+            self.occupied.insert(tile);
+        }
+    }
+    // Relabels the cells of the losing chains and the dummy chain as part of
+    // the winner, returning each relabeled cell along with its old label.
+    fn merge_cells(&mut self, winner: usize, losers: &[usize]) -> Vec<(Tile, GridCell)> {
+        let winner_cell = GridCell::from_chain_idx(winner);
+        let mut merged = Vec::new();
+        for i in losers.iter().copied().chain([MAX_NUM_CHAINS]) {
+            let mask = std::mem::take(&mut self.chain_masks[i]);
+            self.chain_masks[winner] |= mask;
+            for tile in mask.tiles() {
+                let old = std::mem::replace(&mut self.grid[tile.0][tile.1], winner_cell);
+                merged.push((tile, old));
+            }
+        }
+        merged
+    }
+    pub fn chain_name(&self, chain_index: usize) -> &str {
+        &self.chain_names[chain_index]
+    }
+    // Case-insensitive lookup of a chain index by its full name.
+    pub fn find_chain(&self, name: &str) -> Option<usize> {
+        self.chain_names
+            .iter()
+            .position(|n| n.eq_ignore_ascii_case(name.trim()))
+    }
+    // Parses the Display format. Chains on the grid are identified by the
+    // first letter of their names, so those must be unique.
+    pub fn parse(s: &str, chain_names: [String; MAX_NUM_CHAINS]) -> Result<Self, String> {
+        let mut lines = s.lines().map(|l| l.trim()).filter(|l| !l.is_empty());
+        let board = parse_board_lines(&mut lines, chain_names)?;
+        if let Some(line) = lines.next() {
+            return Err(format!("Unexpected trailing line: {}", line));
+        }
+        Ok(board)
+    }
+}
+impl std::str::FromStr for BoardState {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BoardState::parse(s, default_chain_names())
+    }
+}
+
+// Single-letter chain names, as used by the self-play logs.
+pub fn default_chain_names() -> [String; MAX_NUM_CHAINS] {
+    ["K", "L", "M", "N", "O", "P", "Q"].map(|s| s.to_owned())
+}
+
+fn parse_board_lines<'a>(
+    lines: &mut impl Iterator<Item = &'a str>,
+    chain_names: [String; MAX_NUM_CHAINS],
+) -> Result<BoardState, String> {
+    let header = (0..=GRID_WIDTH)
+        .map(|col| (b'0' + (col % 10) as u8) as char)
+        .collect::<String>();
+    match lines.next() {
+        Some(line) if line == header => {}
+        line => return Err(format!("Expected board header, got: {:?}", line)),
+    }
+    let initials = chain_names
+        .iter()
+        .map(|name| name.chars().next())
+        .collect::<Vec<Option<char>>>();
+    let mut grid = [[GridCell::Empty; GRID_WIDTH]; GRID_HEIGHT];
+    for (i, row) in grid.iter_mut().enumerate() {
+        let line = lines
+            .next()
+            .ok_or_else(|| format!("Missing board row {}", (b'A' + i as u8) as char))?;
+        let mut chars = line.chars();
+        if chars.next() != Some((b'A' + i as u8) as char) {
+            return Err(format!("Invalid board row: {}", line));
+        }
+        let cells = chars.collect::<Vec<char>>();
+        if cells.len() != GRID_WIDTH {
+            return Err(format!("Invalid board row length: {}", line));
+        }
+        for (cell, &c) in row.iter_mut().zip(cells.iter()) {
+            *cell = match c {
+                '_' => GridCell::Empty,
+                '*' => GridCell::Hotel,
+                'X' => GridCell::Dummy,
+                _ => {
+                    let mut matches = initials.iter().enumerate().filter(|(_, &i)| i == Some(c));
+                    match (matches.next(), matches.next()) {
+                        (Some((idx, _)), None) => GridCell::from_chain_idx(idx),
+                        (Some(_), Some(_)) => {
+                            return Err(format!("Ambiguous chain initial: {}", c));
+                        }
+                        _ => return Err(format!("Unknown chain initial: {}", c)),
+                    }
+                }
+            };
+        }
+    }
+    let stock_market = parse_chain_counts(lines.next(), "Stock market: ")?;
+    let chain_sizes = parse_chain_counts(lines.next(), "Chain sizes: ")?;
+    BoardState::from_parts(grid, chain_sizes, stock_market, chain_names)
+}
+
+fn check_num_players(num_players: usize) -> Result<(), String> {
+    if !(2..=MAX_NUM_PLAYERS).contains(&num_players) {
+        return Err(format!("Invalid number of players: {}", num_players));
+    }
+    Ok(())
+}
+
+// Checks that the turn state is consistent with the board and with the
+// number of tiles in each player's hand.
+fn check_turn_state(
+    board: &BoardState,
+    turn_state: &TurnState,
+    hand_sizes: &[usize],
+) -> Result<(), String> {
+    let TurnState { player, phase } = turn_state;
+    if *player >= hand_sizes.len() {
+        return Err(format!("Invalid turn player: {}", player));
+    }
+    let chains = match phase {
+        TurnPhase::PlaceTile(indices) => {
+            let num_tiles = hand_sizes[*player];
+            if let Some(idx) = indices.iter().find(|&&idx| idx >= num_tiles) {
+                return Err(format!("Invalid tile index: {}", idx));
+            }
+            Vec::new()
+        }
+        TurnPhase::CreateChain(_, chains) => chains.clone(),
+        TurnPhase::PickWinningChain(choices, chains) => [&choices[..], chains].concat(),
+        // The winner is the dummy chain when the game's ending.
+        TurnPhase::DistributeBonuses(winner, chains, bonuses) => {
+            if chains.is_empty() {
+                return Err("No chains left to merge".to_owned());
+            }
+            if bonuses.len() != hand_sizes.len() {
+                return Err(format!("Invalid number of bonuses: {}", bonuses.len()));
+            }
+            match *winner {
+                DUMMY_CHAIN_INDEX => chains.clone(),
+                w => [&[w], &chains[..]].concat(),
+            }
+        }
+        TurnPhase::ResolveMerger(winner, chains, _) => {
+            if chains.is_empty() {
+                return Err("No chains left to merge".to_owned());
+            }
+            [&[*winner], &chains[..]].concat()
+        }
+        TurnPhase::BuyStock(available) => {
+            let market = &board.stock_market;
+            if available.iter().zip(market).any(|(a, m)| a > m) {
+                return Err(format!(
+                    "More stocks available than on the market: {:?}",
+                    available
+                ));
+            }
+            Vec::new()
+        }
+        TurnPhase::GameOver(_) => Vec::new(),
+    };
+    if let Some(chain) = chains.iter().find(|&&c| c >= MAX_NUM_CHAINS) {
+        return Err(format!("Invalid chain index: {}", chain));
+    }
+    match phase {
+        TurnPhase::ResolveMerger(_, _, p) if *p >= hand_sizes.len() => {
+            Err(format!("Invalid merger player: {}", p))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn check_tile(tile: Tile) -> Result<(), String> {
+    if tile.0 >= GRID_HEIGHT || tile.1 >= GRID_WIDTH {
+        // Not Debug, which only works for tiles on the board.
+        return Err(format!("Tile out of bounds: ({}, {})", tile.0, tile.1));
     }
+    Ok(())
 }
 
+// The tile being placed while a new chain is picked, which isn't on the board
+// yet.
+fn pending_tile(phase: &TurnPhase) -> Option<Tile> {
+    match phase {
+        TurnPhase::CreateChain(tile, _) => Some(*tile),
+        _ => None,
+    }
+}
+
+// Index into BoardState::chain_masks for cells that belong to a chain.
+fn chain_mask_index(cell: GridCell) -> Option<usize> {
+    match cell.to_chain_index() {
+        Some(DUMMY_CHAIN_INDEX) => Some(MAX_NUM_CHAINS),
+        idx => idx,
+    }
+}
+
+fn parse_chain_counts(line: Option<&str>, prefix: &str) -> Result<[usize; MAX_NUM_CHAINS], String> {
+    let line = line.ok_or_else(|| format!("Missing line: {}", prefix.trim()))?;
+    let values = line
+        .strip_prefix(prefix)
+        .ok_or_else(|| format!("Expected {:?}, got: {}", prefix.trim(), line))
+        .and_then(parse_usize_list)?;
+    values
+        .try_into()
+        .map_err(|_| format!("Expected {} values: {}", MAX_NUM_CHAINS, line))
+}
+
+// Parses a "[1, 2, 3]" list, as produced by Debug.
+fn parse_usize_list(s: &str) -> Result<Vec<usize>, String> {
+    let inner = s
+        .trim()
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .ok_or_else(|| format!("Invalid list: {}", s))?;
+    if inner.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    inner
+        .split(',')
+        .map(|x| {
+            x.trim()
+                .parse::<usize>()
+                .map_err(|_| format!("Invalid number: {}", x))
+        })
+        .collect()
+}
+
+// Splits "a, [b, c], d" on commas that are not nested inside brackets.
+fn split_top_level(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(s[start..].trim());
+    parts
+}
+
+fn parse_turn_state(line: &str) -> Result<TurnState, String> {
+    let err = || format!("Invalid turn state: {}", line);
+    let inner = line
+        .strip_prefix("TurnState {")
+        .and_then(|s| s.strip_suffix('}'))
+        .ok_or_else(err)?;
+    let (player, phase) = inner.split_once(',').ok_or_else(err)?;
+    let player = player
+        .trim()
+        .strip_prefix("player:")
+        .and_then(|s| s.trim().parse::<usize>().ok())
+        .ok_or_else(err)?;
+    let phase = phase.trim().strip_prefix("phase:").ok_or_else(err)?.trim();
+    let (name, args) = phase.split_once('(').ok_or_else(err)?;
+    let args = split_top_level(args.strip_suffix(')').ok_or_else(err)?);
+    let num = |s: &str| s.parse::<usize>().map_err(|_| err());
+    let phase = match (name, args.as_slice()) {
+        ("PlaceTile", [a]) => TurnPhase::PlaceTile(parse_usize_list(a)?),
+        ("CreateChain", [a, b]) => TurnPhase::CreateChain(a.parse()?, parse_usize_list(b)?),
+        ("PickWinningChain", [a, b]) => {
+            TurnPhase::PickWinningChain(parse_usize_list(a)?, parse_usize_list(b)?)
+        }
+        ("DistributeBonuses", [a, b, c]) => {
+            TurnPhase::DistributeBonuses(num(a)?, parse_usize_list(b)?, parse_usize_list(c)?)
+        }
+        ("ResolveMerger", [a, b, c]) => {
+            TurnPhase::ResolveMerger(num(a)?, parse_usize_list(b)?, num(c)?)
+        }
+        ("BuyStock", [a]) => {
+            TurnPhase::BuyStock(parse_usize_list(a)?.try_into().map_err(|_| err())?)
+        }
+        ("GameOver", [a]) => TurnPhase::GameOver(parse_usize_list(a)?),
+        _ => return Err(err()),
+    };
+    Ok(TurnState { player, phase })
+}
+
+fn parse_player(line: &str, chain_names: &[String]) -> Result<Player, String> {
+    let err = || format!("Invalid player: {}", line);
+    let rest = line.strip_prefix("Cash: $").ok_or_else(err)?;
+    let (cash, rest) = rest.split_once(", Stocks: [").ok_or_else(err)?;
+    let (stocks, tiles) = rest.split_once("], Tiles: [").ok_or_else(err)?;
+    let tiles = tiles.strip_suffix(']').ok_or_else(err)?;
+    let mut player = Player::new(cash.parse().map_err(|_| err())?, vec![]);
+    for entry in stocks.split(", ").filter(|s| !s.is_empty()) {
+        let (name, amount) = entry.rsplit_once(": ").ok_or_else(err)?;
+        let idx = chain_names
+            .iter()
+            .position(|n| n == name)
+            .ok_or_else(|| format!("Unknown chain name: {}", name))?;
+        player.stocks[idx] = amount.parse().map_err(|_| err())?;
+    }
+    for tile in tiles.split(", ").filter(|s| !s.is_empty()) {
+        player.tiles.push(tile.parse()?);
+    }
+    Ok(player)
+}
+
+// The publicly-known parts of a Player.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PublicPlayer {
+    pub cash: usize,
+    pub stocks: [usize; MAX_NUM_CHAINS],
+    pub num_tiles: usize,
+}
+
+// Everything one player is allowed to know about the game.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PlayerView {
+    // Index of the viewing player.
+    pub player: usize,
+    pub board: BoardState,
+    pub players: Vec<PublicPlayer>,
+    // The viewing player's own tiles.
+    pub tiles: Vec<Tile>,
+    pub turn_state: TurnState,
+    pub num_unclaimed_tiles: usize,
+}
+
+impl PlayerView {
+    // Checks the same things as GameState::from_parts, as far as the viewing
+    // player can see them.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        check_num_players(self.players.len())?;
+        if self.player >= self.players.len() {
+            return Err(format!("Invalid viewing player: {}", self.player));
+        }
+        if self
+            .players
+            .iter()
+            .any(|p| p.stocks.iter().any(|&n| n > STOCKS_PER_CHAIN))
+        {
+            return Err("Invalid stock count".to_owned());
+        }
+        if self.tiles.len() != self.players[self.player].num_tiles {
+            return Err(format!("Invalid number of tiles: {}", self.tiles.len()));
+        }
+        let mut seen = self.board.occupied();
+        for &tile in self
+            .tiles
+            .iter()
+            .chain(&pending_tile(&self.turn_state.phase))
+        {
+            check_tile(tile)?;
+            if seen.contains(tile) {
+                return Err(format!("Tile is in two places: {:?}", tile));
+            }
+            seen.insert(tile);
+        }
+        let hand_sizes = self.players.iter().map(|p| p.num_tiles).collect::<Vec<_>>();
+        check_turn_state(&self.board, &self.turn_state, &hand_sizes)
+    }
+}
+
+// Everything anyone watching the game is allowed to know: no player's tiles,
+// not even which of the turn player's tiles are playable.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PublicView {
+    pub board: BoardState,
+    pub players: Vec<PublicPlayer>,
+    pub turn_state: TurnState,
+    pub num_unclaimed_tiles: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(try_from = "GameFields")]
 pub struct GameState {
     pub board: BoardState,
     pub players: Vec<Player>,
     pub turn_state: TurnState,
+    pub(crate) unclaimed_tiles: Vec<Tile>,
+    #[serde(skip)]
+    hash: u64,
+}
+// The serialized form of GameState.
+#[derive(Deserialize)]
+struct GameFields {
+    board: BoardState,
+    players: Vec<Player>,
+    turn_state: TurnState,
     unclaimed_tiles: Vec<Tile>,
 }
+impl TryFrom<GameFields> for GameState {
+    type Error = String;
+    fn try_from(f: GameFields) -> Result<Self, Self::Error> {
+        GameState::from_parts(f.board, f.players, f.turn_state, f.unclaimed_tiles)
+    }
+}
 impl std::fmt::Display for GameState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for (i, p) in self.players.iter().enumerate() {
@@ -208,12 +781,19 @@ impl std::fmt::Display for GameState {
         writeln!(f, "{:?}", self.turn_state)
     }
 }
+impl std::str::FromStr for GameState {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        GameState::parse(s, default_chain_names())
+    }
+}
 impl GameState {
     pub fn new(
         num_players: usize,
         rng: &mut impl rand::Rng,
         chain_names: [String; MAX_NUM_CHAINS],
-    ) -> Self {
+    ) -> Result<Self, String> {
+        check_num_players(num_players)?;
         let mut unclaimed_tiles = (0..GRID_HEIGHT)
             .flat_map(|row| (0..GRID_WIDTH).map(move |col| Tile(row, col)))
             .collect::<Vec<Tile>>();
@@ -237,39 +817,186 @@ impl GameState {
             }
             unclaimed_tiles.append(&mut tmp_tiles);
         }
-        let board = BoardState {
+        let board = BoardState::from_parts(
             grid,
-            chain_sizes: [0; MAX_NUM_CHAINS],
-            stock_market: [STOCKS_PER_CHAIN; MAX_NUM_CHAINS],
+            [0; MAX_NUM_CHAINS],
+            [STOCKS_PER_CHAIN; MAX_NUM_CHAINS],
             chain_names,
-        };
+        )?;
         let turn_state = TurnState {
             player: rng.gen_range(0..num_players),
             phase: TurnPhase::PlaceTile((0..6).collect()),
         };
-        Self {
-            board,
-            players,
-            turn_state,
-            unclaimed_tiles,
-        }
+        Self::from_parts(board, players, turn_state, unclaimed_tiles)
+    }
+    // Deals a new game from a seeded StdRng, so the same seed always gives
+    // the same tile bag and starting player.
+    pub fn from_seed(
+        num_players: usize,
+        seed: u64,
+        chain_names: [String; MAX_NUM_CHAINS],
+    ) -> Result<Self, String> {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        Self::new(num_players, &mut rng, chain_names)
     }
     pub fn from_parts(
         board: BoardState,
         players: Vec<Player>,
         turn_state: TurnState,
         unclaimed_tiles: Vec<Tile>,
-    ) -> Self {
-        Self {
+    ) -> Result<Self, String> {
+        let mut game = Self {
             board,
             players,
             turn_state,
             unclaimed_tiles,
+            hash: 0,
+        };
+        game.validate()?;
+        game.hash = zobrist::hash_game(&game);
+        Ok(game)
+    }
+    // Checks everything the Zobrist keys are indexed by, plus the phase
+    // payloads the rest of the engine relies on. The board's already been
+    // checked by BoardState::from_parts.
+    fn validate(&self) -> Result<(), String> {
+        check_num_players(self.players.len())?;
+        for (i, player) in self.players.iter().enumerate() {
+            if let Some(n) = player.stocks.iter().find(|&&n| n > STOCKS_PER_CHAIN) {
+                return Err(format!("Invalid stock count for player {}: {}", i, n));
+            }
         }
+        // Each tile is in at most one place: on the board, in a hand, in the
+        // unclaimed pile, or being placed.
+        let pending = pending_tile(&self.turn_state.phase);
+        let mut seen = self.board.occupied();
+        let tiles = self.players.iter().flat_map(|p| &p.tiles);
+        for &tile in tiles.chain(&self.unclaimed_tiles).chain(&pending) {
+            check_tile(tile)?;
+            if seen.contains(tile) {
+                return Err(format!("Tile is in two places: {:?}", tile));
+            }
+            seen.insert(tile);
+        }
+        let hand_sizes = self
+            .players
+            .iter()
+            .map(|p| p.tiles.len())
+            .collect::<Vec<_>>();
+        check_turn_state(&self.board, &self.turn_state, &hand_sizes)
+    }
+    // Parses the Display format. Unclaimed (and discarded) tiles are not part
+    // of that format, so every tile not on the board or in play is restored to
+    // the unclaimed pile in row-major order.
+    pub fn parse(s: &str, chain_names: [String; MAX_NUM_CHAINS]) -> Result<Self, String> {
+        let mut lines = s
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty())
+            .peekable();
+        let mut players = Vec::new();
+        while let Some(line) = lines.next_if(|l| l.starts_with("Player ")) {
+            if !line.starts_with(&format!("Player {}: value = $", players.len())) {
+                return Err(format!("Invalid player header: {}", line));
+            }
+            let line = lines
+                .next()
+                .ok_or_else(|| format!("Missing details for player {}", players.len()))?;
+            players.push(parse_player(line, &chain_names)?);
+        }
+        if players.is_empty() {
+            return Err("No players found".to_owned());
+        }
+        let board = parse_board_lines(&mut lines, chain_names)?;
+        let turn_state = parse_turn_state(lines.next().ok_or("Missing turn state")?)?;
+        if let Some(line) = lines.next() {
+            return Err(format!("Unexpected trailing line: {}", line));
+        }
+        let unclaimed_tiles = (0..GRID_HEIGHT)
+            .flat_map(|row| (0..GRID_WIDTH).map(move |col| Tile(row, col)))
+            .filter(|t| board.grid[t.0][t.1] == GridCell::Empty)
+            .filter(|t| !players.iter().any(|p: &Player| p.tiles.contains(t)))
+            .filter(|&t| pending_tile(&turn_state.phase) != Some(t))
+            .collect();
+        Self::from_parts(board, players, turn_state, unclaimed_tiles)
     }
     pub fn num_unclaimed_tiles(&self) -> usize {
         self.unclaimed_tiles.len()
     }
+    // The player who needs to act next. This is the turn player, except while
+    // a merger is being resolved, when each stockholder acts in turn.
+    pub fn acting_player(&self) -> usize {
+        self.turn_state.acting_player()
+    }
+    // Incrementally-maintained Zobrist hash of the position. Only changes made
+    // through take_turn are tracked, so modifying the public fields directly
+    // will invalidate it.
+    pub fn zobrist_hash(&self) -> u64 {
+        self.hash
+    }
+    fn public_players(&self) -> Vec<PublicPlayer> {
+        self.players
+            .iter()
+            .map(|p| PublicPlayer {
+                cash: p.cash,
+                stocks: p.stocks,
+                num_tiles: p.num_tiles(),
+            })
+            .collect()
+    }
+    // The game as seen by one player, without the other players' tiles.
+    pub fn player_view(&self, player: usize) -> PlayerView {
+        PlayerView {
+            player,
+            board: self.board.clone(),
+            players: self.public_players(),
+            tiles: self.players[player].tiles.clone(),
+            turn_state: self.turn_state.clone(),
+            num_unclaimed_tiles: self.unclaimed_tiles.len(),
+        }
+    }
+    // The game as seen by spectators.
+    pub fn public_view(&self) -> PublicView {
+        let mut turn_state = self.turn_state.clone();
+        if let TurnPhase::PlaceTile(playable) = &mut turn_state.phase {
+            playable.clear();
+        }
+        PublicView {
+            board: self.board.clone(),
+            players: self.public_players(),
+            turn_state,
+            num_unclaimed_tiles: self.unclaimed_tiles.len(),
+        }
+    }
+    // A full game consistent with a player's view, dealing the tiles that
+    // player can't see at random to the other players and the unclaimed pile.
+    pub fn from_view(view: &PlayerView, rng: &mut impl rand::Rng) -> Result<Self, String> {
+        let mut hidden = (0..GRID_HEIGHT)
+            .flat_map(|row| (0..GRID_WIDTH).map(move |col| Tile(row, col)))
+            .filter(|&t| !view.board.occupied().contains(t) && !view.tiles.contains(&t))
+            .filter(|&t| pending_tile(&view.turn_state.phase) != Some(t))
+            .collect::<Vec<Tile>>();
+        hidden.shuffle(rng);
+        let players = view
+            .players
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                let tiles = if i == view.player {
+                    view.tiles.clone()
+                } else {
+                    hidden.split_off(hidden.len().saturating_sub(p.num_tiles))
+                };
+                Player {
+                    cash: p.cash,
+                    stocks: p.stocks,
+                    tiles,
+                }
+            })
+            .collect();
+        hidden.truncate(view.num_unclaimed_tiles);
+        GameState::from_parts(view.board.clone(), players, view.turn_state.clone(), hidden)
+    }
     pub fn take_turn(&mut self, action: TurnAction) -> Result<bool, String> {
         match action {
             TurnAction::PlaceTile(idx) => self.place_tile(idx),
@@ -291,7 +1018,7 @@ impl GameState {
         available_stocks
     }
     pub fn stock_price(&self, chain_index: usize) -> usize {
-        chain_stock_price(chain_index, self.board.chain_sizes[chain_index])
+        self.board.stock_price(chain_index)
     }
     pub fn player_value(&self, player: usize) -> usize {
         let mut value = self.players[player].cash;
@@ -305,27 +1032,22 @@ impl GameState {
     fn tile_playability(&self, tile: Tile) -> TilePlayability {
         // A tile cannot be played if it would merge two or more safe chains,
         // or if it would create an 8th chain.
-        let neighbors = grid_neighbors(tile, &self.board.grid);
+        let neighbors = self.board.occupied_neighbors(tile);
         if neighbors.is_empty() {
             return TilePlayability::Playable;
         }
-        let mut neighbor_chains = neighbors
-            .iter()
-            .filter_map(|(_, cell)| cell.to_chain_index())
-            .collect::<Vec<usize>>();
         // Check for new chain creation.
-        if neighbor_chains.is_empty() {
-            return if self.board.chain_sizes.iter().any(|&size| size == 0) {
+        if self.board.chains_touching(neighbors).next().is_none() {
+            return if self.board.chain_sizes.contains(&0) {
                 TilePlayability::Playable
             } else {
                 TilePlayability::TemporarilyUnplayable
             };
         }
         // Check for safe neighbor chains.
-        neighbor_chains.sort_unstable();
-        neighbor_chains.dedup();
-        let num_safe_neighbors = neighbor_chains
-            .into_iter()
+        let num_safe_neighbors = self
+            .board
+            .chains_touching(neighbors)
             .filter(|&i| self.board.chain_sizes[i] >= SAFE_CHAIN_SIZE)
             .count();
         if num_safe_neighbors <= 1 {
@@ -342,27 +1064,26 @@ impl GameState {
         } else {
             return Err(format!("Wrong phase: {:?}", self.turn_state.phase));
         }
-        let tile = self.players[self.turn_state.player].tiles.remove(idx);
+        let tile = self.remove_tile(self.turn_state.player, idx);
         // Check for neighboring chains or hotels.
-        let neighbors = grid_neighbors(tile, &self.board.grid);
+        let neighbors = self.board.occupied_neighbors(tile);
         if neighbors.is_empty() {
             // Just a single hotel.
-            self.board.grid[tile.0][tile.1] = GridCell::Hotel;
+            self.set_cell(tile, GridCell::Hotel);
             let available_stocks = self.available_stocks();
             if available_stocks.iter().any(|&x| x > 0) {
-                self.turn_state.phase = TurnPhase::BuyStock(available_stocks);
+                self.set_phase(TurnPhase::BuyStock(available_stocks));
             } else {
                 self.next_player();
             }
             return Ok(());
         }
         // Find neighboring tiles that are part of a chain.
-        let mut candidates = neighbors
-            .iter()
-            .filter_map(|(_, cell)| cell.to_chain_index())
+        let mut candidates = self
+            .board
+            .chains_touching(neighbors)
             .collect::<Vec<usize>>();
-        candidates.sort_unstable();
-        candidates.dedup();
+        let neighbor_hotels = neighbors & self.board.hotels();
         match candidates.len() {
             // New chain.
             0 => {
@@ -374,21 +1095,19 @@ impl GameState {
                     .filter(|(_, &size)| size == 0)
                     .map(|(i, _)| i)
                     .collect::<Vec<usize>>();
-                self.turn_state.phase = TurnPhase::CreateChain(tile, available_chains);
+                self.set_phase(TurnPhase::CreateChain(tile, available_chains));
             }
             // Joining an existing chain.
             1 => {
                 let chain_index = candidates[0];
                 let chain = GridCell::from_chain_idx(chain_index);
-                self.board.grid[tile.0][tile.1] = chain;
-                self.board.chain_sizes[chain_index] += 1;
-                for (t, cell) in neighbors {
-                    if let GridCell::Hotel = cell {
-                        self.board.grid[t.0][t.1] = chain;
-                        self.board.chain_sizes[chain_index] += 1;
-                    }
+                self.set_cell(tile, chain);
+                for t in neighbor_hotels.tiles() {
+                    self.set_cell(t, chain);
                 }
-                self.turn_state.phase = TurnPhase::BuyStock(self.available_stocks());
+                let chain_size = self.board.chain_sizes[chain_index] + 1 + neighbor_hotels.count();
+                self.set_chain_size(chain_index, chain_size);
+                self.set_phase(TurnPhase::BuyStock(self.available_stocks()));
             }
             // Merging 2+ chains.
             _ => {
@@ -405,185 +1124,202 @@ impl GameState {
                 // This brings adjacent non-chain hotels into the chain, even
                 // though the player may still need to decide which chain is
                 // the winner of the merger.
-                self.board.grid[tile.0][tile.1] = GridCell::Dummy;
-                for (t, c) in neighbors {
-                    if let GridCell::Hotel = c {
-                        self.board.grid[t.0][t.1] = GridCell::Dummy;
-                    }
+                self.set_cell(tile, GridCell::Dummy);
+                for t in neighbor_hotels.tiles() {
+                    self.set_cell(t, GridCell::Dummy);
                 }
-                self.turn_state.phase = TurnPhase::PickWinningChain(winner_choices, candidates);
+                self.set_phase(TurnPhase::PickWinningChain(winner_choices, candidates));
             }
         }
         Ok(())
     }
     fn create_chain(&mut self, chain_index: usize) -> Result<(), String> {
-        if let TurnPhase::CreateChain(tile, valid_indices) = &self.turn_state.phase {
-            if !valid_indices.contains(&chain_index) {
-                return Err(format!("Invalid chain index: {}", chain_index));
-            }
-            if self.board.chain_sizes[chain_index] != 0 {
-                return Err(format!("Chain {} already exists", chain_index));
-            }
-            let neighbors = grid_neighbors(*tile, &self.board.grid);
-            self.board.chain_sizes[chain_index] = 1 + neighbors.len();
-            let chain = GridCell::from_chain_idx(chain_index);
-            self.board.grid[tile.0][tile.1] = chain;
-            for (t, _) in neighbors {
-                self.board.grid[t.0][t.1] = chain;
-            }
-            // Founder's bonus: one free stock.
-            if self.board.stock_market[chain_index] > 0 {
-                self.board.stock_market[chain_index] -= 1;
-                self.players[self.turn_state.player].stocks[chain_index] += 1;
-            }
-            self.turn_state.phase = TurnPhase::BuyStock(self.available_stocks());
-            Ok(())
-        } else {
-            Err(format!("Wrong phase: {:?}", self.turn_state.phase))
+        let tile = match &self.turn_state.phase {
+            TurnPhase::CreateChain(tile, valid_indices) => {
+                if !valid_indices.contains(&chain_index) {
+                    return Err(format!("Invalid chain index: {}", chain_index));
+                }
+                *tile
+            }
+            _ => return Err(format!("Wrong phase: {:?}", self.turn_state.phase)),
+        };
+        if self.board.chain_sizes[chain_index] != 0 {
+            return Err(format!("Chain {} already exists", chain_index));
         }
+        let neighbors = self.board.occupied_neighbors(tile);
+        self.set_chain_size(chain_index, 1 + neighbors.count());
+        let chain = GridCell::from_chain_idx(chain_index);
+        self.set_cell(tile, chain);
+        for t in neighbors.tiles() {
+            self.set_cell(t, chain);
+        }
+        // Founder's bonus: one free stock.
+        let num_available = self.board.stock_market[chain_index];
+        if num_available > 0 {
+            let player = self.turn_state.player;
+            self.set_market(chain_index, num_available - 1);
+            self.set_stocks(
+                player,
+                chain_index,
+                self.players[player].stocks[chain_index] + 1,
+            );
+        }
+        self.set_phase(TurnPhase::BuyStock(self.available_stocks()));
+        Ok(())
     }
     fn pick_winning_chain(&mut self, chain_index: usize) -> Result<(), String> {
-        if let TurnPhase::PickWinningChain(valid_indices, merging_chains) = &self.turn_state.phase {
-            if !valid_indices.contains(&chain_index) {
-                return Err(format!("Invalid chain index: {}", chain_index));
-            }
-            let loser_chains = merging_chains
-                .iter()
-                .filter(|&&i| i != chain_index)
-                .copied()
-                .collect::<Vec<usize>>();
-            // Update the grid and count additions to the winning chain.
-            let winner_chain = GridCell::from_chain_idx(chain_index);
-            let mut new_hotels = 0;
-            for row in &mut self.board.grid {
-                for cell in row {
-                    if *cell == GridCell::Dummy {
-                        *cell = winner_chain;
-                        new_hotels += 1;
-                    } else if let Some(idx) = cell.to_chain_index() {
-                        if loser_chains.contains(&idx) {
-                            *cell = winner_chain;
-                            new_hotels += 1;
-                        }
-                    }
+        let merging_chains = match &self.turn_state.phase {
+            TurnPhase::PickWinningChain(valid_indices, merging_chains) => {
+                if !valid_indices.contains(&chain_index) {
+                    return Err(format!("Invalid chain index: {}", chain_index));
                 }
+                merging_chains.clone()
             }
-            // Update the winning chain's size.
-            self.board.chain_sizes[chain_index] += new_hotels;
-
-            // Pay bonuses to players who have stocks in the losing chains.
-            let idx = loser_chains[0];
-            let bonus_cash = calculate_bonuses(idx, self.stock_price(idx), &self.players);
-            self.turn_state.phase =
-                TurnPhase::DistributeBonuses(chain_index, loser_chains, bonus_cash);
-            Ok(())
-        } else {
-            Err(format!("Wrong phase: {:?}", self.turn_state.phase))
+            _ => return Err(format!("Wrong phase: {:?}", self.turn_state.phase)),
+        };
+        let loser_chains = merging_chains
+            .into_iter()
+            .filter(|&i| i != chain_index)
+            .collect::<Vec<usize>>();
+        // Update the grid and count additions to the winning chain.
+        let winner_chain = GridCell::from_chain_idx(chain_index);
+        let mut new_hotels = 0;
+        for (tile, old) in self.board.merge_cells(chain_index, &loser_chains) {
+            self.hash ^= zobrist::cell_key(tile, old) ^ zobrist::cell_key(tile, winner_chain);
+            new_hotels += 1;
         }
+        // Update the winning chain's size.
+        self.set_chain_size(
+            chain_index,
+            self.board.chain_sizes[chain_index] + new_hotels,
+        );
+
+        // Pay bonuses to players who have stocks in the losing chains.
+        let idx = loser_chains[0];
+        let bonus_cash = calculate_bonuses(idx, self.stock_price(idx), &self.players);
+        self.set_phase(TurnPhase::DistributeBonuses(
+            chain_index,
+            loser_chains,
+            bonus_cash,
+        ));
+        Ok(())
     }
     fn award_bonuses(&mut self) -> Result<(), String> {
-        if let TurnPhase::DistributeBonuses(winner_chain, loser_chains, bonus_cash) =
-            &self.turn_state.phase
-        {
-            // Deposit the bonus for each player.
-            for (i, &bonus) in bonus_cash.iter().enumerate() {
-                self.players[i].cash += bonus;
-            }
-            if *winner_chain == DUMMY_CHAIN_INDEX {
-                // It's the end of the game.
-                if loser_chains.len() > 1 {
-                    // More bonuses to award.
-                    let idx = loser_chains[1];
-                    self.turn_state.phase = TurnPhase::DistributeBonuses(
-                        DUMMY_CHAIN_INDEX,
-                        loser_chains[1..].to_vec(),
-                        calculate_bonuses(idx, self.stock_price(idx), &self.players),
-                    );
-                } else {
-                    let final_values = (0..self.players.len())
-                        .map(|i| self.player_value(i))
-                        .collect();
-                    self.turn_state.phase = TurnPhase::GameOver(final_values);
-                }
+        let (winner_chain, loser_chains, bonus_cash) = match &self.turn_state.phase {
+            TurnPhase::DistributeBonuses(winner_chain, loser_chains, bonus_cash) => {
+                (*winner_chain, loser_chains.clone(), bonus_cash.clone())
+            }
+            _ => return Err(format!("Wrong phase: {:?}", self.turn_state.phase)),
+        };
+        // Deposit the bonus for each player.
+        for (i, &bonus) in bonus_cash.iter().enumerate() {
+            self.set_cash(i, self.players[i].cash + bonus);
+        }
+        if winner_chain == DUMMY_CHAIN_INDEX {
+            // It's the end of the game.
+            if loser_chains.len() > 1 {
+                // More bonuses to award.
+                let idx = loser_chains[1];
+                self.set_phase(TurnPhase::DistributeBonuses(
+                    DUMMY_CHAIN_INDEX,
+                    loser_chains[1..].to_vec(),
+                    calculate_bonuses(idx, self.stock_price(idx), &self.players),
+                ));
             } else {
-                // Ask the player to sell/trade shares of the first losing chain.
-                self.turn_state.phase = TurnPhase::ResolveMerger(
-                    *winner_chain,
-                    loser_chains.to_vec(),
-                    self.turn_state.player,
-                );
+                let final_values = (0..self.players.len())
+                    .map(|i| self.player_value(i))
+                    .collect();
+                self.set_phase(TurnPhase::GameOver(final_values));
             }
-            Ok(())
         } else {
-            Err(format!("Wrong phase: {:?}", self.turn_state.phase))
+            // Ask the player to sell/trade shares of the first losing chain.
+            self.set_phase(TurnPhase::ResolveMerger(
+                winner_chain,
+                loser_chains,
+                self.turn_state.player,
+            ));
         }
+        Ok(())
     }
     fn resolve_merger(&mut self, sell_amount: usize, trade_amount: usize) -> Result<(), String> {
-        if let TurnPhase::ResolveMerger(winner_chain, loser_chains, selling_player) =
-            &self.turn_state.phase
-        {
-            let loser_index = loser_chains[0];
-            let loser_price = self.stock_price(loser_index);
-
-            let num_not_kept = sell_amount + trade_amount;
-            let num_traded = trade_amount / 2;
-            // Validate that the selling player has enough stocks to sell / trade.
-            let prev_stocks = self.players[*selling_player].stocks[loser_index];
-            if prev_stocks < num_not_kept {
-                return Err(format!(
-                    "Cannot sell/trade {} stocks of {}, only have {} total.",
-                    num_not_kept, self.board.chain_names[loser_index], prev_stocks
-                ));
+        let (winner_chain, loser_chains, selling_player) = match &self.turn_state.phase {
+            TurnPhase::ResolveMerger(winner_chain, loser_chains, selling_player) => {
+                (*winner_chain, loser_chains.clone(), *selling_player)
             }
-            // Validate that there are enough winner chain stocks to trade for.
-            if num_traded > self.board.stock_market[*winner_chain] {
+            _ => return Err(format!("Wrong phase: {:?}", self.turn_state.phase)),
+        };
+        let loser_index = loser_chains[0];
+        let loser_price = self.stock_price(loser_index);
+
+        // Validate that the selling player has enough stocks to sell / trade.
+        let prev_stocks = self.players[selling_player].stocks[loser_index];
+        let num_not_kept = match sell_amount.checked_add(trade_amount) {
+            Some(n) if n <= prev_stocks => n,
+            _ => {
                 return Err(format!(
-                    "Cannot trade {} stocks of {}, market has {} available.",
-                    num_traded,
-                    self.board.chain_names[*winner_chain],
-                    self.board.stock_market[*winner_chain]
-                ));
+                    "Cannot sell {} and trade {} stocks of {}, only have {} total.",
+                    sell_amount, trade_amount, self.board.chain_names[loser_index], prev_stocks
+                ))
             }
+        };
+        let num_traded = trade_amount / 2;
+        // Validate that there are enough winner chain stocks to trade for.
+        if num_traded > self.board.stock_market[winner_chain] {
+            return Err(format!(
+                "Cannot trade {} stocks of {}, market has {} available.",
+                num_traded,
+                self.board.chain_names[winner_chain],
+                self.board.stock_market[winner_chain]
+            ));
+        }
 
-            self.players[*selling_player].stocks[loser_index] -= num_not_kept;
-            self.players[*selling_player].cash += loser_price * sell_amount;
-            self.board.stock_market[loser_index] += num_not_kept;
-            self.board.stock_market[*winner_chain] -= num_traded;
-            self.players[*selling_player].stocks[*winner_chain] += num_traded;
-
-            let next_player = (selling_player + 1) % self.players.len();
-            if next_player == self.turn_state.player {
-                // This merger is complete.
-                self.board.chain_sizes[loser_index] = 0;
-                if loser_chains.len() > 1 {
-                    // There are more mergers to resolve.
-                    let idx = loser_chains[1];
-                    self.turn_state.phase = TurnPhase::DistributeBonuses(
-                        *winner_chain,
-                        loser_chains[1..].to_vec(),
-                        calculate_bonuses(idx, self.stock_price(idx), &self.players),
-                    );
-                } else {
-                    // All mergers are resolved, move on to the buy phase.
-                    self.turn_state.phase = TurnPhase::BuyStock(self.available_stocks());
-                }
+        let player = &self.players[selling_player];
+        let (winner_stocks, cash) = (player.stocks[winner_chain], player.cash);
+        self.set_stocks(selling_player, loser_index, prev_stocks - num_not_kept);
+        self.set_cash(selling_player, cash + loser_price * sell_amount);
+        self.set_market(
+            loser_index,
+            self.board.stock_market[loser_index] + num_not_kept,
+        );
+        self.set_market(
+            winner_chain,
+            self.board.stock_market[winner_chain] - num_traded,
+        );
+        self.set_stocks(selling_player, winner_chain, winner_stocks + num_traded);
+
+        let next_player = (selling_player + 1) % self.players.len();
+        if next_player == self.turn_state.player {
+            // This merger is complete.
+            self.set_chain_size(loser_index, 0);
+            if loser_chains.len() > 1 {
+                // There are more mergers to resolve.
+                let idx = loser_chains[1];
+                self.set_phase(TurnPhase::DistributeBonuses(
+                    winner_chain,
+                    loser_chains[1..].to_vec(),
+                    calculate_bonuses(idx, self.stock_price(idx), &self.players),
+                ));
             } else {
-                // Let the next player resolve the merger.
-                self.turn_state.phase =
-                    TurnPhase::ResolveMerger(*winner_chain, loser_chains.clone(), next_player);
+                // All mergers are resolved, move on to the buy phase.
+                self.set_phase(TurnPhase::BuyStock(self.available_stocks()));
             }
-            Ok(())
         } else {
-            Err(format!("Wrong phase: {:?}", self.turn_state.phase))
+            // Let the next player resolve the merger.
+            self.set_phase(TurnPhase::ResolveMerger(
+                winner_chain,
+                loser_chains,
+                next_player,
+            ));
         }
+        Ok(())
     }
     fn buy_stock(&mut self, buy_order: [usize; MAX_NUM_CHAINS]) -> Result<(), String> {
         if let TurnPhase::BuyStock(available) = &self.turn_state.phase {
-            if buy_order.iter().sum::<usize>() > BUY_LIMIT {
-                return Err(format!(
-                    "Too many stocks bought: {}",
-                    buy_order.iter().sum::<usize>()
-                ));
+            let total = buy_order
+                .iter()
+                .try_fold(0usize, |sum, &n| sum.checked_add(n));
+            if total.is_none_or(|total| total > BUY_LIMIT) {
+                return Err(format!("Too many stocks bought: {:?}", buy_order));
             }
             for (chain_index, &num_stocks) in buy_order.iter().enumerate() {
                 if available[chain_index] < num_stocks {
@@ -602,17 +1338,24 @@ impl GameState {
                 cash_spent += self.stock_price(chain_index) * num_stocks;
             }
         }
-        let player = &mut self.players[self.turn_state.player];
+        let player_idx = self.turn_state.player;
+        let player = &self.players[player_idx];
         if cash_spent > player.cash {
             return Err(format!(
                 "Not enough cash to buy stocks. Price: ${} > Cash: ${}",
                 cash_spent, player.cash
             ));
         }
-        player.cash -= cash_spent;
-        for (chain_index, num_stocks) in buy_order.iter().enumerate() {
-            player.stocks[chain_index] += num_stocks;
-            self.board.stock_market[chain_index] -= num_stocks;
+        self.set_cash(player_idx, player.cash - cash_spent);
+        for (chain_index, &num_stocks) in buy_order.iter().enumerate() {
+            if num_stocks > 0 {
+                let held = self.players[player_idx].stocks[chain_index];
+                self.set_stocks(player_idx, chain_index, held + num_stocks);
+                self.set_market(
+                    chain_index,
+                    self.board.stock_market[chain_index] - num_stocks,
+                );
+            }
         }
         self.next_player();
         Ok(())
@@ -623,7 +1366,7 @@ impl GameState {
             match self.tile_playability(tile) {
                 TilePlayability::PermanentlyUnplayable => {}
                 _ => {
-                    self.players[self.turn_state.player].tiles.push(tile);
+                    self.add_tile(self.turn_state.player, tile);
                     break;
                 }
             }
@@ -647,13 +1390,16 @@ impl GameState {
             let idx = active_chains[0];
             let bonus_cash =
                 calculate_bonuses(idx, chain_stock_price(idx, chain_sizes[idx]), &self.players);
-            self.turn_state.phase =
-                TurnPhase::DistributeBonuses(DUMMY_CHAIN_INDEX, active_chains, bonus_cash);
+            self.set_phase(TurnPhase::DistributeBonuses(
+                DUMMY_CHAIN_INDEX,
+                active_chains,
+                bonus_cash,
+            ));
             return;
         }
 
         // Advance to the next player's turn.
-        self.turn_state.player = (self.turn_state.player + 1) % self.players.len();
+        self.set_turn_player((self.turn_state.player + 1) % self.players.len());
         let playable_tiles = self.players[self.turn_state.player]
             .tiles
             .iter()
@@ -664,17 +1410,65 @@ impl GameState {
             })
             .collect::<Vec<usize>>();
         if playable_tiles.is_empty() {
-            self.turn_state.phase = TurnPhase::BuyStock(self.available_stocks());
+            self.set_phase(TurnPhase::BuyStock(self.available_stocks()));
         } else {
-            self.turn_state.phase = TurnPhase::PlaceTile(playable_tiles);
+            self.set_phase(TurnPhase::PlaceTile(playable_tiles));
         }
     }
+    // State mutators that keep the Zobrist hash up to date.
+    fn set_cell(&mut self, tile: Tile, cell: GridCell) {
+        let old = self.board.grid[tile.0][tile.1];
+        self.hash ^= zobrist::cell_key(tile, old) ^ zobrist::cell_key(tile, cell);
+        self.board.set_cell(tile, cell);
+    }
+    fn set_chain_size(&mut self, chain_index: usize, size: usize) {
+        let old = &mut self.board.chain_sizes[chain_index];
+        self.hash ^=
+            zobrist::chain_size_key(chain_index, *old) ^ zobrist::chain_size_key(chain_index, size);
+        *old = size;
+    }
+    fn set_market(&mut self, chain_index: usize, num_stocks: usize) {
+        let old = &mut self.board.stock_market[chain_index];
+        self.hash ^=
+            zobrist::market_key(chain_index, *old) ^ zobrist::market_key(chain_index, num_stocks);
+        *old = num_stocks;
+    }
+    fn set_stocks(&mut self, player: usize, chain_index: usize, num_stocks: usize) {
+        let old = &mut self.players[player].stocks[chain_index];
+        self.hash ^= zobrist::holding_key(player, chain_index, *old)
+            ^ zobrist::holding_key(player, chain_index, num_stocks);
+        *old = num_stocks;
+    }
+    fn set_cash(&mut self, player: usize, cash: usize) {
+        let old = &mut self.players[player].cash;
+        self.hash ^= zobrist::cash_key(player, *old) ^ zobrist::cash_key(player, cash);
+        *old = cash;
+    }
+    fn remove_tile(&mut self, player: usize, idx: usize) -> Tile {
+        let tile = self.players[player].tiles.remove(idx);
+        self.hash ^= zobrist::hand_key(player, tile);
+        tile
+    }
+    fn add_tile(&mut self, player: usize, tile: Tile) {
+        self.hash ^= zobrist::hand_key(player, tile);
+        self.players[player].tiles.push(tile);
+    }
+    fn set_phase(&mut self, phase: TurnPhase) {
+        self.hash ^= zobrist::turn_key(&self.turn_state);
+        self.turn_state.phase = phase;
+        self.hash ^= zobrist::turn_key(&self.turn_state);
+    }
+    fn set_turn_player(&mut self, player: usize) {
+        self.hash ^= zobrist::turn_key(&self.turn_state);
+        self.turn_state.player = player;
+        self.hash ^= zobrist::turn_key(&self.turn_state);
+    }
 }
 
 fn distribute_bonus(bonus: usize, receiving_players: &[usize], cash: &mut [usize]) {
     let mut amount = bonus / receiving_players.len();
     // Round up to the nearest 100.
-    if amount % 100 != 0 {
+    if !amount.is_multiple_of(100) {
         amount += 100 - (amount % 100);
     }
     for p in receiving_players {
@@ -683,7 +1477,7 @@ fn distribute_bonus(bonus: usize, receiving_players: &[usize], cash: &mut [usize
 }
 
 // Given a stock and its price, calculate the bonuses to be awarded to players.
-fn calculate_bonuses(stock_index: usize, stock_price: usize, players: &[Player]) -> Vec<usize> {
+pub fn calculate_bonuses(stock_index: usize, stock_price: usize, players: &[Player]) -> Vec<usize> {
     let majority_bonus = stock_price * 10;
     let second_bonus = majority_bonus / 2;
     // Sort players by how many of this stock they have.
@@ -776,7 +1570,7 @@ fn grid_neighbors(
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
 
     #[test]
@@ -787,6 +1581,208 @@ mod tests {
         assert_eq!(format!("{:?}", Tile(9, 9)), "10-J");
     }
 
+    #[test]
+    fn tile_from_str() {
+        assert_eq!("1-A".parse::<Tile>(), Ok(Tile(0, 0)));
+        assert_eq!("10-a".parse::<Tile>(), Ok(Tile(0, 9)));
+        assert_eq!("12-I".parse::<Tile>(), Ok(Tile(8, 11)));
+        assert!("13-A".parse::<Tile>().is_err());
+        assert!("0-A".parse::<Tile>().is_err());
+        assert!("1-J".parse::<Tile>().is_err());
+        assert!("A-1".parse::<Tile>().is_err());
+    }
+
+    #[test]
+    fn board_parse_roundtrip() {
+        let text = "\
+0123456789012
+A*___________
+B_KK_________
+C__K_____*___
+D____________
+E______XLL___
+F________L___
+G____________
+H___________*
+I____________
+Stock market: [24, 25, 25, 25, 25, 25, 25]
+Chain sizes: [3, 3, 0, 0, 0, 0, 0]
+";
+        let board = text.parse::<BoardState>().unwrap();
+        assert_eq!(board.grid[1][1], GridCell::Chain0);
+        assert_eq!(board.grid[4][6], GridCell::Dummy);
+        assert_eq!(board.grid[5][8], GridCell::Chain1);
+        assert_eq!(board.stock_market[0], 24);
+        assert_eq!(board.chain_sizes[1], 3);
+        assert_eq!(board.to_string(), text);
+    }
+
+    #[test]
+    fn board_parse_errors() {
+        assert!("".parse::<BoardState>().is_err());
+        let mut text = make_game().board.to_string();
+        assert!(text.parse::<BoardState>().is_ok());
+        // Unknown chain initial.
+        text.replace_range(15..16, "Z");
+        assert_eq!(
+            text.parse::<BoardState>().err(),
+            Some("Unknown chain initial: Z".to_owned())
+        );
+        // Ambiguous chain initials.
+        let mut names = default_chain_names();
+        names[1] = "Z".to_owned();
+        names[2] = "Zed".to_owned();
+        assert!(BoardState::parse(&text, names).is_err());
+    }
+
+    #[test]
+    fn game_parse_roundtrip() {
+        let mut game = make_game();
+        let ai = crate::agent::create_agent(1);
+        loop {
+            let parsed = game.to_string().parse::<GameState>().unwrap();
+            assert_eq!(parsed.to_string(), game.to_string());
+            if game.take_turn(ai.choose_action(&game)).unwrap() {
+                break;
+            }
+        }
+    }
+
+    fn check_chain_cells(board: &BoardState) {
+        let all_tiles = || (0..NUM_TILES).filter_map(Tile::from_index);
+        for i in 0..=MAX_NUM_CHAINS {
+            let expected = all_tiles()
+                .filter(|t| chain_mask_index(board.grid[t.0][t.1]) == Some(i))
+                .map(Tile::to_index)
+                .collect::<Vec<usize>>();
+            let actual = board.chain_masks[i]
+                .tiles()
+                .map(Tile::to_index)
+                .collect::<Vec<usize>>();
+            assert_eq!(actual, expected, "{}", board);
+        }
+        for tile in all_tiles() {
+            assert_eq!(
+                board.occupied.contains(tile),
+                board.grid[tile.0][tile.1] != GridCell::Empty
+            );
+            assert_eq!(
+                board.num_neighbors(tile),
+                grid_neighbors(tile, &board.grid).len()
+            );
+        }
+    }
+
+    #[test]
+    fn game_serde_roundtrip() {
+        let game = make_game();
+        let json = serde_json::to_string(&game).unwrap();
+        let parsed: GameState = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.to_string(), game.to_string());
+        assert_eq!(parsed.unclaimed_tiles, game.unclaimed_tiles);
+        assert_eq!(parsed.zobrist_hash(), game.zobrist_hash());
+    }
+
+    #[test]
+    fn rejects_invalid_states() {
+        assert_eq!(
+            GameState::from_seed(7, 1, default_chain_names()).err(),
+            Some("Invalid number of players: 7".to_owned())
+        );
+        assert!(GameState::from_seed(1, 1, default_chain_names()).is_err());
+        let mut names = default_chain_names();
+        names[3] = String::new();
+        assert!(GameState::from_seed(3, 1, names).is_err());
+        let mut names = default_chain_names();
+        names[3] = names[0].clone();
+        assert!(GameState::from_seed(3, 1, names).is_err());
+
+        // Deserializing checks everything the Zobrist keys are indexed by.
+        let parse = |game: &GameState| {
+            serde_json::from_str::<GameState>(&serde_json::to_string(game).unwrap()).err()
+        };
+        let game = make_game();
+        assert!(parse(&game).is_none());
+        let mut bad = game.clone();
+        bad.players[0].stocks[2] = 26;
+        assert!(parse(&bad)
+            .unwrap()
+            .to_string()
+            .contains("Invalid stock count"));
+        let mut bad = game.clone();
+        bad.board.stock_market[2] = 26;
+        assert!(parse(&bad).is_some());
+        let mut bad = game.clone();
+        bad.board.chain_sizes[2] = NUM_TILES + 1;
+        assert!(parse(&bad).is_some());
+        let mut bad = game.clone();
+        bad.turn_state.phase = TurnPhase::ResolveMerger(0, vec![7], 0);
+        assert!(parse(&bad)
+            .unwrap()
+            .to_string()
+            .contains("Invalid chain index"));
+        let mut bad = game.clone();
+        bad.turn_state.phase = TurnPhase::ResolveMerger(0, vec![1], 4);
+        assert!(parse(&bad).is_some());
+        let mut bad = game.clone();
+        bad.unclaimed_tiles.push(Tile(9, 0));
+        assert!(parse(&bad).is_some());
+        let mut bad = game.clone();
+        bad.unclaimed_tiles.push(bad.players[1].tiles[0]);
+        assert!(parse(&bad).unwrap().to_string().contains("two places"));
+        let mut bad = game;
+        bad.players.push(Player::new(6000, Vec::new()));
+        assert!(parse(&bad).is_none());
+        bad.turn_state.player = 4;
+        assert!(parse(&bad).is_some());
+    }
+
+    #[test]
+    fn game_from_view() {
+        let game = make_game();
+        let view = game.player_view(1);
+        let dealt = GameState::from_view(&view, &mut rand::thread_rng()).unwrap();
+        let json = serde_json::to_string(&view).unwrap();
+        assert_eq!(serde_json::to_string(&dealt.player_view(1)).unwrap(), json);
+        assert_eq!(dealt.players[0].tiles.len(), game.players[0].tiles.len());
+        assert!(!dealt.players[0]
+            .tiles
+            .iter()
+            .any(|t| view.tiles.contains(t)));
+        // Every position can be dealt, including one where a tile is waiting
+        // for its new chain.
+        let mut game = GameState::from_seed(2, 1, default_chain_names()).unwrap();
+        let ai = crate::agent::create_seeded_agent(1, 1);
+        let mut created = false;
+        loop {
+            let view = game.player_view(game.acting_player());
+            GameState::from_view(&view, &mut rand::thread_rng()).unwrap();
+            created |= matches!(game.turn_state.phase, TurnPhase::CreateChain(_, _));
+            if game.take_turn(ai.choose_action(&game)).unwrap() {
+                break;
+            }
+        }
+        assert!(created);
+    }
+
+    #[test]
+    fn tracks_chain_cells() {
+        let mut game = GameState::new(4, &mut rand::thread_rng(), default_chain_names()).unwrap();
+        let ai = crate::agent::create_agent(1);
+        loop {
+            check_chain_cells(&game.board);
+            if game.take_turn(ai.choose_action(&game)).unwrap() {
+                break;
+            }
+        }
+        check_chain_cells(&game.board);
+        // Chain cells are rebuilt when deserializing.
+        let json = serde_json::to_string(&game.board).unwrap();
+        let board = serde_json::from_str::<BoardState>(&json).unwrap();
+        assert_eq!(board.to_string(), game.board.to_string());
+        check_chain_cells(&board);
+    }
+
     #[test]
     fn player_display() {
         let chain_names = ["A".to_string(), "B".to_string(), "C".to_string()];
@@ -829,9 +1825,31 @@ mod tests {
             "Q".to_owned(),
         ];
         let mut rng = rand::thread_rng();
-        GameState::new(2, &mut rng, chain_names)
+        GameState::new(2, &mut rng, chain_names).unwrap()
     }
 
+    // A two-player position where 4-C would merge K and L, shared with the
+    // tests of the modules that parse and encode states.
+    pub(crate) const MERGER_STATE: &str = "\
+Player 0: value = $6500
+  Cash: $6000, Stocks: [K: 1], Tiles: [1-A, 4-C, 9-I]
+Player 1: value = $6500
+  Cash: $5800, Stocks: [L: 2, M: 1], Tiles: [2-B, 8-H]
+0123456789012
+A____________
+B____________
+C_KK_LL______
+D____________
+E____________
+F____________
+G____________
+H____________
+I____________
+Stock market: [24, 23, 24, 25, 25, 25, 25]
+Chain sizes: [2, 2, 0, 0, 0, 0, 0]
+TurnState { player: 0, phase: PlaceTile([0, 1, 2]) }
+";
+
     #[test]
     fn game_display() {
         assert!(make_game().to_string().contains("Player 0: value = $6000"));
@@ -842,6 +1860,33 @@ mod tests {
         assert_eq!(make_game().num_unclaimed_tiles(), 94);
     }
 
+    #[test]
+    fn rejects_overflowing_amounts() {
+        let mut game = GameState::from_seed(4, 3, default_chain_names()).unwrap();
+        let ai = crate::agent::create_seeded_agent(1, 3);
+        let (mut merged, mut bought) = (false, false);
+        while !(merged && bought) {
+            let hash = game.zobrist_hash();
+            match game.turn_state.phase {
+                TurnPhase::ResolveMerger(_, _, _) => {
+                    let action = TurnAction::ResolveMerger(usize::MAX - 1, 2);
+                    assert!(game.take_turn(action).is_err());
+                    merged = true;
+                }
+                TurnPhase::BuyStock(_) => {
+                    let mut order = [0; MAX_NUM_CHAINS];
+                    order[0] = usize::MAX;
+                    order[1] = 1;
+                    assert!(game.take_turn(TurnAction::BuyStock(order)).is_err());
+                    bought = true;
+                }
+                _ => {}
+            }
+            assert_eq!(game.zobrist_hash(), hash);
+            assert!(!game.take_turn(ai.choose_action(&game)).unwrap());
+        }
+    }
+
     #[test]
     fn distributes_bonus() {
         let mut cash = [0; 3];
@@ -0,0 +1,383 @@
+// Parses commands typed by human players into actions, so every front-end
+// shares the same command language. What a command means depends on the
+// phase of the turn:
+//
+//   PlaceTile          a tile in hand, like "7-C" or "7c"
+//   CreateChain        a chain name, or a unique prefix of one, like "dav"
+//   PickWinningChain   a chain name, or nothing if there's only one choice
+//   DistributeBonuses  nothing
+//   ResolveMerger      "sell 3 trade 4", "trade 2", "keep", or nothing
+//   BuyStock           "dave 2, eve", "dave dave eve", or nothing
+//
+// Chain names are matched ignoring case. Unlike notation::parse_action,
+// commands are checked against the choices on offer, so mistakes get an
+// explanation instead of a bare error from GameState::take_turn.
+use crate::game::{
+    BoardState, GameState, PlayerView, Tile, TurnAction, TurnPhase, TurnState, BUY_LIMIT,
+    MAX_NUM_CHAINS,
+};
+
+// Parses a command from the acting player.
+pub fn parse_input(game: &GameState, text: &str) -> Result<TurnAction, String> {
+    let tiles = &game.players[game.turn_state.player].tiles;
+    parse_command(&game.board, &game.turn_state, tiles, text)
+}
+
+// Like parse_input, for a player who only has their own view of the game.
+pub fn parse_view_input(view: &PlayerView, text: &str) -> Result<TurnAction, String> {
+    if view.turn_state.acting_player() != view.player {
+        return Err(format!("It's P{}'s turn", view.turn_state.acting_player()));
+    }
+    parse_command(&view.board, &view.turn_state, &view.tiles, text)
+}
+
+// Describes what the acting player should enter next.
+pub fn prompt(game: &GameState) -> String {
+    let board = &game.board;
+    match &game.turn_state.phase {
+        TurnPhase::PlaceTile(valid) => {
+            let tiles = &game.players[game.turn_state.player].tiles;
+            let playable = valid.iter().map(|&i| tiles[i]).collect::<Vec<_>>();
+            format!("Play a tile: {}", tile_list(&playable))
+        }
+        TurnPhase::CreateChain(tile, chains) => {
+            format!("Found a chain at {:?}: {}", tile, chain_list(board, chains))
+        }
+        TurnPhase::PickWinningChain(choices, _) if choices.len() == 1 => {
+            format!("Press enter to merge into {}", board.chain_name(choices[0]))
+        }
+        TurnPhase::PickWinningChain(choices, _) => {
+            format!("Pick the surviving chain: {}", chain_list(board, choices))
+        }
+        TurnPhase::DistributeBonuses(_, losers, bonuses) => {
+            let bonuses = bonuses
+                .iter()
+                .enumerate()
+                .filter(|(_, &b)| b > 0)
+                .map(|(p, b)| format!("P{} ${}", p, b))
+                .collect::<Vec<_>>();
+            format!(
+                "Bonuses for {}: {}. Press enter to accept",
+                board.chain_name(losers[0]),
+                bonuses.join(", ")
+            )
+        }
+        TurnPhase::ResolveMerger(winner, losers, player) => format!(
+            "P{}: you hold {} shares of {}. Sell N, trade N for {} two-for-one, or keep",
+            player,
+            game.players[*player].stocks[losers[0]],
+            board.chain_name(losers[0]),
+            board.chain_name(*winner)
+        ),
+        TurnPhase::BuyStock(buyable) => {
+            let offers = (0..MAX_NUM_CHAINS)
+                .filter(|&i| buyable[i] > 0)
+                .map(|i| format!("{} ${}", board.chain_name(i), board.stock_price(i)))
+                .collect::<Vec<_>>();
+            if offers.is_empty() {
+                return "No shares to buy. Press enter to continue".to_owned();
+            }
+            format!(
+                "Buy up to {} shares, or press enter for none: {}",
+                BUY_LIMIT,
+                offers.join(", ")
+            )
+        }
+        TurnPhase::GameOver(scores) => format!("Game over! Final values: {:?}", scores),
+    }
+}
+
+fn parse_command(
+    board: &BoardState,
+    turn_state: &TurnState,
+    tiles: &[Tile],
+    text: &str,
+) -> Result<TurnAction, String> {
+    let text = text.trim();
+    match &turn_state.phase {
+        TurnPhase::PlaceTile(valid) => {
+            let tile = parse_tile(text)?;
+            let idx = tiles
+                .iter()
+                .position(|&t| t == tile)
+                .ok_or_else(|| format!("{:?} isn't in your hand: {}", tile, tile_list(tiles)))?;
+            if !valid.contains(&idx) {
+                let playable = valid.iter().map(|&i| tiles[i]).collect::<Vec<_>>();
+                return Err(format!(
+                    "{:?} can't be played now. Playable tiles: {}",
+                    tile,
+                    tile_list(&playable)
+                ));
+            }
+            Ok(TurnAction::PlaceTile(idx))
+        }
+        TurnPhase::CreateChain(_, chains) => {
+            Ok(TurnAction::CreateChain(find_chain(board, chains, text)?))
+        }
+        TurnPhase::PickWinningChain(choices, _) if text.is_empty() && choices.len() == 1 => {
+            Ok(TurnAction::PickWinningChain(choices[0]))
+        }
+        TurnPhase::PickWinningChain(choices, _) => Ok(TurnAction::PickWinningChain(find_chain(
+            board, choices, text,
+        )?)),
+        TurnPhase::DistributeBonuses(_, _, _) => match text.to_lowercase().as_str() {
+            "" | "ok" | "bonus" => Ok(TurnAction::AcceptBonus),
+            _ => Err("Press enter to accept the bonuses".to_owned()),
+        },
+        TurnPhase::ResolveMerger(_, _, _) => parse_merger(text),
+        TurnPhase::BuyStock(buyable) => parse_buy(board, buyable, text),
+        TurnPhase::GameOver(_) => Err("The game is over".to_owned()),
+    }
+}
+
+// Accepts "7-C" as well as "7C" and "7c".
+fn parse_tile(text: &str) -> Result<Tile, String> {
+    if text.is_empty() {
+        return Err("Enter a tile, like 7-C".to_owned());
+    }
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (col, row) = text.split_at(split);
+    format!("{}-{}", col, row.trim_start_matches('-'))
+        .parse::<Tile>()
+        .map_err(|_| format!("Not a tile: \"{}\". Enter a tile like 7-C", text))
+}
+
+// Finds the chain among the choices with the given name, or a unique prefix
+// of one.
+fn find_chain(board: &BoardState, choices: &[usize], text: &str) -> Result<usize, String> {
+    if text.is_empty() {
+        return Err(format!("Choose a chain: {}", chain_list(board, choices)));
+    }
+    let lower = text.to_lowercase();
+    let name = |c: usize| board.chain_name(c).to_lowercase();
+    if let Some(&c) = choices.iter().find(|&&c| name(c) == lower) {
+        return Ok(c);
+    }
+    let matches = choices
+        .iter()
+        .copied()
+        .filter(|&c| name(c).starts_with(&lower))
+        .collect::<Vec<_>>();
+    match matches[..] {
+        [c] => Ok(c),
+        [] => Err(format!(
+            "No chain matching \"{}\". Choose from: {}",
+            text,
+            chain_list(board, choices)
+        )),
+        _ => Err(format!(
+            "\"{}\" could be any of: {}",
+            text,
+            chain_list(board, &matches)
+        )),
+    }
+}
+
+fn parse_merger(text: &str) -> Result<TurnAction, String> {
+    let (mut sell, mut trade) = (0, 0);
+    let mut words = text.split_whitespace().map(|w| w.to_lowercase());
+    while let Some(word) = words.next() {
+        let target = match word.as_str() {
+            "keep" => continue,
+            "sell" => &mut sell,
+            "trade" => &mut trade,
+            _ => {
+                return Err(format!(
+                    "Expected \"sell N\", \"trade N\" or \"keep\", not \"{}\"",
+                    word
+                ))
+            }
+        };
+        *target = words
+            .next()
+            .and_then(|n| n.parse::<usize>().ok())
+            .ok_or_else(|| format!("Expected a number after \"{}\"", word))?;
+    }
+    if trade % 2 != 0 {
+        return Err("Shares are traded two-for-one, so trade an even number".to_owned());
+    }
+    Ok(TurnAction::ResolveMerger(sell, trade))
+}
+
+// Parses chain names, each optionally followed by a number of shares (which
+// may be written like "x2"). Names and amounts are separated by spaces or
+// commas.
+fn parse_buy(
+    board: &BoardState,
+    buyable: &[usize; MAX_NUM_CHAINS],
+    text: &str,
+) -> Result<TurnAction, String> {
+    let mut buy_order = [0; MAX_NUM_CHAINS];
+    if matches!(text.to_lowercase().as_str(), "" | "none" | "pass") {
+        return Ok(TurnAction::BuyStock(buy_order));
+    }
+    let choices = (0..MAX_NUM_CHAINS)
+        .filter(|&i| buyable[i] > 0)
+        .collect::<Vec<_>>();
+    if choices.is_empty() {
+        return Err("There are no shares to buy".to_owned());
+    }
+    let mut last_chain = None;
+    let words = text.split([',', ' ']).filter(|w| !w.is_empty());
+    for word in words {
+        match word.trim_start_matches(['x', '×']).parse::<usize>() {
+            Ok(amount) => {
+                let chain = last_chain
+                    .take()
+                    .ok_or_else(|| format!("Expected a chain name before \"{}\"", word))?;
+                // Checked here, so that huge amounts can't overflow the sum.
+                if amount > BUY_LIMIT {
+                    return Err(format!("You can buy at most {} shares per turn", BUY_LIMIT));
+                }
+                // The chain's name already counted as one share.
+                buy_order[chain] = buy_order[chain] + amount - 1;
+            }
+            Err(_) => {
+                let chain = find_chain(board, &choices, word)?;
+                buy_order[chain] += 1;
+                last_chain = Some(chain);
+            }
+        }
+    }
+    if buy_order.iter().sum::<usize>() > BUY_LIMIT {
+        return Err(format!("You can buy at most {} shares per turn", BUY_LIMIT));
+    }
+    if let Some(i) = (0..MAX_NUM_CHAINS).find(|&i| buy_order[i] > buyable[i]) {
+        return Err(format!(
+            "Only {} shares of {} are left",
+            buyable[i],
+            board.chain_name(i)
+        ));
+    }
+    Ok(TurnAction::BuyStock(buy_order))
+}
+
+fn tile_list(tiles: &[Tile]) -> String {
+    let names = tiles.iter().map(|t| format!("{:?}", t)).collect::<Vec<_>>();
+    names.join(", ")
+}
+
+fn chain_list(board: &BoardState, chains: &[usize]) -> String {
+    let names = chains
+        .iter()
+        .map(|&c| board.chain_name(c))
+        .collect::<Vec<_>>();
+    names.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::tests::MERGER_STATE;
+
+    // The shared merger position, with longer chain names and 4-C held back.
+    fn game() -> GameState {
+        let names = ["Alice", "Bob", "Charlie", "Dave", "Eve", "Frank", "George"];
+        let mut game = MERGER_STATE.parse::<GameState>().unwrap();
+        game.board.chain_names = names.map(|s| s.to_owned());
+        game.turn_state.phase = TurnPhase::PlaceTile(vec![0, 2]);
+        game
+    }
+
+    #[test]
+    fn parses_tiles() {
+        let game = game();
+        assert_eq!(parse_input(&game, "9-I"), Ok(TurnAction::PlaceTile(2)));
+        assert_eq!(parse_input(&game, " 1a "), Ok(TurnAction::PlaceTile(0)));
+        assert_eq!(
+            parse_input(&game, "2-B"),
+            Err("2-B isn't in your hand: 1-A, 4-C, 9-I".to_owned())
+        );
+        assert_eq!(
+            parse_input(&game, "4c"),
+            Err("4-C can't be played now. Playable tiles: 1-A, 9-I".to_owned())
+        );
+        assert!(parse_input(&game, "13-A").is_err());
+        // Other players can parse commands from their own view.
+        let view = game.player_view(1);
+        assert_eq!(
+            parse_view_input(&view, "2-B"),
+            Err("It's P0's turn".to_owned())
+        );
+        assert_eq!(
+            parse_view_input(&game.player_view(0), "9i"),
+            Ok(TurnAction::PlaceTile(2))
+        );
+    }
+
+    #[test]
+    fn matches_chain_prefixes() {
+        let mut game = game();
+        game.board.chain_names[4] = "Dan".to_owned();
+        game.turn_state.phase = TurnPhase::CreateChain(Tile(0, 0), vec![2, 3, 4, 5]);
+        assert_eq!(parse_input(&game, "dav"), Ok(TurnAction::CreateChain(3)));
+        assert_eq!(parse_input(&game, "DAN"), Ok(TurnAction::CreateChain(4)));
+        assert_eq!(
+            parse_input(&game, "Da"),
+            Err("\"Da\" could be any of: Dave, Dan".to_owned())
+        );
+        assert_eq!(
+            parse_input(&game, "alice"),
+            Err("No chain matching \"alice\". Choose from: Charlie, Dave, Dan, Frank".to_owned())
+        );
+        game.turn_state.phase = TurnPhase::PickWinningChain(vec![1], vec![0, 1]);
+        assert_eq!(parse_input(&game, ""), Ok(TurnAction::PickWinningChain(1)));
+    }
+
+    #[test]
+    fn parses_buy_orders() {
+        let mut game = game();
+        game.turn_state.phase = TurnPhase::BuyStock([24, 23, 0, 0, 0, 0, 0]);
+        assert_eq!(
+            parse_input(&game, "bob 2, alice"),
+            Ok(TurnAction::BuyStock([1, 2, 0, 0, 0, 0, 0]))
+        );
+        assert_eq!(
+            parse_input(&game, "a a x1"),
+            Ok(TurnAction::BuyStock([2, 0, 0, 0, 0, 0, 0]))
+        );
+        assert_eq!(
+            parse_input(&game, ""),
+            Ok(TurnAction::BuyStock([0; MAX_NUM_CHAINS]))
+        );
+        assert_eq!(
+            parse_input(&game, "bob 4"),
+            Err("You can buy at most 3 shares per turn".to_owned())
+        );
+        assert_eq!(
+            parse_input(&game, &format!("bob {}", usize::MAX)),
+            Err("You can buy at most 3 shares per turn".to_owned())
+        );
+        assert_eq!(
+            parse_input(&game, "2 bob"),
+            Err("Expected a chain name before \"2\"".to_owned())
+        );
+        assert!(parse_input(&game, "charlie").is_err());
+    }
+
+    #[test]
+    fn parses_merger_commands() {
+        let mut game = game();
+        game.turn_state.phase = TurnPhase::ResolveMerger(0, vec![1], 1);
+        assert_eq!(
+            parse_input(&game, "sell 3 trade 4"),
+            Ok(TurnAction::ResolveMerger(3, 4))
+        );
+        assert_eq!(
+            parse_input(&game, "Trade 2"),
+            Ok(TurnAction::ResolveMerger(0, 2))
+        );
+        assert_eq!(
+            parse_input(&game, "keep"),
+            Ok(TurnAction::ResolveMerger(0, 0))
+        );
+        assert!(parse_input(&game, "trade 3").is_err());
+        assert_eq!(
+            parse_input(&game, "sell"),
+            Err("Expected a number after \"sell\"".to_owned())
+        );
+        assert!(prompt(&game).starts_with("P1: you hold 2 shares of Bob."));
+    }
+}
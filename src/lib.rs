@@ -1,2 +1,28 @@
 pub mod agent;
+pub mod bitboard;
+pub mod clock;
+pub mod encoding;
+pub mod engine;
+pub mod env;
 pub mod game;
+pub mod input;
+pub mod notation;
+pub mod render;
+pub mod replay;
+#[cfg(feature = "thread_rng")]
+pub mod server;
+pub mod spectator;
+#[cfg(feature = "thread_rng")]
+pub mod sprt;
+pub mod stats;
+#[cfg(feature = "thread_rng")]
+pub mod tournament;
+pub mod zobrist;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+mod python;
+
+#[cfg(feature = "wasm")]
+mod wasm;
@@ -0,0 +1,280 @@
+// Compact, human-readable notation for recording whole games.
+//
+// Each line holds one player's turn: the player, followed by every step taken
+// during that turn, separated by semicolons. For example:
+//
+//   P2 7-C; found Dave; buy Dave×2, Eve×1
+//   P3 8-C; merge Dave; bonus; P3 sell 2; P0 keep; P1 sell 1 trade 4; buy none
+//
+// Steps are written relative to the game state they are played from, so a
+// record can only be read back given the same initial state.
+use crate::game::{GameState, TurnAction, TurnPhase, MAX_NUM_CHAINS};
+
+// Formats a single action, as taken from the given game state.
+pub fn write_action(game: &GameState, action: &TurnAction) -> Result<String, String> {
+    let board = &game.board;
+    let text = match action {
+        TurnAction::PlaceTile(idx) => {
+            let tiles = &game.players[game.turn_state.player].tiles;
+            let tile = tiles
+                .get(*idx)
+                .ok_or_else(|| format!("Invalid tile index: {}", idx))?;
+            format!("{:?}", tile)
+        }
+        TurnAction::CreateChain(idx) => format!("found {}", chain_name(game, *idx)?),
+        TurnAction::PickWinningChain(idx) => format!("merge {}", chain_name(game, *idx)?),
+        TurnAction::AcceptBonus => "bonus".to_owned(),
+        TurnAction::ResolveMerger(sell, trade) => {
+            let player = match &game.turn_state.phase {
+                TurnPhase::ResolveMerger(_, _, player) => *player,
+                phase => return Err(format!("Wrong phase: {:?}", phase)),
+            };
+            let mut parts = vec![format!("P{}", player)];
+            if *sell > 0 {
+                parts.push(format!("sell {}", sell));
+            }
+            if *trade > 0 {
+                parts.push(format!("trade {}", trade));
+            }
+            if parts.len() == 1 {
+                parts.push("keep".to_owned());
+            }
+            parts.join(" ")
+        }
+        TurnAction::BuyStock(buy_order) => {
+            let orders = buy_order
+                .iter()
+                .enumerate()
+                .filter(|(_, &n)| n > 0)
+                .map(|(i, n)| format!("{}×{}", board.chain_name(i), n))
+                .collect::<Vec<String>>();
+            if orders.is_empty() {
+                "buy none".to_owned()
+            } else {
+                format!("buy {}", orders.join(", "))
+            }
+        }
+    };
+    Ok(text)
+}
+
+fn chain_name(game: &GameState, idx: usize) -> Result<&str, String> {
+    if idx >= MAX_NUM_CHAINS {
+        return Err(format!("Invalid chain index: {}", idx));
+    }
+    Ok(game.board.chain_name(idx))
+}
+
+// Parses a single step, relative to the given game state.
+pub fn parse_action(game: &GameState, text: &str) -> Result<TurnAction, String> {
+    let text = text.trim();
+    let (keyword, rest) = text.split_once(' ').unwrap_or((text, ""));
+    let rest = rest.trim();
+    let find_chain = |name: &str| {
+        game.board
+            .find_chain(name)
+            .ok_or_else(|| format!("Unknown chain: {}", name))
+    };
+    match keyword {
+        "found" => Ok(TurnAction::CreateChain(find_chain(rest)?)),
+        "merge" => Ok(TurnAction::PickWinningChain(find_chain(rest)?)),
+        "bonus" if rest.is_empty() => Ok(TurnAction::AcceptBonus),
+        "buy" => {
+            let mut buy_order = [0; MAX_NUM_CHAINS];
+            if rest == "none" {
+                return Ok(TurnAction::BuyStock(buy_order));
+            }
+            for order in rest.split(',') {
+                let (name, amount) = match order.rsplit_once(['×', 'x']) {
+                    Some((name, amount)) if amount.trim().parse::<usize>().is_ok() => {
+                        (name, amount.trim().parse::<usize>().unwrap())
+                    }
+                    _ => (order, 1),
+                };
+                buy_order[find_chain(name)?] += amount;
+            }
+            Ok(TurnAction::BuyStock(buy_order))
+        }
+        _ if keyword.starts_with('P') && !rest.is_empty() => {
+            let player = parse_player(keyword)?;
+            match &game.turn_state.phase {
+                TurnPhase::ResolveMerger(_, _, p) if *p == player => {}
+                TurnPhase::ResolveMerger(_, _, p) => {
+                    return Err(format!("Expected P{} to resolve the merger: {}", p, text));
+                }
+                phase => return Err(format!("Wrong phase: {:?}", phase)),
+            }
+            let (mut sell, mut trade) = (0, 0);
+            let mut words = rest.split_whitespace();
+            while let Some(word) = words.next() {
+                let target = match word {
+                    "keep" => continue,
+                    "sell" => &mut sell,
+                    "trade" => &mut trade,
+                    _ => return Err(format!("Invalid merger step: {}", text)),
+                };
+                *target = words
+                    .next()
+                    .and_then(|n| n.parse::<usize>().ok())
+                    .ok_or_else(|| format!("Invalid merger step: {}", text))?;
+            }
+            Ok(TurnAction::ResolveMerger(sell, trade))
+        }
+        _ if rest.is_empty() => {
+            let tile = keyword.parse()?;
+            game.players[game.turn_state.player]
+                .tiles
+                .iter()
+                .position(|&t| t == tile)
+                .map(TurnAction::PlaceTile)
+                .ok_or_else(|| format!("Tile {} is not in hand", keyword))
+        }
+        _ => Err(format!("Invalid step: {}", text)),
+    }
+}
+
+fn parse_player(text: &str) -> Result<usize, String> {
+    text.strip_prefix('P')
+        .and_then(|n| n.parse::<usize>().ok())
+        .ok_or_else(|| format!("Invalid player: {}", text))
+}
+
+// Writes out a whole game, one line per turn, by replaying the actions from
+// the initial state.
+pub fn write_game(initial: &GameState, actions: &[TurnAction]) -> Result<String, String> {
+    let mut game = initial.clone();
+    let mut lines: Vec<String> = Vec::new();
+    let mut line_player = None;
+    for action in actions {
+        let step = write_action(&game, action)?;
+        if line_player == Some(game.turn_state.player) {
+            let line = lines.last_mut().unwrap();
+            line.push_str("; ");
+            line.push_str(&step);
+        } else {
+            line_player = Some(game.turn_state.player);
+            lines.push(format!("P{} {}", game.turn_state.player, step));
+        }
+        game.take_turn(action.clone())?;
+    }
+    Ok(lines.into_iter().map(|l| l + "\n").collect())
+}
+
+// Reads a game written by write_game back into its list of actions, checking
+// each one against the rules. Blank lines and lines starting with '#' are
+// ignored.
+pub fn parse_game(initial: &GameState, text: &str) -> Result<Vec<TurnAction>, String> {
+    let mut game = initial.clone();
+    let mut actions = Vec::new();
+    for (line_idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let with_line = |e: String| format!("Line {}: {}", line_idx + 1, e);
+        let (player, steps) = line.split_once(' ').unwrap_or((line, ""));
+        let player = parse_player(player).map_err(with_line)?;
+        if player != game.turn_state.player {
+            return Err(with_line(format!(
+                "Expected P{} to play, not P{}",
+                game.turn_state.player, player
+            )));
+        }
+        for step in steps.split(';') {
+            let action = parse_action(&game, step).map_err(with_line)?;
+            game.take_turn(action.clone()).map_err(with_line)?;
+            actions.push(action);
+        }
+    }
+    Ok(actions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::create_agent;
+    use crate::game::tests::MERGER_STATE;
+
+    #[test]
+    fn parses_actions() {
+        let game = MERGER_STATE.parse::<GameState>().unwrap();
+        assert_eq!(parse_action(&game, "9-I"), Ok(TurnAction::PlaceTile(2)));
+        assert!(parse_action(&game, "2-B").is_err());
+        assert_eq!(
+            parse_action(&game, "found m"),
+            Ok(TurnAction::CreateChain(2))
+        );
+        assert_eq!(
+            parse_action(&game, "buy K×2, Lx1, L"),
+            Ok(TurnAction::BuyStock([2, 2, 0, 0, 0, 0, 0]))
+        );
+        assert_eq!(
+            parse_action(&game, "buy none"),
+            Ok(TurnAction::BuyStock([0; MAX_NUM_CHAINS]))
+        );
+        assert_eq!(
+            parse_action(&game, "buy Z").err(),
+            Some("Unknown chain: Z".to_owned())
+        );
+        assert!(parse_action(&game, "P1 sell 2").is_err());
+    }
+
+    #[test]
+    fn writes_merger_turn() {
+        let initial = MERGER_STATE.parse::<GameState>().unwrap();
+        let actions = [
+            TurnAction::PlaceTile(1),
+            TurnAction::PickWinningChain(1),
+            TurnAction::AcceptBonus,
+            TurnAction::ResolveMerger(0, 0),
+            TurnAction::ResolveMerger(0, 0),
+            TurnAction::BuyStock([0, 2, 0, 0, 0, 0, 0]),
+        ];
+        let text = write_game(&initial, &actions).unwrap();
+        assert_eq!(text, "P0 4-C; merge L; bonus; P0 keep; P1 keep; buy L×2\n");
+        let parsed = parse_game(&initial, &text).unwrap();
+        assert_eq!(parsed, actions);
+    }
+
+    #[test]
+    fn rejects_wrong_player() {
+        let initial = MERGER_STATE.parse::<GameState>().unwrap();
+        assert_eq!(
+            parse_game(&initial, "P1 2-B; buy none").err(),
+            Some("Line 1: Expected P0 to play, not P1".to_owned())
+        );
+    }
+
+    #[test]
+    fn rejects_bad_chain_index() {
+        let game = MERGER_STATE.parse::<GameState>().unwrap();
+        for action in [TurnAction::CreateChain(7), TurnAction::PickWinningChain(99)] {
+            assert!(write_action(&game, &action)
+                .unwrap_err()
+                .starts_with("Invalid chain index"));
+        }
+    }
+
+    #[test]
+    fn full_game_roundtrip() {
+        let initial = GameState::new(
+            3,
+            &mut rand::thread_rng(),
+            crate::game::default_chain_names(),
+        )
+        .unwrap();
+        let mut game = initial.clone();
+        let ai = create_agent(1);
+        let mut actions = Vec::new();
+        loop {
+            let action = ai.choose_action(&game);
+            actions.push(action.clone());
+            if game.take_turn(action).unwrap() {
+                break;
+            }
+        }
+        let text = write_game(&initial, &actions).unwrap();
+        let parsed = parse_game(&initial, &text).unwrap();
+        assert_eq!(parsed, actions);
+    }
+}
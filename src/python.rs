@@ -0,0 +1,244 @@
+// Python bindings, built with the "python" feature. See pyproject.toml for
+// building the module with maturin.
+//
+// Actions are exchanged either as ids in the env module's action space, or
+// as JSON-encoded TurnActions.
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::agent;
+use crate::game::{default_chain_names, GameState, TurnAction, TurnPhase};
+use crate::{encoding, env, notation};
+
+fn value_error(e: impl ToString) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+#[pyclass(name = "GameState")]
+#[derive(Clone)]
+struct PyGameState {
+    game: GameState,
+}
+
+#[pymethods]
+impl PyGameState {
+    // Deals a new game, from the given seed if there is one. Raises
+    // ValueError for fewer than 2 or more than 6 players.
+    #[new]
+    #[pyo3(signature = (num_players, seed=None))]
+    fn new(num_players: usize, seed: Option<u64>) -> PyResult<Self> {
+        let seed = seed.unwrap_or_else(rand::random);
+        let game =
+            GameState::from_seed(num_players, seed, default_chain_names()).map_err(value_error)?;
+        Ok(Self { game })
+    }
+    // Restores a game from to_json output. Raises ValueError if the JSON
+    // isn't a consistent game state, e.g. if a player holds 26 shares.
+    #[staticmethod]
+    fn from_json(json: &str) -> PyResult<Self> {
+        let game = serde_json::from_str(json).map_err(value_error)?;
+        Ok(Self { game })
+    }
+    fn to_json(&self) -> PyResult<String> {
+        serde_json::to_string(&self.game).map_err(value_error)
+    }
+    fn player_view_json(&self, player: usize) -> PyResult<String> {
+        if player >= self.game.players.len() {
+            return Err(value_error(format!("Invalid player: {}", player)));
+        }
+        serde_json::to_string(&self.game.player_view(player)).map_err(value_error)
+    }
+    fn copy(&self) -> Self {
+        self.clone()
+    }
+    #[getter]
+    fn num_players(&self) -> usize {
+        self.game.players.len()
+    }
+    #[getter]
+    fn acting_player(&self) -> usize {
+        self.game.acting_player()
+    }
+    #[getter]
+    fn is_over(&self) -> bool {
+        matches!(self.game.turn_state.phase, TurnPhase::GameOver(_))
+    }
+    // Final values of each player, once the game is over.
+    #[getter]
+    fn scores(&self) -> Option<Vec<usize>> {
+        match &self.game.turn_state.phase {
+            TurnPhase::GameOver(scores) => Some(scores.clone()),
+            _ => None,
+        }
+    }
+    fn player_values(&self) -> Vec<usize> {
+        (0..self.game.players.len())
+            .map(|i| self.game.player_value(i))
+            .collect()
+    }
+    fn zobrist_hash(&self) -> u64 {
+        self.game.zobrist_hash()
+    }
+    // Takes an action by id, returning whether the game is over.
+    fn take_turn(&mut self, action_id: usize) -> PyResult<bool> {
+        if !env::action_mask(&self.game)
+            .get(action_id)
+            .copied()
+            .unwrap_or(false)
+        {
+            return Err(value_error(format!("Illegal action: {}", action_id)));
+        }
+        let action = env::action_from_id(action_id).unwrap();
+        self.game.take_turn(action).map_err(value_error)
+    }
+    // Takes a JSON-encoded TurnAction, which may be outside the action space
+    // (e.g. trading an odd number of stocks).
+    fn take_turn_json(&mut self, action: &str) -> PyResult<bool> {
+        let action: TurnAction = serde_json::from_str(action).map_err(value_error)?;
+        self.game.take_turn(action).map_err(value_error)
+    }
+    fn legal_actions(&self) -> Vec<usize> {
+        env::legal_actions(&self.game)
+    }
+    fn action_mask(&self) -> Vec<bool> {
+        env::action_mask(&self.game)
+    }
+    // The env module's flat observation, for the acting player.
+    fn observation(&self) -> Vec<f32> {
+        env::observation(&self.game)
+    }
+    // The encoding module's fixed-size binary encoding.
+    fn encode<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &encoding::encode_game_state(&self.game))
+    }
+    // Describes an action in game record notation, e.g. "buy K×2".
+    fn describe_action(&self, action_id: usize) -> PyResult<String> {
+        let action = env::action_from_id(action_id)
+            .ok_or_else(|| value_error(format!("Invalid action: {}", action_id)))?;
+        notation::write_action(&self.game, &action).map_err(value_error)
+    }
+    fn __str__(&self) -> String {
+        self.game.to_string()
+    }
+}
+
+// An action as Python sees it: its id when it's in the action space, and
+// JSON-encoded otherwise.
+#[derive(Debug, PartialEq, IntoPyObject)]
+enum PyAction {
+    Id(usize),
+    Json(String),
+}
+
+impl PyAction {
+    fn new(action: &TurnAction) -> PyResult<Self> {
+        match env::action_id(action) {
+            Some(id) => Ok(Self::Id(id)),
+            None => serde_json::to_string(action)
+                .map(Self::Json)
+                .map_err(value_error),
+        }
+    }
+}
+
+// One of the built-in agents, by difficulty.
+#[pyclass(name = "Agent", unsendable)]
+struct PyAgent {
+    agent: Box<dyn agent::Agent + Send>,
+}
+
+#[pymethods]
+impl PyAgent {
+    #[new]
+    fn new(difficulty: usize) -> Self {
+        Self {
+            agent: agent::create_agent(difficulty),
+        }
+    }
+    // The id of the chosen action, or its JSON if it's outside the action
+    // space, for take_turn or take_turn_json respectively.
+    fn choose_action(&self, game: &PyGameState) -> PyResult<PyAction> {
+        PyAction::new(&self.agent.choose_action(&game.game))
+    }
+    fn choose_action_json(&self, game: &PyGameState) -> PyResult<String> {
+        serde_json::to_string(&self.agent.choose_action(&game.game)).map_err(value_error)
+    }
+}
+
+#[pyfunction]
+fn action_json(action_id: usize) -> PyResult<String> {
+    let action = env::action_from_id(action_id)
+        .ok_or_else(|| value_error(format!("Invalid action: {}", action_id)))?;
+    serde_json::to_string(&action).map_err(value_error)
+}
+
+#[pymodule]
+fn acquire(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyGameState>()?;
+    m.add_class::<PyAgent>()?;
+    m.add_function(wrap_pyfunction!(action_json, m)?)?;
+    m.add("NUM_ACTIONS", env::NUM_ACTIONS)?;
+    m.add("OBSERVATION_SIZE", env::OBSERVATION_SIZE)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::PlayerView;
+
+    #[test]
+    fn converts_game_json() {
+        assert!(PyGameState::new(1, None).is_err());
+        let game = PyGameState::new(3, Some(7)).unwrap();
+        let json = game.to_json().unwrap();
+        let restored = PyGameState::from_json(&json).unwrap();
+        assert_eq!(restored.to_json().unwrap(), json);
+        assert!(PyGameState::from_json(&json.replacen("\"cash\"", "\"cask\"", 1)).is_err());
+        let view: PlayerView = serde_json::from_str(&game.player_view_json(2).unwrap()).unwrap();
+        assert_eq!(view.player, 2);
+        assert_eq!(view.tiles, game.game.players[2].tiles);
+        assert!(game.player_view_json(3).is_err());
+    }
+
+    #[test]
+    fn takes_turns_by_id_or_json() {
+        let mut game = PyGameState::new(2, Some(1)).unwrap();
+        let mask = game.action_mask();
+        let illegal = mask.iter().position(|&legal| !legal).unwrap();
+        assert!(game.take_turn(illegal).is_err());
+        assert!(game.take_turn(env::NUM_ACTIONS).is_err());
+        assert!(action_json(env::NUM_ACTIONS).is_err());
+        let legal = game.legal_actions();
+        let mut copy = game.copy();
+        assert!(!game.take_turn(legal[0]).unwrap());
+        let json = action_json(legal[0]).unwrap();
+        assert!(!copy.take_turn_json(&json).unwrap());
+        assert_eq!(copy.to_json().unwrap(), game.to_json().unwrap());
+        assert!(copy.take_turn_json("\"Pass\"").is_err());
+    }
+
+    #[test]
+    fn chooses_actions_in_either_form() {
+        let action = TurnAction::PlaceTile(3);
+        let id = env::action_id(&action).unwrap();
+        assert_eq!(PyAction::new(&action).unwrap(), PyAction::Id(id));
+        // Trading an odd number of shares is outside the action space.
+        let action = TurnAction::ResolveMerger(0, 3);
+        assert_eq!(env::action_id(&action), None);
+        let expected = serde_json::to_string(&action).unwrap();
+        assert_eq!(PyAction::new(&action).unwrap(), PyAction::Json(expected));
+        // Play a whole game the way the demo does.
+        let agent = PyAgent::new(1);
+        let mut game = PyGameState::new(3, Some(5)).unwrap();
+        while !game.is_over() {
+            let over = match agent.choose_action(&game).unwrap() {
+                PyAction::Id(id) => game.take_turn(id),
+                PyAction::Json(json) => game.take_turn_json(&json),
+            };
+            assert!(over.is_ok());
+        }
+        assert_eq!(game.scores().unwrap().len(), 3);
+    }
+}
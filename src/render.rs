@@ -0,0 +1,183 @@
+// Draws the board as an image: the 12×9 grid with each chain in its own
+// color, and a table of chain sizes, prices and shares left beside it. Safe
+// chains are outlined on the grid and marked in the table. The output is a
+// self-contained SVG, which can be rasterized to PNG with the "png" feature.
+use crate::game::{BoardState, Tile, GRID_HEIGHT, GRID_WIDTH, MAX_NUM_CHAINS};
+
+const CELL: usize = 40;
+const MARGIN: usize = 20;
+const TABLE_WIDTH: usize = 280;
+const ROW_HEIGHT: usize = 32;
+const FONT: &str = "DejaVu Sans, Helvetica, Arial, sans-serif";
+const CHAIN_COLORS: [&str; MAX_NUM_CHAINS] = [
+    "#d9473a", "#e8b730", "#4f9a4a", "#3aa3b8", "#3d5fbf", "#a04fb0", "#e07b39",
+];
+
+// Renders the board to SVG, outlining the highlighted tiles (like the one
+// just placed).
+pub fn render_svg(board: &BoardState, highlight: &[Tile]) -> String {
+    let grid_width = GRID_WIDTH * CELL;
+    let width = MARGIN * 3 + grid_width + TABLE_WIDTH;
+    let height = MARGIN * 2 + (GRID_HEIGHT * CELL).max(ROW_HEIGHT * (MAX_NUM_CHAINS + 1));
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+         viewBox=\"0 0 {w} {h}\" font-family=\"{font}\">\n\
+         <rect width=\"{w}\" height=\"{h}\" fill=\"#fbf8f1\"/>\n",
+        w = width,
+        h = height,
+        font = FONT,
+    );
+    for row in 0..GRID_HEIGHT {
+        for col in 0..GRID_WIDTH {
+            let tile = Tile(row, col);
+            let (fill, text, stroke) = match board.chain_at(tile) {
+                Some(chain) if board.is_safe(chain) => (CHAIN_COLORS[chain], "#fff", "#222"),
+                Some(chain) => (CHAIN_COLORS[chain], "#fff", CHAIN_COLORS[chain]),
+                None if board.is_occupied(tile) => ("#7a7a7a", "#fff", "#7a7a7a"),
+                None => ("#ece6d8", "#a39a88", "#d8d0bf"),
+            };
+            let (stroke, stroke_width) = if highlight.contains(&tile) {
+                ("#000", 3)
+            } else {
+                (stroke, 1)
+            };
+            let (x, y) = (MARGIN + col * CELL, MARGIN + row * CELL);
+            svg.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{s}\" height=\"{s}\" rx=\"4\" fill=\"{}\" \
+                 stroke=\"{}\" stroke-width=\"{}\"/>\n",
+                x + 1,
+                y + 1,
+                fill,
+                stroke,
+                stroke_width,
+                s = CELL - 2,
+            ));
+            svg.push_str(&text_element(
+                x + CELL / 2,
+                y + CELL / 2 + 4,
+                "middle",
+                text,
+                &format!("{:?}", tile),
+            ));
+        }
+    }
+    render_table(&mut svg, board, MARGIN * 2 + grid_width);
+    svg.push_str("</svg>\n");
+    svg
+}
+
+// Renders the board to PNG, via render_svg.
+#[cfg(feature = "png")]
+pub fn render_png(board: &BoardState, highlight: &[Tile]) -> Result<Vec<u8>, String> {
+    use resvg::{tiny_skia, usvg};
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    let svg = render_svg(board, highlight);
+    let tree = usvg::Tree::from_str(&svg, &options).map_err(|e| e.to_string())?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| format!("Invalid image size: {:?}", size))?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    pixmap.encode_png().map_err(|e| e.to_string())
+}
+
+fn render_table(svg: &mut String, board: &BoardState, left: usize) {
+    let columns = [
+        ("Chain", 28, "start"),
+        ("Size", 150, "end"),
+        ("Price", 205, "end"),
+        ("Left", 250, "end"),
+    ];
+    for (name, x, anchor) in columns {
+        svg.push_str(&text_element(left + x, MARGIN + 20, anchor, "#555", name));
+    }
+    for (chain, color) in CHAIN_COLORS.iter().enumerate() {
+        let y = MARGIN + ROW_HEIGHT * (chain + 1);
+        let size = board.chain_size(chain);
+        let ink = if size > 0 { "#222" } else { "#a39a88" };
+        svg.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"18\" height=\"18\" rx=\"3\" fill=\"{}\"/>\n",
+            left,
+            y + 6,
+            color
+        ));
+        let price = match size {
+            0 => "-".to_owned(),
+            _ => format!("${}", board.stock_price(chain)),
+        };
+        let mut name = escape(board.chain_name(chain));
+        if board.is_safe(chain) {
+            name.push_str(" (safe)");
+        }
+        let cells = [
+            (28, "start", name),
+            (150, "end", size.to_string()),
+            (205, "end", price),
+            (250, "end", board.stocks_left(chain).to_string()),
+        ];
+        for (x, anchor, text) in cells {
+            svg.push_str(&text_element(left + x, y + 20, anchor, ink, &text));
+        }
+    }
+}
+
+fn text_element(x: usize, y: usize, anchor: &str, fill: &str, text: &str) -> String {
+    format!(
+        "<text x=\"{}\" y=\"{}\" text-anchor=\"{}\" font-size=\"12\" fill=\"{}\">{}</text>\n",
+        x, y, anchor, fill, text
+    )
+}
+
+// Chain names are the only text that doesn't come from us.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOARD: &str = "\
+0123456789012
+A*___________
+B____________
+C_KK_LLLLLLLL
+D_____LLL____
+E____________
+F____________
+G____________
+H____________
+I____________
+Stock market: [24, 23, 25, 25, 25, 25, 25]
+Chain sizes: [2, 11, 0, 0, 0, 0, 0]
+";
+
+    #[test]
+    fn draws_grid_and_table() {
+        let mut board = BOARD.parse::<BoardState>().unwrap();
+        board.chain_names[2] = "M&M".to_owned();
+        let svg = render_svg(&board, &["3-C".parse().unwrap()]);
+        assert!(svg.starts_with("<svg xmlns="));
+        assert!(svg.ends_with("</svg>\n"));
+        // One rect per cell, plus the background and a swatch per chain.
+        assert_eq!(svg.matches("<rect").count(), 1 + 12 * 9 + MAX_NUM_CHAINS);
+        assert_eq!(svg.matches("stroke-width=\"3\"").count(), 1);
+        // Both cells' fills, the unhighlighted cell's stroke, and the swatch.
+        assert_eq!(svg.matches(CHAIN_COLORS[0]).count(), 4);
+        assert!(svg.contains(">L (safe)</text>"));
+        assert!(svg.contains(">$700</text>"));
+        assert!(svg.contains(">M&amp;M</text>"));
+        assert!(svg.contains(">12-I</text>"));
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn rasterizes_png() {
+        let board = BOARD.parse::<BoardState>().unwrap();
+        let png = render_png(&board, &[]).unwrap();
+        assert!(png.starts_with(b"\x89PNG"));
+    }
+}
@@ -0,0 +1,228 @@
+// Exports a recorded game as a standalone HTML page, for reviewing it step by
+// step. There's one frame per action, showing the board (drawn by
+// render::render_svg, with the last tile placed outlined), the action in
+// notation, and everyone's cash and shares. The page has buttons to step
+// through the game or play it, and also responds to the arrow keys.
+use serde_json::json;
+
+use crate::game::{GameState, Tile, TurnAction, TurnPhase, MAX_NUM_CHAINS};
+use crate::notation::write_action;
+use crate::render::render_svg;
+
+pub fn replay_html(initial: &GameState, actions: &[TurnAction]) -> Result<String, String> {
+    let mut game = initial.clone();
+    let mut last_tile: Vec<Tile> = Vec::new();
+    // Boards only change with some actions, so each distinct board is only
+    // drawn once, and frames refer to them by index.
+    let mut boards = vec![render_svg(&game.board, &last_tile)];
+    let mut frames = vec![frame(&game, 0, "Start".to_owned(), None)];
+    for (i, action) in actions.iter().enumerate() {
+        let player = game.acting_player();
+        let text = write_action(&game, action).map_err(|e| format!("Move {}: {}", i + 1, e))?;
+        let caption = match action {
+            // These already name the player.
+            TurnAction::ResolveMerger(_, _) => text,
+            _ => format!("P{} {}", player, text),
+        };
+        if let TurnAction::PlaceTile(idx) = action {
+            last_tile = vec![game.players[player].tiles[*idx]];
+        }
+        game.take_turn(action.clone())
+            .map_err(|e| format!("Move {}: {}", i + 1, e))?;
+        let board = render_svg(&game.board, &last_tile);
+        if boards.last() != Some(&board) {
+            boards.push(board);
+        }
+        frames.push(frame(&game, boards.len() - 1, caption, Some(player)));
+    }
+    if let TurnPhase::GameOver(scores) = &game.turn_state.phase {
+        let scores = scores
+            .iter()
+            .enumerate()
+            .map(|(p, s)| format!("P{} ${}", p, s));
+        let last = frames.last_mut().unwrap();
+        last["caption"] = json!(format!(
+            "{} (game over: {})",
+            last["caption"].as_str().unwrap(),
+            scores.collect::<Vec<_>>().join(", ")
+        ));
+    }
+
+    let mut html = HEADER.to_owned();
+    for (i, board) in boards.iter().enumerate() {
+        // The first board is shown until the script runs.
+        let hidden = if i == 0 { "" } else { " hidden" };
+        html.push_str(&format!("<div class=\"board\"{}>{}</div>\n", hidden, board));
+    }
+    // Keep the frames from closing the script element.
+    let frames = serde_json::to_string(&frames).map_err(|e| e.to_string())?;
+    html.push_str(&FOOTER.replace("FRAMES", &frames.replace("</", "<\\/")));
+    Ok(html)
+}
+
+// A frame's caption, board index and player table. The actor's row is
+// highlighted.
+fn frame(
+    game: &GameState,
+    board: usize,
+    caption: String,
+    actor: Option<usize>,
+) -> serde_json::Value {
+    let players = game
+        .players
+        .iter()
+        .enumerate()
+        .map(|(i, p)| {
+            let stocks = (0..MAX_NUM_CHAINS)
+                .filter(|&c| p.stocks[c] > 0)
+                .map(|c| format!("{} {}", game.board.chain_name(c), p.stocks[c]))
+                .collect::<Vec<_>>();
+            json!([
+                format!("P{}", i),
+                p.cash,
+                game.player_value(i),
+                stocks.join(", ")
+            ])
+        })
+        .collect::<Vec<_>>();
+    json!({"board": board, "caption": caption, "actor": actor, "players": players})
+}
+
+const HEADER: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Acquire replay</title>
+<style>
+body { font-family: sans-serif; background: #fbf8f1; margin: 20px; }
+#controls button { font-size: 16px; min-width: 40px; }
+#slider { width: 400px; vertical-align: middle; }
+#caption { font-size: 20px; margin: 10px 0; min-height: 1.5em; }
+table { border-collapse: collapse; }
+td, th { padding: 2px 12px; text-align: right; }
+td:last-child, th:last-child { text-align: left; }
+tr.actor { background: #f1e3b8; font-weight: bold; }
+</style>
+</head>
+<body>
+<div id="controls">
+<button id="first">&#x23EE;</button>
+<button id="prev">&#x25C0;</button>
+<button id="play">&#x25B6;</button>
+<button id="next">&#x25B6;&#x25B6;</button>
+<button id="last">&#x23ED;</button>
+<input id="slider" type="range" min="0" value="0">
+<span id="position"></span>
+</div>
+<div id="caption"></div>
+"#;
+
+const FOOTER: &str = r#"<table>
+<thead><tr><th></th><th>Cash</th><th>Value</th><th>Shares</th></tr></thead>
+<tbody id="players"></tbody>
+</table>
+<script>
+const frames = FRAMES;
+const boards = document.querySelectorAll(".board");
+const slider = document.getElementById("slider");
+const play = document.getElementById("play");
+let current = 0;
+let timer = null;
+slider.max = frames.length - 1;
+
+function show(i) {
+  current = Math.max(0, Math.min(frames.length - 1, i));
+  const frame = frames[current];
+  boards.forEach((board, j) => board.hidden = j !== frame.board);
+  document.getElementById("caption").textContent = frame.caption;
+  document.getElementById("position").textContent = current + " / " + (frames.length - 1);
+  slider.value = current;
+  const rows = frame.players.map((player, p) => {
+    const row = document.createElement("tr");
+    row.className = p === frame.actor ? "actor" : "";
+    player.forEach((value, k) => {
+      const cell = document.createElement("td");
+      cell.textContent = k === 1 || k === 2 ? "$" + value : value;
+      row.appendChild(cell);
+    });
+    return row;
+  });
+  document.getElementById("players").replaceChildren(...rows);
+  if (current === frames.length - 1) stop();
+}
+function stop() {
+  clearInterval(timer);
+  timer = null;
+  play.innerHTML = "&#x25B6;";
+}
+function toggle() {
+  if (timer !== null) return stop();
+  if (current === frames.length - 1) show(0);
+  timer = setInterval(() => show(current + 1), 700);
+  play.innerHTML = "&#x23F8;";
+}
+document.getElementById("first").onclick = () => show(0);
+document.getElementById("prev").onclick = () => show(current - 1);
+document.getElementById("next").onclick = () => show(current + 1);
+document.getElementById("last").onclick = () => show(frames.length - 1);
+play.onclick = toggle;
+slider.oninput = () => show(Number(slider.value));
+document.onkeydown = (e) => {
+  if (e.key === "ArrowLeft") show(current - 1);
+  else if (e.key === "ArrowRight") show(current + 1);
+  else if (e.key === "Home") show(0);
+  else if (e.key === "End") show(frames.length - 1);
+  else if (e.key === " ") toggle();
+  else return;
+  e.preventDefault();
+};
+show(0);
+</script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::create_seeded_agent;
+    use crate::game::default_chain_names;
+
+    #[test]
+    fn exports_whole_game() {
+        let initial = GameState::from_seed(3, 5, default_chain_names()).unwrap();
+        let agent = create_seeded_agent(1, 5);
+        let mut game = initial.clone();
+        let mut actions = Vec::new();
+        loop {
+            let action = agent.choose_action(&game);
+            actions.push(action.clone());
+            if game.take_turn(action).unwrap() {
+                break;
+            }
+        }
+        let html = replay_html(&initial, &actions).unwrap();
+        let num_boards = html.matches("<div class=\"board\"").count();
+        assert_eq!(html.matches("<svg ").count(), num_boards);
+        assert_eq!(
+            html.matches("<div class=\"board\" hidden>").count(),
+            num_boards - 1
+        );
+        // Every tile placed gets a new board, but other actions mostly don't.
+        let num_placed = actions
+            .iter()
+            .filter(|a| matches!(a, TurnAction::PlaceTile(_)))
+            .count();
+        assert!(num_boards > num_placed && num_boards < actions.len());
+        assert_eq!(html.matches("\"caption\":").count(), actions.len() + 1);
+        assert!(html.contains("(game over: P0 $"));
+        assert!(!html.contains("FRAMES"));
+    }
+
+    #[test]
+    fn rejects_invalid_actions() {
+        let initial = GameState::from_seed(2, 5, default_chain_names()).unwrap();
+        let err = replay_html(&initial, &[TurnAction::AcceptBonus]);
+        assert!(err.unwrap_err().starts_with("Move 1: "));
+    }
+}
@@ -0,0 +1,1224 @@
+// Hosts games for remote players over TCP.
+//
+// The protocol is line-delimited JSON: each line holds one ClientMessage or
+// ServerMessage. The lobby is a list of tables, each hosting one game. A
+// connection creates a table or joins one, and takes a seat, and the table's
+// creator or players can fill its empty seats with bots. Once every seat is
+// filled, the game starts, and each seat is sent its own PlayerView whenever
+// the game changes. Actions are only accepted from the acting player, which
+// is not the turn player while a merger is resolved.
+//
+// Spectators can watch a table's public feed, or its omniscient feed if the
+// table has one, delayed so it can't help the players (see spectator.rs).
+//
+// Tables can have time controls (see clock.rs), which the server enforces by
+// playing the table's timeout action for players who run out of time.
+//
+// Players who disconnect from a started game keep their seat, and can rejoin
+// it with the token they were given when they joined. If the table allows it,
+// a bot takes over for a player who's been disconnected for too long, until
+// they rejoin.
+//
+// Finished tables are removed once their players and spectators have left.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::agent::{create_agent, Agent};
+use crate::clock::{Clock, TimeControl, TimeoutAction};
+use crate::game::{
+    default_chain_names, GameState, PlayerView, TurnAction, TurnPhase, MAX_NUM_CHAINS,
+};
+use crate::spectator::{FeedMode, SpectatorFeed, SpectatorUpdate};
+
+pub type GameId = u64;
+pub type ConnId = u64;
+
+// How often the server checks for players who are out of time, or have been
+// disconnected for too long.
+const TICK: Duration = Duration::from_millis(200);
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TableConfig {
+    pub num_players: usize,
+    // Seed for the deal, or a random one. Only servers created with
+    // Server::with_seeds accept this, and it's never shown to other clients.
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub chain_names: Option<[String; MAX_NUM_CHAINS]>,
+    // Seconds a player may be disconnected from a started game before a bot
+    // plays for them, or never if None.
+    #[serde(default)]
+    pub takeover_secs: Option<u64>,
+    // Difficulty of the bots that take over, as understood by create_agent.
+    #[serde(default)]
+    pub takeover_difficulty: usize,
+    // Number of actions the omniscient spectator feed lags behind the game,
+    // or None to only allow the public feed.
+    #[serde(default)]
+    pub spectator_delay: Option<usize>,
+    #[serde(default)]
+    pub time_control: Option<TimeControl>,
+    #[serde(default)]
+    pub on_timeout: TimeoutAction,
+}
+impl TableConfig {
+    pub fn new(num_players: usize) -> Self {
+        Self {
+            num_players,
+            seed: None,
+            chain_names: None,
+            takeover_secs: None,
+            takeover_difficulty: 1,
+            spectator_delay: None,
+            time_control: None,
+            on_timeout: TimeoutAction::Passive,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TableInfo {
+    pub game_id: GameId,
+    pub config: TableConfig,
+    // Name of the player in each seat, or None for an empty seat.
+    pub seats: Vec<Option<String>>,
+    pub started: bool,
+    pub over: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum ClientMessage {
+    // Creates a table and replies with its id. The sender must still join it.
+    Create(TableConfig),
+    ListTables,
+    Join { game_id: GameId, name: String },
+    // Takes back a seat after disconnecting.
+    Rejoin { game_id: GameId, token: String },
+    // Watches a table without a seat, with the public feed or (if full) the
+    // delayed omniscient feed.
+    Spectate { game_id: GameId, full: bool },
+    // Fills every empty seat with a bot of the given difficulty. Only the
+    // table's creator and its players can do this, before the game starts.
+    FillWithBots { game_id: GameId, difficulty: usize },
+    Action { action: TurnAction },
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum ServerMessage {
+    Created {
+        game_id: GameId,
+    },
+    Tables {
+        tables: Vec<TableInfo>,
+    },
+    Joined {
+        game_id: GameId,
+        player: usize,
+        // Secret for rejoining this seat.
+        token: String,
+    },
+    // Sent to every seat when the game starts (or is rejoined), with the
+    // players' names.
+    Started {
+        game_id: GameId,
+        names: Vec<String>,
+    },
+    View {
+        game_id: GameId,
+        view: Box<PlayerView>,
+        // Whether this player needs to act next.
+        to_act: bool,
+        // Time left for each player, in a timed game.
+        clocks_ms: Option<Vec<u64>>,
+    },
+    GameOver {
+        game_id: GameId,
+        scores: Vec<usize>,
+    },
+    Spectator {
+        game_id: GameId,
+        update: SpectatorUpdate,
+    },
+    Error {
+        message: String,
+    },
+}
+
+struct Seat {
+    name: String,
+    // None for bots, and for players who've disconnected.
+    conn: Option<ConnId>,
+    // None for bots.
+    token: Option<String>,
+    disconnected_at: Option<Instant>,
+    // Plays for bots, and for players who've been disconnected for too long.
+    bot: Option<Box<dyn Agent + Send>>,
+}
+impl Seat {
+    fn bot_to_act(&self) -> Option<&dyn Agent> {
+        match (&self.bot, self.conn) {
+            (Some(bot), None) => Some(&**bot),
+            _ => None,
+        }
+    }
+}
+
+struct HostedGame {
+    config: TableConfig,
+    // The connection that created the table.
+    creator: ConnId,
+    // The game's log: its initial state and every action taken since.
+    initial: GameState,
+    log: Vec<TurnAction>,
+    game: GameState,
+    seats: Vec<Option<Seat>>,
+    started: bool,
+    spectators: Vec<(ConnId, SpectatorFeed)>,
+    // Runs once the game has started, in a timed game.
+    clock: Option<Clock>,
+}
+impl HostedGame {
+    fn take_turn(&mut self, action: TurnAction, now: Instant) -> Result<bool, String> {
+        let over = match &mut self.clock {
+            Some(clock) => clock.take_turn(&mut self.game, action.clone(), now)?,
+            None => self.game.take_turn(action.clone())?,
+        };
+        self.log.push(action);
+        Ok(over)
+    }
+    // Plays the timeout action if the acting player is out of time,
+    // returning whether it did.
+    fn time_out(&mut self, now: Instant) -> Result<bool, String> {
+        let Some(clock) = &mut self.clock else {
+            return Ok(false);
+        };
+        let action = clock.check_timeout(&mut self.game, self.config.on_timeout, now)?;
+        self.log.extend(action.clone());
+        Ok(action.is_some())
+    }
+    fn names(&self) -> Vec<String> {
+        self.seats
+            .iter()
+            .map(|s| s.as_ref().map_or(String::new(), |s| s.name.clone()))
+            .collect()
+    }
+    fn info(&self, game_id: GameId) -> TableInfo {
+        TableInfo {
+            game_id,
+            // Anyone who knew the seed could work out every player's tiles.
+            config: TableConfig {
+                seed: None,
+                ..self.config.clone()
+            },
+            seats: self
+                .seats
+                .iter()
+                .map(|s| s.as_ref().map(|s| s.name.clone()))
+                .collect(),
+            started: self.started,
+            over: is_over(&self.game),
+        }
+    }
+    // Starts the game once every seat is filled, returning whether it did.
+    fn try_start(&mut self, now: Instant) -> bool {
+        let starting = !self.started && self.seats.iter().all(Option::is_some);
+        if starting {
+            self.started = true;
+            self.clock = self
+                .config
+                .time_control
+                .map(|control| Clock::new(&self.game, control, now));
+        }
+        starting
+    }
+    // Hands the seat of the acting player to a bot, if they've been gone for
+    // longer than the table allows.
+    fn take_over(&mut self, now: Instant) {
+        let Some(secs) = self.config.takeover_secs else {
+            return;
+        };
+        let difficulty = self.config.takeover_difficulty;
+        let Some(seat) = &mut self.seats[self.game.acting_player()] else {
+            return;
+        };
+        if let (None, Some(since)) = (&seat.bot, seat.disconnected_at) {
+            if now.duration_since(since) >= Duration::from_secs(secs) {
+                seat.bot = Some(create_agent(difficulty));
+            }
+        }
+    }
+}
+
+struct Connection {
+    sender: Sender<ServerMessage>,
+    seat: Option<(GameId, usize)>,
+    watching: Option<GameId>,
+}
+
+#[derive(Default)]
+struct ServerState {
+    next_game_id: GameId,
+    next_conn_id: ConnId,
+    games: HashMap<GameId, HostedGame>,
+    conns: HashMap<ConnId, Connection>,
+    // Whether clients may choose the seed for a table's deal.
+    allow_seeds: bool,
+}
+
+impl ServerState {
+    fn send(&self, conn: ConnId, msg: ServerMessage) {
+        if let Some(c) = self.conns.get(&conn) {
+            // The connection may have just closed, which is fine to ignore.
+            let _ = c.sender.send(msg);
+        }
+    }
+    fn send_view(&self, game_id: GameId, player: usize) {
+        let hosted = &self.games[&game_id];
+        let Some(conn) = hosted.seats[player].as_ref().and_then(|s| s.conn) else {
+            return;
+        };
+        let view = Box::new(hosted.game.player_view(player));
+        let to_act = player == hosted.game.acting_player() && !is_over(&hosted.game);
+        let clocks_ms = hosted
+            .clock
+            .as_ref()
+            .map(|c| c.remaining_ms(Instant::now()));
+        self.send(
+            conn,
+            ServerMessage::View {
+                game_id,
+                view,
+                to_act,
+                clocks_ms,
+            },
+        );
+        if let TurnPhase::GameOver(scores) = &hosted.game.turn_state.phase {
+            let scores = scores.clone();
+            self.send(conn, ServerMessage::GameOver { game_id, scores });
+        }
+    }
+    fn broadcast_views(&self, game_id: GameId) {
+        for player in 0..self.games[&game_id].seats.len() {
+            self.send_view(game_id, player);
+        }
+    }
+    // Sends each spectator whatever their feed can show of the log so far.
+    fn update_spectators(&mut self, game_id: GameId) {
+        let hosted = self.games.get_mut(&game_id).unwrap();
+        let over = is_over(&hosted.game);
+        let mut updates = Vec::new();
+        for (conn, feed) in &mut hosted.spectators {
+            // The log only holds actions that were valid when they were taken.
+            for update in feed.poll(&hosted.log, over).unwrap_or_default() {
+                updates.push((*conn, update));
+            }
+        }
+        for (conn, update) in updates {
+            self.send(conn, ServerMessage::Spectator { game_id, update });
+        }
+    }
+    fn after_turn(&mut self, game_id: GameId) {
+        self.broadcast_views(game_id);
+        self.update_spectators(game_id);
+    }
+    fn broadcast_start(&self, game_id: GameId) {
+        let hosted = &self.games[&game_id];
+        let names = hosted.names();
+        for conn in hosted.seats.iter().flatten().filter_map(|s| s.conn) {
+            let names = names.clone();
+            self.send(conn, ServerMessage::Started { game_id, names });
+        }
+        self.broadcast_views(game_id);
+    }
+    // Tells everyone at a table about a problem that isn't any one
+    // client's fault, and logs it.
+    fn notify_table(&self, game_id: GameId, message: String) {
+        let message = format!("Game {}: {}", game_id, message);
+        eprintln!("{}", message);
+        let hosted = &self.games[&game_id];
+        let players = hosted.seats.iter().flatten().filter_map(|s| s.conn);
+        let spectators = hosted.spectators.iter().map(|(c, _)| *c);
+        for conn in players.chain(spectators) {
+            let message = message.clone();
+            self.send(conn, ServerMessage::Error { message });
+        }
+    }
+    // Plays for players who are out of time, and lets bots act, until it's a
+    // connected player's turn, or a disconnected player's turn who hasn't
+    // been taken over yet. A bot's invalid action is replaced with the
+    // passive timeout action, so the game can go on.
+    fn advance(&mut self, game_id: GameId, now: Instant) {
+        loop {
+            let hosted = self.games.get_mut(&game_id).unwrap();
+            if !hosted.started || is_over(&hosted.game) {
+                return;
+            }
+            match hosted.time_out(now) {
+                Ok(true) => {
+                    self.after_turn(game_id);
+                    continue;
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    self.notify_table(game_id, format!("Timeout action failed: {}", e));
+                    return;
+                }
+            }
+            hosted.take_over(now);
+            let acting = hosted.game.acting_player();
+            let Some(bot) = hosted.seats[acting].as_ref().and_then(Seat::bot_to_act) else {
+                return;
+            };
+            let action = match &hosted.clock {
+                Some(clock) => bot.choose_action_within(&hosted.game, clock.remaining(acting, now)),
+                None => bot.choose_action(&hosted.game),
+            };
+            if let Err(e) = hosted.take_turn(action, now) {
+                let passive = TimeoutAction::Passive.action(&hosted.game);
+                let result = hosted.take_turn(passive, now);
+                self.notify_table(game_id, format!("Bot P{} failed: {}", acting, e));
+                if let Err(e) = result {
+                    self.notify_table(game_id, format!("Passive action failed: {}", e));
+                    return;
+                }
+            }
+            self.after_turn(game_id);
+        }
+    }
+    // Drops finished games once nobody is left to see them.
+    fn remove_finished(&mut self) {
+        self.games.retain(|_, hosted| {
+            let watched = !hosted.spectators.is_empty()
+                || hosted.seats.iter().flatten().any(|s| s.conn.is_some());
+            watched || !is_over(&hosted.game)
+        });
+    }
+    fn create(&mut self, conn: ConnId, config: TableConfig) -> Result<GameId, String> {
+        if config.seed.is_some() && !self.allow_seeds {
+            return Err("This server doesn't accept seeds".to_owned());
+        }
+        let seed = config.seed.unwrap_or_else(rand::random);
+        let chain_names = config
+            .chain_names
+            .clone()
+            .unwrap_or_else(default_chain_names);
+        let game = GameState::from_seed(config.num_players, seed, chain_names)?;
+        let game_id = self.next_game_id;
+        self.next_game_id += 1;
+        self.games.insert(
+            game_id,
+            HostedGame {
+                initial: game.clone(),
+                log: Vec::new(),
+                game,
+                seats: (0..config.num_players).map(|_| None).collect(),
+                started: false,
+                spectators: Vec::new(),
+                clock: None,
+                config,
+                creator: conn,
+            },
+        );
+        Ok(game_id)
+    }
+    fn tables(&self) -> Vec<TableInfo> {
+        let mut tables = self
+            .games
+            .iter()
+            .map(|(&game_id, hosted)| hosted.info(game_id))
+            .collect::<Vec<TableInfo>>();
+        tables.sort_by_key(|t| t.game_id);
+        tables
+    }
+    // Spectators can't take a seat, so they can't play with hidden knowledge.
+    fn check_unseated(&self, conn: ConnId) -> Result<(), String> {
+        let c = &self.conns[&conn];
+        if let Some((other, player)) = c.seat {
+            return Err(format!("Already seated as P{} in game {}", player, other));
+        }
+        if let Some(other) = c.watching {
+            return Err(format!("Already spectating game {}", other));
+        }
+        Ok(())
+    }
+    fn spectate(&mut self, conn: ConnId, game_id: GameId, full: bool) -> Result<(), String> {
+        self.check_unseated(conn)?;
+        let hosted = self
+            .games
+            .get_mut(&game_id)
+            .ok_or_else(|| format!("No such game: {}", game_id))?;
+        let mode = match (full, hosted.config.spectator_delay) {
+            (false, _) => FeedMode::Public,
+            (true, Some(delay)) => FeedMode::Delayed(delay),
+            (true, None) => return Err(format!("Game {} has no omniscient feed", game_id)),
+        };
+        let feed = SpectatorFeed::new(&hosted.initial, mode);
+        hosted.spectators.push((conn, feed));
+        self.conns.get_mut(&conn).unwrap().watching = Some(game_id);
+        self.update_spectators(game_id);
+        Ok(())
+    }
+    fn join(&mut self, conn: ConnId, game_id: GameId, name: String) -> Result<(), String> {
+        self.check_unseated(conn)?;
+        let hosted = self
+            .games
+            .get_mut(&game_id)
+            .ok_or_else(|| format!("No such game: {}", game_id))?;
+        let player = hosted
+            .seats
+            .iter()
+            .position(Option::is_none)
+            .ok_or_else(|| format!("Game {} is full", game_id))?;
+        let token = format!("{:016x}", rand::random::<u64>());
+        hosted.seats[player] = Some(Seat {
+            name,
+            conn: Some(conn),
+            token: Some(token.clone()),
+            disconnected_at: None,
+            bot: None,
+        });
+        let starting = hosted.try_start(Instant::now());
+        self.conns.get_mut(&conn).unwrap().seat = Some((game_id, player));
+        let joined = ServerMessage::Joined {
+            game_id,
+            player,
+            token,
+        };
+        self.send(conn, joined);
+        if starting {
+            self.broadcast_start(game_id);
+            self.advance(game_id, Instant::now());
+        }
+        Ok(())
+    }
+    fn rejoin(&mut self, conn: ConnId, game_id: GameId, token: String) -> Result<(), String> {
+        self.check_unseated(conn)?;
+        let hosted = self
+            .games
+            .get_mut(&game_id)
+            .ok_or_else(|| format!("No such game: {}", game_id))?;
+        let player = hosted
+            .seats
+            .iter()
+            .position(|s| s.as_ref().is_some_and(|s| s.token.as_ref() == Some(&token)))
+            .ok_or("Invalid token")?;
+        let seat = hosted.seats[player].as_mut().unwrap();
+        let old_conn = seat.conn.replace(conn);
+        seat.disconnected_at = None;
+        seat.bot = None;
+        let names = hosted.names();
+        let started = hosted.started;
+        // The seat may still belong to a connection that hasn't noticed it's
+        // gone yet.
+        if let Some(old) = old_conn.and_then(|c| self.conns.get_mut(&c)) {
+            old.seat = None;
+        }
+        self.conns.get_mut(&conn).unwrap().seat = Some((game_id, player));
+        let joined = ServerMessage::Joined {
+            game_id,
+            player,
+            token,
+        };
+        self.send(conn, joined);
+        if started {
+            self.send(conn, ServerMessage::Started { game_id, names });
+            self.send_view(game_id, player);
+        }
+        Ok(())
+    }
+    // Only the table's creator and its players may start the game with bots.
+    fn fill_with_bots(
+        &mut self,
+        conn: ConnId,
+        game_id: GameId,
+        difficulty: usize,
+    ) -> Result<(), String> {
+        let seated = self.conns[&conn].seat.map(|(g, _)| g) == Some(game_id);
+        let hosted = self
+            .games
+            .get_mut(&game_id)
+            .ok_or_else(|| format!("No such game: {}", game_id))?;
+        if hosted.creator != conn && !seated {
+            return Err(format!(
+                "Only the creator or players of game {} can add bots",
+                game_id
+            ));
+        }
+        if hosted.started {
+            return Err(format!("Game {} has already started", game_id));
+        }
+        for (i, seat) in hosted.seats.iter_mut().enumerate() {
+            if seat.is_none() {
+                *seat = Some(Seat {
+                    name: format!("bot{}-{}", difficulty, i),
+                    conn: None,
+                    token: None,
+                    disconnected_at: None,
+                    bot: Some(create_agent(difficulty)),
+                });
+            }
+        }
+        if hosted.try_start(Instant::now()) {
+            self.broadcast_start(game_id);
+            self.advance(game_id, Instant::now());
+        }
+        Ok(())
+    }
+    fn act(&mut self, conn: ConnId, action: TurnAction) -> Result<(), String> {
+        let (game_id, player) = self.conns[&conn].seat.ok_or("Not seated in a game")?;
+        let hosted = self.games.get_mut(&game_id).unwrap();
+        if !hosted.started {
+            return Err("Game has not started yet".to_owned());
+        }
+        let acting = hosted.game.acting_player();
+        if player != acting || is_over(&hosted.game) {
+            return Err(format!("Not your turn: waiting for P{}", acting));
+        }
+        let now = Instant::now();
+        let result = hosted.take_turn(action, now);
+        if result.is_ok() {
+            self.after_turn(game_id);
+        }
+        // This also plays the timeout action if the player was out of time.
+        self.advance(game_id, now);
+        result.map(|_| ())
+    }
+    fn disconnect(&mut self, conn: ConnId, now: Instant) {
+        let Some(connection) = self.conns.remove(&conn) else {
+            return;
+        };
+        if let Some(hosted) = connection.watching.and_then(|id| self.games.get_mut(&id)) {
+            hosted.spectators.retain(|(c, _)| *c != conn);
+        }
+        self.leave_seat(connection.seat, now);
+        self.remove_finished();
+    }
+    fn leave_seat(&mut self, seat: Option<(GameId, usize)>, now: Instant) {
+        let Some((game_id, player)) = seat else {
+            return;
+        };
+        let hosted = self.games.get_mut(&game_id).unwrap();
+        if !hosted.started {
+            // Free the seat for someone else.
+            hosted.seats[player] = None;
+            return;
+        }
+        if let Some(seat) = &mut hosted.seats[player] {
+            seat.conn = None;
+            seat.disconnected_at = Some(now);
+        }
+        self.advance(game_id, now);
+    }
+}
+
+fn is_over(game: &GameState) -> bool {
+    matches!(game.turn_state.phase, TurnPhase::GameOver(_))
+}
+
+// The server's state, shared between connections.
+#[derive(Clone, Default)]
+pub struct Server {
+    state: Arc<Mutex<ServerState>>,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // A server that lets clients choose the deal, for testing and replaying
+    // games. Public servers shouldn't, since whoever picks the seed knows
+    // everyone's tiles.
+    pub fn with_seeds() -> Self {
+        let server = Self::default();
+        server.lock().allow_seeds = true;
+        server
+    }
+    // A panic while handling one message shouldn't take down every other
+    // connection, so a poisoned lock is recovered rather than unwrapped.
+    fn lock(&self) -> MutexGuard<'_, ServerState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    // Registers a connection, returning its id and the messages sent to it.
+    pub fn connect(&self) -> (ConnId, Receiver<ServerMessage>) {
+        let (sender, receiver) = channel();
+        let mut state = self.lock();
+        let conn = state.next_conn_id;
+        state.next_conn_id += 1;
+        let connection = Connection {
+            sender,
+            seat: None,
+            watching: None,
+        };
+        state.conns.insert(conn, connection);
+        (conn, receiver)
+    }
+    // Closes a connection. Its seat is freed if the game hasn't started, and
+    // kept for rejoining otherwise.
+    pub fn disconnect(&self, conn: ConnId) {
+        self.lock().disconnect(conn, Instant::now());
+    }
+    pub fn handle(&self, conn: ConnId, msg: ClientMessage) {
+        let mut state = self.lock();
+        let result = match msg {
+            ClientMessage::Create(config) => state
+                .create(conn, config)
+                .map(|game_id| state.send(conn, ServerMessage::Created { game_id })),
+            ClientMessage::ListTables => {
+                let tables = state.tables();
+                state.send(conn, ServerMessage::Tables { tables });
+                Ok(())
+            }
+            ClientMessage::Join { game_id, name } => state.join(conn, game_id, name),
+            ClientMessage::Rejoin { game_id, token } => state.rejoin(conn, game_id, token),
+            ClientMessage::Spectate { game_id, full } => state.spectate(conn, game_id, full),
+            ClientMessage::FillWithBots {
+                game_id,
+                difficulty,
+            } => state.fill_with_bots(conn, game_id, difficulty),
+            ClientMessage::Action { action } => state.act(conn, action),
+        };
+        if let Err(message) = result {
+            state.send(conn, ServerMessage::Error { message });
+        }
+    }
+    // Plays for players who've run out of time, and lets bots take over for
+    // players who've been disconnected for too long.
+    pub fn tick(&self) {
+        let mut state = self.lock();
+        let now = Instant::now();
+        let game_ids = state.games.keys().copied().collect::<Vec<GameId>>();
+        for game_id in game_ids {
+            state.advance(game_id, now);
+        }
+        state.remove_finished();
+    }
+    // Accepts connections until the listener fails, handling each on its own
+    // threads.
+    pub fn serve(&self, listener: TcpListener) -> std::io::Result<()> {
+        let server = self.clone();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(TICK);
+            server.tick();
+        });
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let server = self.clone();
+            std::thread::spawn(move || server.handle_stream(stream));
+        }
+        Ok(())
+    }
+    fn handle_stream(&self, stream: TcpStream) {
+        let (conn, receiver) = self.connect();
+        let _ = stream.set_nodelay(true);
+        let Ok(mut writer) = stream.try_clone() else {
+            self.disconnect(conn);
+            return;
+        };
+        std::thread::spawn(move || {
+            for msg in receiver {
+                let Ok(line) = serde_json::to_string(&msg) else {
+                    continue;
+                };
+                if writer.write_all(format!("{}\n", line).as_bytes()).is_err() {
+                    break;
+                }
+            }
+        });
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else { break };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str(&line) {
+                Ok(msg) => self.handle(conn, msg),
+                Err(e) => {
+                    let message = format!("Invalid message: {}", e);
+                    self.lock().send(conn, ServerMessage::Error { message });
+                }
+            }
+        }
+        self.disconnect(conn);
+    }
+}
+
+// A minimal blocking client, for tests and scripted players.
+pub struct Client {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Client {
+    pub fn connect(addr: impl ToSocketAddrs) -> std::io::Result<Self> {
+        let writer = TcpStream::connect(addr)?;
+        // Messages are small and latency matters more than throughput.
+        writer.set_nodelay(true)?;
+        let reader = BufReader::new(writer.try_clone()?);
+        Ok(Self { reader, writer })
+    }
+    pub fn send(&mut self, msg: &ClientMessage) -> std::io::Result<()> {
+        let line = serde_json::to_string(msg)?;
+        self.writer.write_all(format!("{}\n", line).as_bytes())
+    }
+    pub fn recv(&mut self) -> std::io::Result<ServerMessage> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(serde_json::from_str(&line)?)
+    }
+}
+
+// Always-legal action for a player view: the first valid choice in each
+// phase, keeping all stock in mergers, and buying nothing.
+pub fn simple_action(view: &PlayerView) -> TurnAction {
+    let stocks = &view.players[view.turn_state.acting_player()].stocks;
+    TimeoutAction::Passive.action_for(&view.turn_state, stocks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expect_error(receiver: &Receiver<ServerMessage>) -> String {
+        match receiver.try_recv() {
+            Ok(ServerMessage::Error { message }) => message,
+            _ => panic!("Expected an error"),
+        }
+    }
+
+    // Drains a connection's messages, returning the latest view if it's this
+    // player's turn to act, and the final scores once the game is over.
+    fn latest(receiver: &Receiver<ServerMessage>) -> (Option<PlayerView>, Option<Vec<usize>>) {
+        let (mut to_act, mut scores) = (None, None);
+        for msg in receiver.try_iter() {
+            match msg {
+                ServerMessage::View {
+                    view, to_act: true, ..
+                } => to_act = Some(*view),
+                ServerMessage::View { .. } => to_act = None,
+                ServerMessage::GameOver { scores: s, .. } => scores = Some(s),
+                ServerMessage::Error { message } => panic!("{}", message),
+                _ => {}
+            }
+        }
+        (to_act, scores)
+    }
+
+    #[test]
+    fn routes_actions_to_acting_player() {
+        let server = Server::with_seeds();
+        let conns = (0..3).map(|_| server.connect()).collect::<Vec<_>>();
+        server.handle(
+            conns[0].0,
+            ClientMessage::Create(TableConfig {
+                seed: Some(5),
+                ..TableConfig::new(3)
+            }),
+        );
+        assert!(matches!(
+            conns[0].1.try_recv(),
+            Ok(ServerMessage::Created { game_id: 0 })
+        ));
+        let action = ClientMessage::Action {
+            action: TurnAction::PlaceTile(0),
+        };
+        server.handle(conns[0].0, action.clone());
+        assert_eq!(expect_error(&conns[0].1), "Not seated in a game");
+        for (i, (conn, _)) in conns.iter().enumerate() {
+            let name = format!("player{}", i);
+            server.handle(*conn, ClientMessage::Join { game_id: 0, name });
+        }
+
+        let mut views = conns.iter().map(|(_, r)| latest(r).0).collect::<Vec<_>>();
+        let join = ClientMessage::Join {
+            game_id: 0,
+            name: "extra".to_owned(),
+        };
+        server.handle(conns[0].0, join);
+        assert!(expect_error(&conns[0].1).starts_with("Already seated"));
+        let first = views.iter().position(Option::is_some).unwrap();
+        assert_eq!(views.iter().flatten().count(), 1);
+        // Only the acting player may move.
+        let other = (first + 1) % 3;
+        server.handle(conns[other].0, action);
+        assert!(expect_error(&conns[other].1).starts_with("Not your turn"));
+
+        let mut saw_out_of_turn_merger = false;
+        let scores = loop {
+            let player = views.iter().position(Option::is_some).unwrap();
+            let view = views[player].take().unwrap();
+            if let TurnPhase::ResolveMerger(_, _, p) = view.turn_state.phase {
+                saw_out_of_turn_merger |= p != view.turn_state.player;
+            }
+            let action = simple_action(&view);
+            server.handle(conns[player].0, ClientMessage::Action { action });
+            let mut scores = None;
+            for (i, (_, receiver)) in conns.iter().enumerate() {
+                let (view, s) = latest(receiver);
+                views[i] = view;
+                scores = scores.or(s);
+            }
+            if let Some(scores) = scores {
+                break scores;
+            }
+        };
+        assert_eq!(scores.len(), 3);
+        assert!(saw_out_of_turn_merger);
+    }
+
+    // Plays the given player's turns until the game is over, checking that
+    // the other seats are played by bots.
+    fn play_until_over(
+        server: &Server,
+        conn: ConnId,
+        receiver: &Receiver<ServerMessage>,
+    ) -> Vec<usize> {
+        loop {
+            let (view, scores) = latest(receiver);
+            if let Some(scores) = scores {
+                return scores;
+            }
+            let view = view.expect("Bots should act until it's our turn");
+            let action = simple_action(&view);
+            server.handle(conn, ClientMessage::Action { action });
+        }
+    }
+
+    #[test]
+    fn fills_seats_with_bots() {
+        let server = Server::new();
+        let (conn, receiver) = server.connect();
+        let mut config = TableConfig::new(4);
+        config.chain_names = Some(["A", "B", "C", "D", "E", "F", "G"].map(String::from));
+        server.handle(conn, ClientMessage::Create(config.clone()));
+        server.handle(conn, ClientMessage::Create(TableConfig::new(7)));
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(ServerMessage::Created { game_id: 0 })
+        ));
+        assert_eq!(expect_error(&receiver), "Invalid number of players: 7");
+        let name = "alice".to_owned();
+        server.handle(conn, ClientMessage::Join { game_id: 0, name });
+        server.handle(conn, ClientMessage::ListTables);
+        let tables = receiver.try_iter().find_map(|msg| match msg {
+            ServerMessage::Tables { tables } => Some(tables),
+            _ => None,
+        });
+        let expected = TableInfo {
+            game_id: 0,
+            config,
+            seats: vec![Some("alice".to_owned()), None, None, None],
+            started: false,
+            over: false,
+        };
+        assert_eq!(tables, Some(vec![expected]));
+
+        let fill = ClientMessage::FillWithBots {
+            game_id: 0,
+            difficulty: 1,
+        };
+        // Someone else in the lobby can't start the game.
+        let (other, other_receiver) = server.connect();
+        server.handle(other, fill.clone());
+        assert_eq!(
+            expect_error(&other_receiver),
+            "Only the creator or players of game 0 can add bots"
+        );
+        server.handle(conn, fill.clone());
+        let scores = play_until_over(&server, conn, &receiver);
+        assert_eq!(scores.len(), 4);
+        server.handle(conn, fill);
+        assert_eq!(expect_error(&receiver), "Game 0 has already started");
+    }
+
+    #[test]
+    fn keeps_seeds_secret() {
+        let seeded = TableConfig {
+            seed: Some(9),
+            ..TableConfig::new(2)
+        };
+        let server = Server::new();
+        let (conn, receiver) = server.connect();
+        server.handle(conn, ClientMessage::Create(seeded.clone()));
+        assert_eq!(expect_error(&receiver), "This server doesn't accept seeds");
+
+        let server = Server::with_seeds();
+        let (conn, receiver) = server.connect();
+        server.handle(conn, ClientMessage::Create(seeded));
+        server.handle(conn, ClientMessage::ListTables);
+        let tables = receiver.try_iter().find_map(|msg| match msg {
+            ServerMessage::Tables { tables } => Some(tables),
+            _ => None,
+        });
+        assert_eq!(tables.unwrap()[0].config, TableConfig::new(2));
+    }
+
+    #[test]
+    fn rejoins_and_takes_over() {
+        let server = Server::with_seeds();
+        let conns = (0..2).map(|_| server.connect()).collect::<Vec<_>>();
+        let config = TableConfig {
+            seed: Some(2),
+            takeover_secs: Some(60),
+            ..TableConfig::new(2)
+        };
+        server.handle(conns[0].0, ClientMessage::Create(config));
+        let mut tokens = Vec::new();
+        for (i, (conn, receiver)) in conns.iter().enumerate() {
+            let name = format!("player{}", i);
+            server.handle(*conn, ClientMessage::Join { game_id: 0, name });
+            tokens.extend(receiver.try_iter().find_map(|msg| match msg {
+                ServerMessage::Joined { token, .. } => Some(token),
+                _ => None,
+            }));
+        }
+        // Leave as the acting player, so the game waits for us.
+        let acting = conns
+            .iter()
+            .position(|(_, receiver)| latest(receiver).0.is_some())
+            .unwrap();
+        server.disconnect(conns[acting].0);
+        server.tick();
+        let other = &conns[1 - acting];
+        assert!(latest(&other.1).0.is_none());
+
+        let (conn, receiver) = server.connect();
+        let rejoin = |token: &str| ClientMessage::Rejoin {
+            game_id: 0,
+            token: token.to_owned(),
+        };
+        server.handle(conn, rejoin("bogus"));
+        assert_eq!(expect_error(&receiver), "Invalid token");
+        server.handle(conn, rejoin(&tokens[acting]));
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(ServerMessage::Joined { player, .. }) if player == acting
+        ));
+        assert!(latest(&receiver).0.is_some());
+
+        // Once we're gone for longer than the table allows, a bot plays for
+        // us, and the other player can finish the game.
+        let now = Instant::now();
+        let mut state = server.lock();
+        state.disconnect(conn, now);
+        state.advance(0, now + Duration::from_secs(60));
+        drop(state);
+        let scores = play_until_over(&server, other.0, &other.1);
+        assert_eq!(scores.len(), 2);
+    }
+
+    #[test]
+    fn feeds_spectators() {
+        let server = Server::new();
+        let (conn, receiver) = server.connect();
+        server.handle(conn, ClientMessage::Create(TableConfig::new(3)));
+        let config = TableConfig {
+            spectator_delay: Some(10),
+            ..TableConfig::new(3)
+        };
+        server.handle(conn, ClientMessage::Create(config));
+        let spectate = |game_id, full| ClientMessage::Spectate { game_id, full };
+        server.handle(conn, spectate(0, true));
+        let spectators = (0..2).map(|_| server.connect()).collect::<Vec<_>>();
+        server.handle(spectators[0].0, spectate(1, false));
+        server.handle(spectators[1].0, spectate(1, true));
+        let fill = ClientMessage::FillWithBots {
+            game_id: 1,
+            difficulty: 1,
+        };
+        server.handle(conn, fill);
+        let errors = receiver
+            .try_iter()
+            .filter_map(|msg| match msg {
+                ServerMessage::Error { message } => Some(message),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(errors, ["Game 0 has no omniscient feed"]);
+
+        let num_actions = server.lock().games[&1].log.len();
+        for (_, receiver) in &spectators {
+            let updates = receiver
+                .try_iter()
+                .filter_map(|msg| match msg {
+                    ServerMessage::Spectator { update, .. } => Some(update),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(updates.len(), num_actions + 1);
+            assert_eq!(updates.last().unwrap().move_number, num_actions);
+        }
+        let name = "late".to_owned();
+        server.handle(spectators[0].0, ClientMessage::Join { game_id: 1, name });
+        assert_eq!(expect_error(&spectators[0].1), "Already spectating game 1");
+    }
+
+    #[test]
+    fn times_out_players() {
+        let server = Server::with_seeds();
+        let conns = (0..2).map(|_| server.connect()).collect::<Vec<_>>();
+        let config = TableConfig {
+            seed: Some(3),
+            time_control: Some(TimeControl {
+                initial_ms: 60_000,
+                increment_ms: 1000,
+            }),
+            ..TableConfig::new(2)
+        };
+        server.handle(conns[0].0, ClientMessage::Create(config));
+        for (i, (conn, _)) in conns.iter().enumerate() {
+            let name = format!("player{}", i);
+            server.handle(*conn, ClientMessage::Join { game_id: 0, name });
+        }
+        let clocks = conns[0].1.try_iter().find_map(|msg| match msg {
+            ServerMessage::View { clocks_ms, .. } => clocks_ms,
+            _ => None,
+        });
+        assert_eq!(clocks.map(|c| c.len()), Some(2));
+
+        let mut state = server.lock();
+        let acting = state.games[&0].game.acting_player();
+        let expected = TimeoutAction::Passive.action(&state.games[&0].game);
+        state.advance(0, Instant::now() + Duration::from_secs(61));
+        let hosted = &state.games[&0];
+        assert_eq!(hosted.log, [expected]);
+        assert_eq!(hosted.clock.as_ref().unwrap().timeouts()[acting], 1);
+    }
+
+    // Only plays invalid actions, except when accepting bonuses.
+    struct BadAgent;
+    impl Agent for BadAgent {
+        fn choose_action(&self, _game: &GameState) -> TurnAction {
+            TurnAction::AcceptBonus
+        }
+    }
+
+    #[test]
+    fn recovers_from_bad_bots() {
+        let server = Server::new();
+        let (conn, receiver) = server.connect();
+        server.handle(conn, ClientMessage::Create(TableConfig::new(2)));
+        let name = "alice".to_owned();
+        server.handle(conn, ClientMessage::Join { game_id: 0, name });
+        let mut state = server.lock();
+        let hosted = state.games.get_mut(&0).unwrap();
+        hosted.seats[1] = Some(Seat {
+            name: "bad".to_owned(),
+            conn: None,
+            token: None,
+            disconnected_at: None,
+            bot: Some(Box::new(BadAgent)),
+        });
+        assert!(hosted.try_start(Instant::now()));
+        state.broadcast_start(0);
+        state.advance(0, Instant::now());
+        drop(state);
+
+        // The bot's turns are played passively, and we're told about it.
+        let mut num_errors = 0;
+        let scores = loop {
+            let (mut view, mut scores) = (None, None);
+            for msg in receiver.try_iter() {
+                match msg {
+                    ServerMessage::View {
+                        view: v, to_act, ..
+                    } => view = to_act.then_some(v),
+                    ServerMessage::GameOver { scores: s, .. } => scores = Some(s),
+                    ServerMessage::Error { message } => {
+                        assert!(message.starts_with("Game 0: Bot P1 failed: "));
+                        num_errors += 1;
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(scores) = scores {
+                break scores;
+            }
+            let action = simple_action(&view.expect("The bot should have been skipped"));
+            server.handle(conn, ClientMessage::Action { action });
+        };
+        assert_eq!(scores.len(), 2);
+        assert!(num_errors > 0);
+        // The finished table goes once its player leaves.
+        assert_eq!(server.lock().games.len(), 1);
+        server.disconnect(conn);
+        assert!(server.lock().games.is_empty());
+    }
+
+    #[test]
+    fn recovers_from_poisoned_lock() {
+        let server = Server::new();
+        let handle = server.clone();
+        let _ = std::thread::spawn(move || {
+            let _state = handle.lock();
+            panic!("Poisoning the lock");
+        })
+        .join();
+        assert!(server.state.is_poisoned());
+        let (conn, receiver) = server.connect();
+        server.handle(conn, ClientMessage::ListTables);
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(ServerMessage::Tables { .. })
+        ));
+    }
+
+    #[test]
+    fn plays_over_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = Server::new();
+        let handle = server.clone();
+        std::thread::spawn(move || handle.serve(listener));
+
+        let mut clients = (0..2)
+            .map(|_| Client::connect(addr).unwrap())
+            .collect::<Vec<_>>();
+        clients[0]
+            .send(&ClientMessage::Create(TableConfig::new(2)))
+            .unwrap();
+        let game_id = match clients[0].recv().unwrap() {
+            ServerMessage::Created { game_id } => game_id,
+            _ => panic!("Expected Created"),
+        };
+        for (i, client) in clients.iter_mut().enumerate() {
+            let name = format!("p{}", i);
+            client.send(&ClientMessage::Join { game_id, name }).unwrap();
+        }
+        // Play each client in its own thread, acting whenever it's asked to.
+        let players = clients.into_iter().map(|mut client| {
+            std::thread::spawn(move || loop {
+                match client.recv().unwrap() {
+                    ServerMessage::View {
+                        view, to_act: true, ..
+                    } => {
+                        let action = simple_action(&view);
+                        client.send(&ClientMessage::Action { action }).unwrap();
+                    }
+                    ServerMessage::GameOver { scores, .. } => return scores,
+                    ServerMessage::Error { message } => panic!("{}", message),
+                    _ => {}
+                }
+            })
+        });
+        let players = players.collect::<Vec<_>>();
+        let results = players
+            .into_iter()
+            .map(|p| p.join().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(results[0], results[1]);
+
+        let mut client = Client::connect(addr).unwrap();
+        client.writer.write_all(b"not json\n").unwrap();
+        assert!(matches!(
+            client.recv().unwrap(),
+            ServerMessage::Error { .. }
+        ));
+    }
+}
@@ -0,0 +1,153 @@
+// Spectator feeds, derived from a game's log: its initial state and the
+// actions taken since. Each feed replays the log on its own copy of the game,
+// so watching can never affect play.
+use serde::{Deserialize, Serialize};
+
+use crate::game::{GameState, PublicView, TurnAction};
+use crate::notation;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum FeedMode {
+    // Public information only, as soon as it happens.
+    Public,
+    // Everything, including every player's tiles, but only once this many
+    // more actions have been taken, or the game is over.
+    Delayed(usize),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub enum SpectatorView {
+    Public(Box<PublicView>),
+    Full(Box<GameState>),
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SpectatorUpdate {
+    // Number of actions taken to reach this view.
+    pub move_number: usize,
+    // The player who took the last action, and the action in record notation.
+    pub last_action: Option<(usize, String)>,
+    pub view: SpectatorView,
+}
+
+pub struct SpectatorFeed {
+    mode: FeedMode,
+    // The game after `next - 1` actions, or the initial state.
+    game: GameState,
+    // Number of actions in the next update.
+    next: usize,
+    last_action: Option<(usize, String)>,
+}
+
+impl SpectatorFeed {
+    pub fn new(initial: &GameState, mode: FeedMode) -> Self {
+        Self {
+            mode,
+            game: initial.clone(),
+            next: 0,
+            last_action: None,
+        }
+    }
+    pub fn mode(&self) -> FeedMode {
+        self.mode
+    }
+    // Replays the log as far as the feed's mode allows, returning an update
+    // for each position not yet shown (starting with the initial state).
+    pub fn poll(
+        &mut self,
+        log: &[TurnAction],
+        finished: bool,
+    ) -> Result<Vec<SpectatorUpdate>, String> {
+        let visible = match self.mode {
+            FeedMode::Public => Some(log.len()),
+            FeedMode::Delayed(_) if finished => Some(log.len()),
+            FeedMode::Delayed(delay) => log.len().checked_sub(delay),
+        };
+        let mut updates = Vec::new();
+        let Some(visible) = visible else {
+            return Ok(updates);
+        };
+        while self.next <= visible {
+            if self.next > 0 {
+                let action = &log[self.next - 1];
+                let player = self.game.acting_player();
+                let text = notation::write_action(&self.game, action)?;
+                self.game.take_turn(action.clone())?;
+                self.last_action = Some((player, text));
+            }
+            updates.push(self.update());
+            self.next += 1;
+        }
+        Ok(updates)
+    }
+    fn update(&self) -> SpectatorUpdate {
+        let view = match self.mode {
+            FeedMode::Public => SpectatorView::Public(Box::new(self.game.public_view())),
+            FeedMode::Delayed(_) => SpectatorView::Full(Box::new(self.game.clone())),
+        };
+        SpectatorUpdate {
+            move_number: self.next,
+            last_action: self.last_action.clone(),
+            view,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::create_seeded_agent;
+    use crate::game::default_chain_names;
+
+    #[test]
+    fn follows_game_log() {
+        let initial = GameState::from_seed(3, 4, default_chain_names()).unwrap();
+        let mut game = initial.clone();
+        let agent = create_seeded_agent(1, 4);
+        let mut public = SpectatorFeed::new(&initial, FeedMode::Public);
+        let mut delayed = SpectatorFeed::new(&initial, FeedMode::Delayed(5));
+        let mut log = Vec::new();
+        let first = public.poll(&log, false).unwrap();
+        assert_eq!(first.len(), 1);
+        assert!(first[0].last_action.is_none());
+        let mut num_delayed = 0;
+        loop {
+            let action = agent.choose_action(&game);
+            let player = game.acting_player();
+            let over = game.take_turn(action.clone()).unwrap();
+            log.push(action);
+            let updates = public.poll(&log, over).unwrap();
+            assert_eq!(updates.len(), 1);
+            assert_eq!(updates[0].move_number, log.len());
+            assert_eq!(updates[0].last_action.as_ref().unwrap().0, player);
+            let SpectatorView::Public(view) = &updates[0].view else {
+                panic!("Expected a public view");
+            };
+            assert_eq!(view.players[player].cash, game.players[player].cash);
+            if over {
+                break;
+            }
+            for update in delayed.poll(&log, over).unwrap() {
+                assert_eq!(update.move_number + 5, log.len());
+                num_delayed += 1;
+            }
+        }
+        // The rest of the delayed feed is released once the game is over.
+        let rest = delayed.poll(&log, true).unwrap();
+        assert_eq!(num_delayed + rest.len(), log.len() + 1);
+        let SpectatorView::Full(last) = &rest.last().unwrap().view else {
+            panic!("Expected a full view");
+        };
+        assert_eq!(last.to_string(), game.to_string());
+    }
+
+    #[test]
+    fn hides_tiles() {
+        let game = GameState::from_seed(2, 9, default_chain_names()).unwrap();
+        let mut feed = SpectatorFeed::new(&game, FeedMode::Public);
+        let update = feed.poll(&[], false).unwrap().pop().unwrap();
+        let json = serde_json::to_string(&update.view).unwrap();
+        assert!(!json.contains("tiles\":["), "{}", json);
+        assert!(json.contains("\"PlaceTile\":[]"), "{}", json);
+    }
+}
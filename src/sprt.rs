@@ -0,0 +1,200 @@
+// Sequential probability ratio test for A/B testing a candidate agent
+// against a baseline.
+//
+// Games are played in pairs from the same deal, with the agents swapping
+// seats for the second game, and each pair is scored for the candidate as the
+// mean of its two results (win = 1, tie = 0.5, loss = 0). The log-likelihood
+// ratio of H1 (candidate is elo1 stronger) against H0 (elo0 stronger) uses
+// the normal approximation to the distribution of pair scores (GSPRT).
+use crate::tournament::{AgentFactory, Seating, Tournament};
+
+// Floor on the variance of pair scores. When every pair has the same score
+// (e.g. the candidate wins them all) the sample variance is zero, and the
+// LLR would be undefined, so the test would never stop.
+const MIN_VARIANCE: f64 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    // Probability of accepting H1 when H0 is true.
+    pub alpha: f64,
+    // Probability of accepting H0 when H1 is true.
+    pub beta: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SprtResult {
+    AcceptH0,
+    AcceptH1,
+    Continue,
+}
+
+// Expected score for a player rated `elo` points above their opponent.
+fn elo_to_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+impl Sprt {
+    pub fn new(elo0: f64, elo1: f64, alpha: f64, beta: f64) -> Self {
+        Self {
+            elo0,
+            elo1,
+            alpha,
+            beta,
+        }
+    }
+    // Lower and upper LLR bounds, for accepting H0 and H1 respectively.
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+    pub fn llr(&self, pair_scores: &[f64]) -> f64 {
+        let n = pair_scores.len() as f64;
+        if n < 2.0 {
+            return 0.0;
+        }
+        let mean = pair_scores.iter().sum::<f64>() / n;
+        let var = pair_scores.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n;
+        let var = var.max(MIN_VARIANCE);
+        let (s0, s1) = (elo_to_score(self.elo0), elo_to_score(self.elo1));
+        n * (s1 - s0) * (2.0 * mean - s0 - s1) / (2.0 * var)
+    }
+    pub fn status(&self, llr: f64) -> SprtResult {
+        let (lower, upper) = self.bounds();
+        if llr >= upper {
+            SprtResult::AcceptH1
+        } else if llr <= lower {
+            SprtResult::AcceptH0
+        } else {
+            SprtResult::Continue
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SprtProgress {
+    pub num_pairs: usize,
+    // Mean pair score for the candidate.
+    pub score: f64,
+    pub llr: f64,
+    pub result: SprtResult,
+}
+
+pub struct SprtMatch {
+    // Plays the pairs, with the candidate as agent 0 and the baseline as
+    // agent 1. Each pair is a deal of its duplicate seating, so pair i is
+    // dealt (and its agents seeded) from seed + i.
+    pub tournament: Tournament,
+    pub sprt: Sprt,
+    // Give up (with SprtResult::Continue) after this many pairs.
+    pub max_pairs: usize,
+    // Pairs played between LLR checks.
+    pub batch_size: usize,
+}
+
+// Scores a finished two-player game for player `i`.
+fn game_score(scores: &[usize], i: usize) -> f64 {
+    let other = scores[1 - i];
+    match scores[i].cmp(&other) {
+        std::cmp::Ordering::Greater => 1.0,
+        std::cmp::Ordering::Equal => 0.5,
+        std::cmp::Ordering::Less => 0.0,
+    }
+}
+
+impl SprtMatch {
+    pub fn new(candidate: AgentFactory, baseline: AgentFactory, sprt: Sprt) -> Self {
+        let mut tournament = Tournament::new(vec![candidate, baseline], 0);
+        tournament.seating = Seating::Duplicate;
+        Self {
+            tournament,
+            sprt,
+            max_pairs: 100_000,
+            batch_size: 100,
+        }
+    }
+    // Plays pairs until the test concludes or max_pairs is reached, calling
+    // `on_progress` after each batch. Returns the final progress.
+    pub fn run(&self, mut on_progress: impl FnMut(&SprtProgress)) -> Result<SprtProgress, String> {
+        let mut pair_scores = Vec::new();
+        loop {
+            let start = pair_scores.len();
+            let end = (start + self.batch_size.max(1)).min(self.max_pairs);
+            pair_scores.extend(self.play_batch(start..end)?);
+            let llr = self.sprt.llr(&pair_scores);
+            let progress = SprtProgress {
+                num_pairs: pair_scores.len(),
+                score: pair_scores.iter().sum::<f64>() / pair_scores.len().max(1) as f64,
+                llr,
+                result: self.sprt.status(llr),
+            };
+            on_progress(&progress);
+            if progress.result != SprtResult::Continue || end >= self.max_pairs {
+                return Ok(progress);
+            }
+        }
+    }
+    fn play_batch(&self, pairs: std::ops::Range<usize>) -> Result<Vec<f64>, String> {
+        let games = self.tournament.run_games(pairs.start * 2..pairs.end * 2)?;
+        Ok(games
+            .chunks(2)
+            .map(|pair| {
+                let scores = pair.iter().map(|r| game_score(&r.agent_scores(), 0));
+                scores.sum::<f64>() / 2.0
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tournament::builtin_agent;
+
+    #[test]
+    fn computes_llr() {
+        let sprt = Sprt::new(0.0, 10.0, 0.05, 0.05);
+        let (lower, upper) = sprt.bounds();
+        assert!((upper - 2.944).abs() < 1e-3);
+        assert_eq!(lower, -upper);
+        // Even results favor neither hypothesis much, but lean towards H0.
+        let even = [0.0, 1.0, 0.5, 0.5].repeat(10);
+        assert!(sprt.llr(&even) < 0.0 && sprt.llr(&even) > lower);
+        // Strong results accept H1, and weak results accept H0.
+        let strong = [1.0, 1.0, 0.5, 0.0].repeat(200);
+        assert_eq!(sprt.status(sprt.llr(&strong)), SprtResult::AcceptH1);
+        let weak = [0.0, 0.0, 0.5, 1.0].repeat(200);
+        assert_eq!(sprt.status(sprt.llr(&weak)), SprtResult::AcceptH0);
+        // Identical results still end the test.
+        assert_eq!(sprt.status(sprt.llr(&[1.0; 10])), SprtResult::AcceptH1);
+        assert_eq!(sprt.status(sprt.llr(&[0.0; 10])), SprtResult::AcceptH0);
+        assert_eq!(sprt.status(sprt.llr(&[1.0; 2])), SprtResult::Continue);
+    }
+
+    #[test]
+    fn scores_games() {
+        assert_eq!(game_score(&[100, 50], 0), 1.0);
+        assert_eq!(game_score(&[100, 50], 1), 0.0);
+        assert_eq!(game_score(&[70, 70], 1), 0.5);
+    }
+
+    #[test]
+    fn basic_beats_random() {
+        let sprt = Sprt::new(0.0, 20.0, 0.05, 0.05);
+        let mut sprt_match = SprtMatch::new(builtin_agent(1), builtin_agent(0), sprt);
+        sprt_match.batch_size = 20;
+        sprt_match.max_pairs = 2000;
+        let mut num_updates = 0;
+        let progress = sprt_match.run(|_| num_updates += 1).unwrap();
+        assert_eq!(progress.result, SprtResult::AcceptH1);
+        assert!(progress.score > 0.5);
+        assert_eq!(num_updates, progress.num_pairs / 20);
+        // The same seed plays the same games.
+        let rerun = sprt_match.run(|_| {}).unwrap();
+        assert_eq!(rerun.num_pairs, progress.num_pairs);
+        assert_eq!(rerun.llr, progress.llr);
+    }
+}
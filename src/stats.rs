@@ -0,0 +1,328 @@
+// Summary statistics for self-play results, mirroring
+// examples/analyze_results.py without needing Python.
+//
+// Scores are given as one row per game, with one column per agent.
+use std::fmt::Write;
+
+const ELO_INITIAL: f64 = 1500.0;
+const ELO_K: f64 = 20.0;
+const ELO_K_DECAY: f64 = 0.99;
+const ELO_K_MIN: f64 = 1.0;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub count: usize,
+    pub mean: f64,
+    // Sample standard deviation.
+    pub std: f64,
+    pub min: f64,
+    pub p25: f64,
+    pub median: f64,
+    pub p75: f64,
+    pub max: f64,
+}
+
+pub fn summarize(values: &[f64]) -> Summary {
+    let count = values.len();
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable_by(f64::total_cmp);
+    let mean = sorted.iter().sum::<f64>() / count as f64;
+    let var = sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (count as f64 - 1.0);
+    Summary {
+        count,
+        mean,
+        std: var.sqrt(),
+        min: percentile(&sorted, 0.0),
+        p25: percentile(&sorted, 25.0),
+        median: percentile(&sorted, 50.0),
+        p75: percentile(&sorted, 75.0),
+        max: percentile(&sorted, 100.0),
+    }
+}
+
+// Linearly interpolated percentile of already-sorted values.
+fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    let pos = pct / 100.0 * (sorted.len() - 1) as f64;
+    let lo = pos.floor() as usize;
+    let hi = pos.ceil() as usize;
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f64)
+}
+
+// Agent indices ordered from lowest to highest score. Ties keep agent order.
+fn argsort(scores: &[usize]) -> Vec<usize> {
+    let mut order = (0..scores.len()).collect::<Vec<usize>>();
+    order.sort_by_key(|&i| scores[i]);
+    order
+}
+
+// Each agent's finishing position in each game, where 1 is the winner.
+pub fn ranks(scores: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    scores
+        .iter()
+        .map(|row| {
+            let mut ranks = vec![0; row.len()];
+            for (pos, agent) in argsort(row).into_iter().enumerate() {
+                ranks[agent] = row.len() - pos;
+            }
+            ranks
+        })
+        .collect()
+}
+
+// counts[agent][rank - 1] is how often the agent finished in that position.
+pub fn rank_counts(ranks: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let num_agents = ranks.first().map_or(0, |r| r.len());
+    let mut counts = vec![vec![0; num_agents]; num_agents];
+    for row in ranks {
+        for (agent, &rank) in row.iter().enumerate() {
+            counts[agent][rank - 1] += 1;
+        }
+    }
+    counts
+}
+
+fn elo_change(rating_loser: f64, rating_winner: f64, k: f64) -> f64 {
+    let gap = rating_winner - rating_loser;
+    k / (1.0 + 10f64.powf(gap / 400.0))
+}
+
+// Rating changes for one game, where `ranking` lists agents from last place
+// to first. Each adjacent pair is scored as a head-to-head result.
+pub fn update_elos(current: &[f64], ranking: &[usize], k: f64) -> Vec<f64> {
+    let mut change = vec![0.0; current.len()];
+    for pair in ranking.windows(2) {
+        let (loser, winner) = (pair[0], pair[1]);
+        let delta = elo_change(current[loser], current[winner], k);
+        change[loser] -= delta;
+        change[winner] += delta;
+    }
+    change
+}
+
+// Running Elo ratings, starting before the first game. Agents start a point
+// apart to break ties, and K decays after every game.
+pub fn elo_history(scores: &[Vec<usize>]) -> Vec<Vec<f64>> {
+    let num_agents = scores.first().map_or(0, |r| r.len());
+    let mut elos = (0..num_agents)
+        .map(|i| ELO_INITIAL + i as f64)
+        .collect::<Vec<f64>>();
+    let mut history = vec![elos.clone()];
+    let mut k = ELO_K;
+    for row in scores {
+        let change = update_elos(&elos, &argsort(row), k);
+        elos.iter_mut().zip(change).for_each(|(e, c)| *e += c);
+        history.push(elos.clone());
+        k = (ELO_K_DECAY * k).max(ELO_K_MIN);
+    }
+    history
+}
+
+// One-sided p-value for the differences being centered above zero, using the
+// Wilcoxon signed-rank test with the normal approximation. Zero differences
+// are ranked and then dropped (Pratt's method), with a correction for ties.
+pub fn wilcoxon_greater(diffs: &[f64]) -> f64 {
+    let n = diffs.len();
+    let mut order = (0..n).collect::<Vec<usize>>();
+    order.sort_unstable_by(|&a, &b| diffs[a].abs().total_cmp(&diffs[b].abs()));
+    // Average ranks over runs of tied absolute differences.
+    let mut r_plus = 0.0;
+    let mut tie_term = 0.0;
+    let mut start = 0;
+    while start < n {
+        let abs = diffs[order[start]].abs();
+        let end = (start..n)
+            .find(|&j| diffs[order[j]].abs() != abs)
+            .unwrap_or(n);
+        let rank = (start + end + 1) as f64 / 2.0;
+        for &i in &order[start..end] {
+            if diffs[i] > 0.0 {
+                r_plus += rank;
+            }
+        }
+        // The zeros are dropped, so they don't count as ties either.
+        if abs != 0.0 {
+            let t = (end - start) as f64;
+            tie_term += t * t * t - t;
+        }
+        start = end;
+    }
+    let n = n as f64;
+    let zeros = diffs.iter().filter(|&&d| d == 0.0).count() as f64;
+    let mean = (n * (n + 1.0) - zeros * (zeros + 1.0)) / 4.0;
+    let var = (n * (n + 1.0) * (2.0 * n + 1.0) - zeros * (zeros + 1.0) * (2.0 * zeros + 1.0))
+        / 24.0
+        - tie_term / 48.0;
+    if var <= 0.0 {
+        return 0.5;
+    }
+    let z = (r_plus - mean) / var.sqrt();
+    0.5 * erfc(z / std::f64::consts::SQRT_2)
+}
+
+// Complementary error function, with fractional error below 1.2e-7.
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let poly = -z * z - 1.26551223
+        + t * (1.00002368
+            + t * (0.37409196
+                + t * (0.09678418
+                    + t * (-0.18628806
+                        + t * (0.27886807
+                            + t * (-1.13520398
+                                + t * (1.48851587 + t * (-0.82215223 + t * 0.17087277))))))));
+    let ans = t * poly.exp();
+    if x >= 0.0 {
+        ans
+    } else {
+        2.0 - ans
+    }
+}
+
+// p-values for agent 0 outscoring each of the other agents.
+pub fn p_values(scores: &[Vec<usize>]) -> Vec<f64> {
+    let num_agents = scores.first().map_or(0, |r| r.len());
+    (1..num_agents)
+        .map(|j| {
+            let diffs = scores
+                .iter()
+                .map(|row| row[0] as f64 - row[j] as f64)
+                .collect::<Vec<f64>>();
+            wilcoxon_greater(&diffs)
+        })
+        .collect()
+}
+
+fn write_summaries(out: &mut String, columns: &[Vec<f64>]) {
+    let _ = writeln!(
+        out,
+        "{:>6} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "agent", "mean", "std", "min", "25%", "50%", "75%", "max"
+    );
+    for (agent, values) in columns.iter().enumerate() {
+        let s = summarize(values);
+        let _ = writeln!(
+            out,
+            "{:>6} {:>10.2} {:>10.2} {:>10.2} {:>10.2} {:>10.2} {:>10.2} {:>10.2}",
+            agent, s.mean, s.std, s.min, s.p25, s.median, s.p75, s.max
+        );
+    }
+}
+
+fn columns(rows: &[Vec<usize>]) -> Vec<Vec<f64>> {
+    let num_agents = rows.first().map_or(0, |r| r.len());
+    (0..num_agents)
+        .map(|j| rows.iter().map(|row| row[j] as f64).collect())
+        .collect()
+}
+
+// A plain-text report covering the same ground as analyze_results.py.
+pub fn report(scores: &[Vec<usize>]) -> String {
+    let mut out = String::new();
+    if scores.is_empty() {
+        return "No games played.\n".to_owned();
+    }
+    let num_agents = scores[0].len();
+    let _ = writeln!(out, "Per game scoring ({} games)", scores.len());
+    write_summaries(&mut out, &columns(scores));
+
+    let ranks = ranks(scores);
+    let _ = writeln!(out, "\nPer game ranking (1.0 = winner)");
+    write_summaries(&mut out, &columns(&ranks));
+
+    let _ = writeln!(out, "\nRank counts");
+    let header = (1..=num_agents)
+        .map(|r| format!("{:>8}", format!("#{}", r)))
+        .collect::<String>();
+    let _ = writeln!(out, "{:>6}{}", "agent", header);
+    for (agent, counts) in rank_counts(&ranks).iter().enumerate() {
+        let row = counts
+            .iter()
+            .map(|c| format!("{:>8}", c))
+            .collect::<String>();
+        let _ = writeln!(out, "{:>6}{}", agent, row);
+    }
+
+    let p_values = p_values(scores);
+    let _ = writeln!(out, "\np-Values: {:.4?}", p_values);
+    let verdict = if p_values.iter().all(|&p| p < 0.05) {
+        "an improvement!"
+    } else if p_values.iter().all(|&p| p > 0.95) {
+        "a regression!"
+    } else {
+        "inconclusive."
+    };
+    let _ = writeln!(out, "Test condition is {}", verdict);
+
+    let _ = writeln!(out, "\nFinal Elo ratings:");
+    let final_elos = elo_history(scores).pop().unwrap();
+    let mut order = (0..num_agents).collect::<Vec<usize>>();
+    order.sort_by(|&a, &b| final_elos[b].total_cmp(&final_elos[a]));
+    for agent in order {
+        let _ = writeln!(out, "{}: {:.2}", agent, final_elos[agent]);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_values() {
+        let s = summarize(&[4.0, 1.0, 3.0, 2.0]);
+        assert_eq!(s.mean, 2.5);
+        assert_eq!(s.min, 1.0);
+        assert_eq!(s.p25, 1.75);
+        assert_eq!(s.median, 2.5);
+        assert_eq!(s.max, 4.0);
+        assert!((s.std - 1.2910).abs() < 1e-4);
+    }
+
+    #[test]
+    fn ranks_games() {
+        let scores = vec![vec![10, 30, 20], vec![5, 5, 1]];
+        let ranks = ranks(&scores);
+        assert_eq!(ranks, [[3, 1, 2], [2, 1, 3]]);
+        assert_eq!(rank_counts(&ranks), [[0, 1, 1], [2, 0, 0], [0, 1, 1]]);
+    }
+
+    #[test]
+    fn updates_elos() {
+        let change = update_elos(&[1500.0, 1501.0], &[0, 1], 20.0);
+        assert!((change[1] - 9.9712).abs() < 1e-4);
+        assert_eq!(change[0], -change[1]);
+        let history = elo_history(&[vec![10, 20], vec![10, 20]]);
+        assert_eq!(history.len(), 3);
+        assert!(history[2][1] > history[1][1]);
+    }
+
+    #[test]
+    fn wilcoxon_pratt_normal_approx() {
+        // Worked by hand: R+ = 24.5, mean = 13.5, var = 34.625, z = 1.869.
+        let p = wilcoxon_greater(&[1.0, 2.0, 3.0, 4.0, 5.0, -1.0, 0.0]);
+        assert!((p - 0.03078).abs() < 1e-4, "{}", p);
+        assert!(wilcoxon_greater(&[-1.0, -2.0, -3.0, -4.0, -5.0]) > 0.95);
+        assert_eq!(wilcoxon_greater(&[0.0, 0.0]), 0.5);
+        // Tied zeros: R+ = 48.5, mean = 30, var = 123 - 30 / 48 = 122.375,
+        // z = 1.672, matching scipy's wilcoxon with zero_method="pratt",
+        // correction=False and method="approx".
+        let p = wilcoxon_greater(&[0.0, 0.0, 0.0, 1.0, 2.0, -2.0, 3.0, -1.0, 4.0, 5.0, 2.0]);
+        assert!((p - 0.04723).abs() < 1e-4, "{}", p);
+    }
+
+    #[test]
+    fn writes_report() {
+        let scores = (0..20).map(|i| vec![100 + i, 50, 75]).collect::<Vec<_>>();
+        let text = report(&scores);
+        assert!(
+            text.contains("Test condition is an improvement!"),
+            "{}",
+            text
+        );
+        assert!(text.contains("Final Elo ratings:\n0: "), "{}", text);
+    }
+}
@@ -0,0 +1,394 @@
+// Plays many games between agents in parallel, and collects the results.
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use crate::agent::{create_seeded_agent, Agent};
+use crate::clock::{Clock, TimeControl, TimeoutAction};
+use crate::game::{default_chain_names, GameState, TurnPhase};
+
+// How agents are assigned to seats (player indices) in each game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Seating {
+    // Agent i always plays as player i.
+    Fixed,
+    // Seats are rotated by one position each game, so every agent plays from
+    // every seat equally often (given a multiple of the number of agents).
+    Rotated,
+    // Seats are shuffled independently for each game.
+    Random,
+    // Each deal is replayed once per agent, rotating the agents through every
+    // seat, so all agents face the same tiles. Game i plays deal i / n with
+    // rotation i % n, for n agents.
+    Duplicate,
+}
+
+// Makes an agent for a single game, given a seed for any randomness it uses.
+pub type AgentFactory = Box<dyn Fn(u64) -> Result<Box<dyn Agent + Send>, String> + Sync>;
+
+// A factory for the built-in agent of the given difficulty.
+pub fn builtin_agent(difficulty: usize) -> AgentFactory {
+    Box::new(move |seed| Ok(create_seeded_agent(difficulty, seed)))
+}
+
+pub struct Tournament {
+    pub agents: Vec<AgentFactory>,
+    pub num_games: usize,
+    pub seating: Seating,
+    pub num_threads: usize,
+    // Deal i is dealt from a StdRng seeded with seed + i, which then seats
+    // the agents and seeds them. Each game has its own deal, except with
+    // Seating::Duplicate.
+    pub seed: u64,
+    // Keep the initial and final states of each game in its result.
+    pub keep_states: bool,
+    // Play each game with a clock, passing the time left to the agents.
+    pub time_control: Option<TimeControl>,
+    pub on_timeout: TimeoutAction,
+}
+
+#[derive(Clone)]
+pub struct GameResult {
+    pub game_index: usize,
+    pub deal: usize,
+    // Index (into Tournament::agents) of the agent in each seat.
+    pub seats: Vec<usize>,
+    // Final value of each seat's player.
+    pub scores: Vec<usize>,
+    pub initial_state: Option<GameState>,
+    pub final_state: Option<GameState>,
+    // Number of times each seat ran out of time, in a timed game.
+    pub timeouts: Vec<usize>,
+}
+impl GameResult {
+    // Final scores, ordered by agent instead of by seat.
+    pub fn agent_scores(&self) -> Vec<usize> {
+        let mut scores = vec![0; self.seats.len()];
+        for (seat, &agent) in self.seats.iter().enumerate() {
+            scores[agent] = self.scores[seat];
+        }
+        scores
+    }
+    // Agents that finished with the highest score (more than one on a tie).
+    pub fn winners(&self) -> Vec<usize> {
+        let best = self.scores.iter().max().copied().unwrap_or(0);
+        self.seats
+            .iter()
+            .zip(&self.scores)
+            .filter(|(_, &s)| s == best)
+            .map(|(&a, _)| a)
+            .collect()
+    }
+}
+
+pub struct TournamentResults {
+    pub num_agents: usize,
+    // One result per game, in game order.
+    pub games: Vec<GameResult>,
+}
+impl TournamentResults {
+    pub fn mean_scores(&self) -> Vec<f64> {
+        let mut totals = vec![0.0; self.num_agents];
+        for result in &self.games {
+            for (total, score) in totals.iter_mut().zip(result.agent_scores()) {
+                *total += score as f64;
+            }
+        }
+        let n = self.games.len().max(1) as f64;
+        totals.into_iter().map(|t| t / n).collect()
+    }
+    // Number of games won by each agent, where ties count as a win for every
+    // agent involved.
+    pub fn wins(&self) -> Vec<usize> {
+        let mut wins = vec![0; self.num_agents];
+        for result in &self.games {
+            for agent in result.winners() {
+                wins[agent] += 1;
+            }
+        }
+        wins
+    }
+    // Total score of each agent over each deal, skipping incomplete deals.
+    // With duplicate seating, comparing these cancels out most tile luck.
+    pub fn deal_totals(&self) -> Vec<Vec<usize>> {
+        let mut totals: Vec<(Vec<usize>, usize)> = Vec::new();
+        for result in &self.games {
+            if totals.len() <= result.deal {
+                totals.resize(result.deal + 1, (vec![0; self.num_agents], 0));
+            }
+            let (deal_totals, num_games) = &mut totals[result.deal];
+            for (total, score) in deal_totals.iter_mut().zip(result.agent_scores()) {
+                *total += score;
+            }
+            *num_games += 1;
+        }
+        let games_per_deal = self.games.iter().filter(|r| r.deal == 0).count();
+        totals
+            .into_iter()
+            .filter(|(_, n)| *n == games_per_deal)
+            .map(|(t, _)| t)
+            .collect()
+    }
+}
+
+impl Tournament {
+    pub fn new(agents: Vec<AgentFactory>, num_games: usize) -> Self {
+        Self {
+            agents,
+            num_games,
+            seating: Seating::Rotated,
+            num_threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            seed: rand::random(),
+            keep_states: false,
+            time_control: None,
+            on_timeout: TimeoutAction::Passive,
+        }
+    }
+    pub fn run(&self) -> Result<TournamentResults, String> {
+        let games = self.run_games(0..self.num_games)?;
+        Ok(TournamentResults {
+            num_agents: self.agents.len(),
+            games,
+        })
+    }
+    // Plays the given range of games, in parallel, returning their results
+    // in game order.
+    pub(crate) fn run_games(&self, range: Range<usize>) -> Result<Vec<GameResult>, String> {
+        let num_agents = self.agents.len();
+        if !(2..=crate::game::MAX_NUM_PLAYERS).contains(&num_agents) {
+            return Err(format!("Invalid number of agents: {}", num_agents));
+        }
+        let next_game = AtomicUsize::new(range.start);
+        let mut games = std::thread::scope(|scope| {
+            let workers = (0..self.num_threads.max(1))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut results = Vec::new();
+                        loop {
+                            let game_index = next_game.fetch_add(1, Ordering::Relaxed);
+                            if game_index >= range.end {
+                                break;
+                            }
+                            results.push(self.play(game_index)?);
+                        }
+                        Ok(results)
+                    })
+                })
+                .collect::<Vec<_>>();
+            let mut games = Vec::with_capacity(range.len());
+            for worker in workers {
+                let results: Result<Vec<GameResult>, String> = worker.join().unwrap();
+                games.extend(results?);
+            }
+            Ok::<_, String>(games)
+        })?;
+        games.sort_unstable_by_key(|r| r.game_index);
+        Ok(games)
+    }
+    fn play(&self, game_index: usize) -> Result<GameResult, String> {
+        let n = self.agents.len();
+        let deal = match self.seating {
+            Seating::Duplicate => game_index / n,
+            _ => game_index,
+        };
+        let mut rng = StdRng::seed_from_u64(self.seed.wrapping_add(deal as u64));
+        let game = GameState::new(n, &mut rng, default_chain_names())?;
+        let mut seats = (0..n).collect::<Vec<usize>>();
+        match self.seating {
+            Seating::Fixed => {}
+            Seating::Rotated | Seating::Duplicate => seats.rotate_left(game_index % n),
+            Seating::Random => seats.shuffle(&mut rng),
+        }
+        let agents = self
+            .agents
+            .iter()
+            .map(|factory| factory(rng.gen()))
+            .collect::<Result<Vec<_>, String>>()?;
+        let seated = seats
+            .iter()
+            .map(|&a| &*agents[a] as &dyn Agent)
+            .collect::<Vec<_>>();
+        let initial_state = self.keep_states.then(|| game.clone());
+        let (scores, final_state, timeouts) = match self.time_control {
+            Some(control) => play_timed_game(game, &seated, control, self.on_timeout)?,
+            None => {
+                let (scores, final_state) = play_game(game, &seated)?;
+                (scores, final_state, vec![0; n])
+            }
+        };
+        Ok(GameResult {
+            game_index,
+            deal,
+            seats,
+            scores,
+            initial_state,
+            final_state: self.keep_states.then_some(final_state),
+            timeouts,
+        })
+    }
+}
+
+// Plays a game to completion, with agents[i] acting for player i. Returns
+// the final scores along with the final state.
+pub fn play_game(
+    mut game: GameState,
+    agents: &[&dyn Agent],
+) -> Result<(Vec<usize>, GameState), String> {
+    loop {
+        let action = agents[game.acting_player()].choose_action(&game);
+        if game.take_turn(action)? {
+            break;
+        }
+    }
+    match &game.turn_state.phase {
+        TurnPhase::GameOver(scores) => Ok((scores.clone(), game)),
+        _ => unreachable!(),
+    }
+}
+
+// Like play_game, but with a clock. Each agent is given the time left in its
+// bank as its budget, and the timeout action is played for it if it takes
+// longer. Also returns the number of times each player ran out of time.
+pub fn play_timed_game(
+    mut game: GameState,
+    agents: &[&dyn Agent],
+    control: TimeControl,
+    on_timeout: TimeoutAction,
+) -> Result<(Vec<usize>, GameState, Vec<usize>), String> {
+    let mut clock = Clock::new(&game, control, Instant::now());
+    loop {
+        let player = game.acting_player();
+        let budget = clock.remaining(player, Instant::now());
+        let action = agents[player].choose_action_within(&game, budget);
+        let now = Instant::now();
+        let over = match clock.check_timeout(&mut game, on_timeout, now)? {
+            Some(_) => matches!(game.turn_state.phase, TurnPhase::GameOver(_)),
+            None => clock.take_turn(&mut game, action, now)?,
+        };
+        if over {
+            break;
+        }
+    }
+    match &game.turn_state.phase {
+        TurnPhase::GameOver(scores) => Ok((scores.clone(), game, clock.timeouts().to_vec())),
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agents(difficulties: &[usize]) -> Vec<AgentFactory> {
+        difficulties.iter().map(|&d| builtin_agent(d)).collect()
+    }
+
+    #[test]
+    fn rotates_seats() {
+        let mut tournament = Tournament::new(agents(&[0, 1, 0]), 6);
+        tournament.num_threads = 3;
+        let results = tournament.run().unwrap();
+        assert_eq!(results.games.len(), 6);
+        for (i, result) in results.games.iter().enumerate() {
+            assert_eq!(result.game_index, i);
+            assert_eq!(result.seats[0], i % 3);
+            assert_eq!(result.scores.len(), 3);
+            assert!(result.initial_state.is_none() && result.final_state.is_none());
+            let agent_scores = result.agent_scores();
+            assert_eq!(agent_scores[result.seats[1]], result.scores[1]);
+        }
+        assert!(results.wins().iter().sum::<usize>() >= 6);
+        assert_eq!(results.mean_scores().len(), 3);
+    }
+
+    #[test]
+    fn reproduces_results() {
+        let mut tournament = Tournament::new(agents(&[0, 1, 1]), 6);
+        tournament.seating = Seating::Random;
+        let scores = || {
+            let results = tournament.run().unwrap();
+            results
+                .games
+                .into_iter()
+                .map(|r| (r.seats, r.scores))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(scores(), scores());
+    }
+
+    #[test]
+    fn shuffles_seats() {
+        let mut tournament = Tournament::new(agents(&[0, 0, 0, 0]), 8);
+        tournament.seating = Seating::Random;
+        tournament.keep_states = true;
+        for result in tournament.run().unwrap().games {
+            let mut seats = result.seats.clone();
+            seats.sort_unstable();
+            assert_eq!(seats, [0, 1, 2, 3]);
+            assert!(result.final_state.is_some());
+        }
+    }
+
+    #[test]
+    fn replays_duplicate_deals() {
+        let mut tournament = Tournament::new(agents(&[0, 1, 0]), 7);
+        tournament.seating = Seating::Duplicate;
+        tournament.keep_states = true;
+        let results = tournament.run().unwrap();
+        let deals = results.games.iter().map(|r| r.deal).collect::<Vec<_>>();
+        assert_eq!(deals, [0, 0, 0, 1, 1, 1, 2]);
+        // Every game of a deal starts from the same state: the same hands,
+        // unclaimed tiles and starting player.
+        for result in &results.games {
+            let seed = tournament.seed + result.deal as u64;
+            let deal = GameState::from_seed(3, seed, default_chain_names()).unwrap();
+            let initial = result.initial_state.as_ref().unwrap();
+            for (player, dealt) in initial.players.iter().zip(&deal.players) {
+                assert_eq!(player.tiles, dealt.tiles);
+            }
+            assert_eq!(initial.unclaimed_tiles, deal.unclaimed_tiles);
+            assert_eq!(
+                serde_json::to_string(initial).unwrap(),
+                serde_json::to_string(&deal).unwrap()
+            );
+        }
+        let totals = results.deal_totals();
+        assert_eq!(totals.len(), 2);
+        let expected = results.games[..3]
+            .iter()
+            .map(|r| r.agent_scores())
+            .fold(vec![0; 3], |acc, s| {
+                acc.iter().zip(s).map(|(a, b)| a + b).collect()
+            });
+        assert_eq!(totals[0], expected);
+    }
+
+    #[test]
+    fn plays_timed_games() {
+        let mut tournament = Tournament::new(agents(&[0, 1]), 2);
+        tournament.time_control = Some(TimeControl {
+            initial_ms: 60_000,
+            increment_ms: 0,
+        });
+        let results = tournament.run().unwrap();
+        assert!(results.games.iter().all(|r| r.timeouts == [0, 0]));
+        // With no time at all, every action is a timeout action.
+        tournament.time_control = Some(TimeControl {
+            initial_ms: 0,
+            increment_ms: 0,
+        });
+        let results = tournament.run().unwrap();
+        assert!(results
+            .games
+            .iter()
+            .all(|r| r.timeouts.iter().all(|&t| t > 0)));
+    }
+
+    #[test]
+    fn rejects_bad_agent_count() {
+        assert!(Tournament::new(agents(&[0]), 1).run().is_err());
+    }
+}
@@ -0,0 +1,138 @@
+// WebAssembly bindings, built with the "wasm" feature. Build for the browser
+// without the default features, since there's no thread-local rng there:
+//
+//   cargo build --lib --release --target wasm32-unknown-unknown \
+//     --no-default-features --features wasm
+//   wasm-bindgen --target web target/wasm32-unknown-unknown/release/acquire.wasm --out-dir pkg
+//
+// Actions, phases and views are exchanged as JSON, in the serde format of the
+// corresponding Rust types.
+use wasm_bindgen::prelude::*;
+
+use crate::agent::{create_seeded_agent, Agent};
+use crate::game::{default_chain_names, GameState, TurnAction, TurnPhase};
+use crate::{env, notation};
+
+fn to_json(value: &impl serde::Serialize) -> Result<String, JsError> {
+    serde_json::to_string(value).map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn parse_action(json: &str) -> Result<TurnAction, JsError> {
+    serde_json::from_str(json).map_err(|e| JsError::new(&e.to_string()))
+}
+
+#[wasm_bindgen]
+pub struct Game {
+    game: GameState,
+}
+
+#[wasm_bindgen]
+impl Game {
+    #[wasm_bindgen(constructor)]
+    pub fn new(num_players: usize, seed: u64) -> Result<Game, JsError> {
+        let game = GameState::from_seed(num_players, seed, default_chain_names())
+            .map_err(|e| JsError::new(&e))?;
+        Ok(Game { game })
+    }
+    // Restores a game from toJson output. Throws if the JSON isn't a
+    // consistent game state, e.g. if a player holds 26 shares.
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<Game, JsError> {
+        let game = serde_json::from_str(json).map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(Game { game })
+    }
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        to_json(&self.game)
+    }
+    // What the given player is allowed to see.
+    #[wasm_bindgen(js_name = playerViewJson)]
+    pub fn player_view_json(&self, player: usize) -> Result<String, JsError> {
+        if player >= self.game.players.len() {
+            return Err(JsError::new(&format!("Invalid player: {}", player)));
+        }
+        to_json(&self.game.player_view(player))
+    }
+    // The current TurnState: whose turn it is and the phase's payload.
+    #[wasm_bindgen(js_name = turnStateJson)]
+    pub fn turn_state_json(&self) -> Result<String, JsError> {
+        to_json(&self.game.turn_state)
+    }
+    // The name of the current phase, e.g. "BuyStock".
+    pub fn phase(&self) -> String {
+        let phase = match &self.game.turn_state.phase {
+            TurnPhase::PlaceTile(_) => "PlaceTile",
+            TurnPhase::CreateChain(_, _) => "CreateChain",
+            TurnPhase::PickWinningChain(_, _) => "PickWinningChain",
+            TurnPhase::DistributeBonuses(_, _, _) => "DistributeBonuses",
+            TurnPhase::ResolveMerger(_, _, _) => "ResolveMerger",
+            TurnPhase::BuyStock(_) => "BuyStock",
+            TurnPhase::GameOver(_) => "GameOver",
+        };
+        phase.to_owned()
+    }
+    #[wasm_bindgen(js_name = actingPlayer)]
+    pub fn acting_player(&self) -> usize {
+        self.game.acting_player()
+    }
+    #[wasm_bindgen(js_name = isOver)]
+    pub fn is_over(&self) -> bool {
+        matches!(self.game.turn_state.phase, TurnPhase::GameOver(_))
+    }
+    // Applies an action, returning whether the game is over. Invalid actions
+    // leave the game unchanged.
+    #[wasm_bindgen(js_name = takeTurn)]
+    pub fn take_turn(&mut self, action_json: &str) -> Result<bool, JsError> {
+        let action = parse_action(action_json)?;
+        self.game.take_turn(action).map_err(|e| JsError::new(&e))
+    }
+    // Checks an action without applying it. Returns the reason it's invalid,
+    // or undefined if it's valid.
+    #[wasm_bindgen(js_name = validateAction)]
+    pub fn validate_action(&self, action_json: &str) -> Option<String> {
+        let action = match serde_json::from_str(action_json) {
+            Ok(action) => action,
+            Err(e) => return Some(e.to_string()),
+        };
+        self.game.clone().take_turn(action).err()
+    }
+    // All legal actions in the env module's action space, as a JSON list.
+    #[wasm_bindgen(js_name = legalActionsJson)]
+    pub fn legal_actions_json(&self) -> Result<String, JsError> {
+        let actions = env::legal_actions(&self.game)
+            .into_iter()
+            .filter_map(env::action_from_id)
+            .collect::<Vec<TurnAction>>();
+        to_json(&actions)
+    }
+    // Describes an action in game record notation, e.g. "buy K×2".
+    #[wasm_bindgen(js_name = describeAction)]
+    pub fn describe_action(&self, action_json: &str) -> Result<String, JsError> {
+        let action = parse_action(action_json)?;
+        notation::write_action(&self.game, &action).map_err(|e| JsError::new(&e))
+    }
+    #[wasm_bindgen(js_name = toString)]
+    pub fn to_display_string(&self) -> String {
+        self.game.to_string()
+    }
+}
+
+// One of the built-in agents, seeded so it doesn't need the thread-local rng.
+#[wasm_bindgen]
+pub struct Bot {
+    agent: Box<dyn Agent + Send>,
+}
+
+#[wasm_bindgen]
+impl Bot {
+    #[wasm_bindgen(constructor)]
+    pub fn new(difficulty: usize, seed: u64) -> Bot {
+        Bot {
+            agent: create_seeded_agent(difficulty, seed),
+        }
+    }
+    #[wasm_bindgen(js_name = chooseActionJson)]
+    pub fn choose_action_json(&self, game: &Game) -> Result<String, JsError> {
+        to_json(&self.agent.choose_action(&game.game))
+    }
+}
@@ -0,0 +1,183 @@
+// Zobrist hashing of game positions, for transposition detection in search.
+//
+// GameState keeps its hash up to date as actions are taken (see
+// GameState::zobrist_hash), by XOR-ing out the key for each old value and
+// XOR-ing in the key for the new one. hash_game computes the same hash from
+// scratch.
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::OnceLock;
+
+use crate::game::{
+    GameState, GridCell, Tile, TurnPhase, TurnState, MAX_NUM_CHAINS, MAX_NUM_PLAYERS,
+    NUM_CELL_STATES, NUM_TILES, STOCKS_PER_CHAIN,
+};
+
+// Cash is hashed in $100 increments (the smallest unit of any transaction),
+// with everything above the last bucket sharing a key.
+const CASH_BUCKETS: usize = 1000;
+const NUM_PHASES: usize = 7;
+
+struct Keys {
+    cells: Vec<[u64; NUM_CELL_STATES]>,
+    chain_sizes: Vec<[u64; NUM_TILES + 1]>,
+    market: Vec<[u64; STOCKS_PER_CHAIN + 1]>,
+    holdings: Vec<[[u64; STOCKS_PER_CHAIN + 1]; MAX_NUM_CHAINS]>,
+    cash: Vec<[u64; CASH_BUCKETS]>,
+    hands: Vec<[u64; NUM_TILES]>,
+    phases: [u64; NUM_PHASES],
+    turn_players: [u64; MAX_NUM_PLAYERS],
+    merger_players: [u64; MAX_NUM_PLAYERS],
+    merger_chains: [u64; MAX_NUM_CHAINS],
+    pending_tiles: [u64; NUM_TILES],
+}
+
+fn keys() -> &'static Keys {
+    static KEYS: OnceLock<Keys> = OnceLock::new();
+    KEYS.get_or_init(|| {
+        // Fixed seed, so hashes are stable across runs.
+        let mut rng = StdRng::seed_from_u64(0x5eed_ac91_2e00_0001);
+        let mut cells = vec![[0; NUM_CELL_STATES]; NUM_TILES];
+        for keys in cells.iter_mut() {
+            // Empty cells don't contribute to the hash.
+            rng.fill(&mut keys[1..]);
+        }
+        let mut keys = Keys {
+            cells,
+            chain_sizes: vec![[0; NUM_TILES + 1]; MAX_NUM_CHAINS],
+            market: vec![[0; STOCKS_PER_CHAIN + 1]; MAX_NUM_CHAINS],
+            holdings: vec![[[0; STOCKS_PER_CHAIN + 1]; MAX_NUM_CHAINS]; MAX_NUM_PLAYERS],
+            cash: vec![[0; CASH_BUCKETS]; MAX_NUM_PLAYERS],
+            hands: vec![[0; NUM_TILES]; MAX_NUM_PLAYERS],
+            phases: rng.gen(),
+            turn_players: rng.gen(),
+            merger_players: rng.gen(),
+            merger_chains: rng.gen(),
+            pending_tiles: [0; NUM_TILES],
+        };
+        keys.chain_sizes
+            .iter_mut()
+            .for_each(|k| rng.fill(&mut k[..]));
+        keys.market.iter_mut().for_each(|k| rng.fill(&mut k[..]));
+        keys.holdings
+            .iter_mut()
+            .flatten()
+            .for_each(|k| rng.fill(&mut k[..]));
+        keys.cash.iter_mut().for_each(|k| rng.fill(&mut k[..]));
+        keys.hands.iter_mut().for_each(|k| rng.fill(&mut k[..]));
+        rng.fill(&mut keys.pending_tiles[..]);
+        keys
+    })
+}
+
+pub(crate) fn cell_key(tile: Tile, cell: GridCell) -> u64 {
+    keys().cells[tile.to_index()][cell.ordinal()]
+}
+
+pub(crate) fn chain_size_key(chain_index: usize, size: usize) -> u64 {
+    keys().chain_sizes[chain_index][size]
+}
+
+pub(crate) fn market_key(chain_index: usize, num_stocks: usize) -> u64 {
+    keys().market[chain_index][num_stocks]
+}
+
+pub(crate) fn holding_key(player: usize, chain_index: usize, num_stocks: usize) -> u64 {
+    keys().holdings[player][chain_index][num_stocks]
+}
+
+pub(crate) fn cash_key(player: usize, cash: usize) -> u64 {
+    keys().cash[player][(cash / 100).min(CASH_BUCKETS - 1)]
+}
+
+pub(crate) fn hand_key(player: usize, tile: Tile) -> u64 {
+    keys().hands[player][tile.to_index()]
+}
+
+// Covers the phase and whose turn it is, plus the parts of the phase payload
+// that can't be derived from the rest of the position.
+pub(crate) fn turn_key(turn_state: &TurnState) -> u64 {
+    let keys = keys();
+    let mut key = keys.turn_players[turn_state.player];
+    key ^= match &turn_state.phase {
+        TurnPhase::PlaceTile(_) => keys.phases[0],
+        TurnPhase::CreateChain(tile, _) => keys.phases[1] ^ keys.pending_tiles[tile.to_index()],
+        TurnPhase::PickWinningChain(_, _) => keys.phases[2],
+        TurnPhase::DistributeBonuses(_, chains, _) => {
+            keys.phases[3] ^ keys.merger_chains[chains[0]]
+        }
+        TurnPhase::ResolveMerger(_, chains, player) => {
+            keys.phases[4] ^ keys.merger_chains[chains[0]] ^ keys.merger_players[*player]
+        }
+        TurnPhase::BuyStock(_) => keys.phases[5],
+        TurnPhase::GameOver(_) => keys.phases[6],
+    };
+    key
+}
+
+// Computes the hash of a position from scratch.
+pub fn hash_game(game: &GameState) -> u64 {
+    let mut hash = 0;
+    for (row, cells) in game.board.grid.iter().enumerate() {
+        for (col, &cell) in cells.iter().enumerate() {
+            hash ^= cell_key(Tile(row, col), cell);
+        }
+    }
+    for i in 0..MAX_NUM_CHAINS {
+        hash ^= chain_size_key(i, game.board.chain_sizes[i]);
+        hash ^= market_key(i, game.board.stock_market[i]);
+    }
+    for (p, player) in game.players.iter().enumerate() {
+        for (i, &num_stocks) in player.stocks.iter().enumerate() {
+            hash ^= holding_key(p, i, num_stocks);
+        }
+        hash ^= cash_key(p, player.cash);
+        for &tile in &player.tiles {
+            hash ^= hand_key(p, tile);
+        }
+    }
+    hash ^ turn_key(&game.turn_state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::create_agent;
+    use crate::game::{default_chain_names, TurnAction};
+
+    #[test]
+    fn incremental_matches_full_hash() {
+        for num_players in 2..=MAX_NUM_PLAYERS {
+            let mut game =
+                GameState::new(num_players, &mut rand::thread_rng(), default_chain_names())
+                    .unwrap();
+            let ai = create_agent(0);
+            loop {
+                assert_eq!(game.zobrist_hash(), hash_game(&game), "{}", game);
+                if game.take_turn(ai.choose_action(&game)).unwrap() {
+                    break;
+                }
+            }
+            assert_eq!(game.zobrist_hash(), hash_game(&game), "{}", game);
+        }
+    }
+
+    #[test]
+    fn failed_actions_keep_hash() {
+        let mut game = GameState::new(3, &mut rand::thread_rng(), default_chain_names()).unwrap();
+        let hash = game.zobrist_hash();
+        assert!(game.take_turn(TurnAction::AcceptBonus).is_err());
+        assert!(game.take_turn(TurnAction::PlaceTile(9)).is_err());
+        assert_eq!(game.zobrist_hash(), hash);
+    }
+
+    #[test]
+    fn distinguishes_positions() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let game = GameState::new(4, &mut rng, default_chain_names()).unwrap();
+        let mut other = game.clone();
+        assert_eq!(other.zobrist_hash(), game.zobrist_hash());
+        other.take_turn(TurnAction::PlaceTile(0)).unwrap();
+        assert_ne!(other.zobrist_hash(), game.zobrist_hash());
+    }
+}